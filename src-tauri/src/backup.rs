@@ -0,0 +1,536 @@
+use crate::crypto::{self, VaultKey};
+use crate::history::content_hash;
+use crate::links::normalize_path;
+use crate::storage::write_atomic;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+/// Largest file read back from an archive; anything bigger is not something we wrote
+const MAX_ENTRY_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Sections written by older versions that are no longer restored
+const RETIRED_SECTIONS: &[&str] = &["session.json"];
+
+/// Settings left out of archives because they hold credentials
+const SECRET_SETTINGS: &[&str] = &["webdav_password"];
+
+/// Where each part of the app state lives on disk
+pub struct BackupLocations {
+    pub notes: Option<PathBuf>,
+    pub settings: PathBuf,
+    pub templates: PathBuf,
+    pub history: PathBuf,
+    /// The wrapped vault key, already protected by the vault passphrase
+    pub vault_key: PathBuf,
+    /// Folder scheduled backups are written to, left out so archives never contain each other
+    pub backups: Option<PathBuf>,
+}
+
+impl BackupLocations {
+    /// Pairs each archive prefix with the file or folder it is read from and restored to
+    fn sections(&self) -> Vec<(&'static str, PathBuf)> {
+        let mut sections = Vec::new();
+        if let Some(notes) = &self.notes {
+            sections.push(("notes", notes.clone()));
+        }
+        sections.push(("settings.json", self.settings.clone()));
+        sections.push(("templates", self.templates.clone()));
+        sections.push(("history", self.history.clone()));
+        sections.push(("vault.key", self.vault_key.clone()));
+        sections
+    }
+
+    /// Maps an archive path back to a local path
+    fn local_path(&self, archive_path: &str) -> Option<PathBuf> {
+        let (prefix, rest) = match archive_path.split_once('/') {
+            Some((prefix, rest)) => (prefix, Some(rest)),
+            None => (archive_path, None),
+        };
+        let (_, base) = self
+            .sections()
+            .into_iter()
+            .find(|(name, _)| *name == prefix)?;
+        match rest {
+            Some(rest) => safe_join(&base, rest),
+            None => Some(base),
+        }
+    }
+}
+
+/// One file stored in the archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub version: u32,
+    pub app_version: String,
+    /// RFC 3339 timestamp of when the backup was made
+    pub created_at: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+    pub path: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// What to do when a restored file already exists with different content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    /// Keep the existing file and restore next to it under a new name
+    KeepBoth,
+}
+
+impl ConflictPolicy {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "keep_both" => Ok(ConflictPolicy::KeepBoth),
+            other => Err(anyhow!("Unknown conflict policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    pub restored: Vec<String>,
+    /// Files that already existed with the same content
+    pub unchanged: Vec<String>,
+    /// Existing files left alone because of the conflict policy
+    pub skipped: Vec<String>,
+    /// Existing files that were replaced
+    pub overwritten: Vec<String>,
+    /// Archive path and the new local path for files restored next to an existing one
+    pub renamed: Vec<(String, String)>,
+}
+
+/// Section an archive path belongs to, e.g. "notes" for "notes/sub/one.md"
+fn section_of(archive_path: &str) -> &str {
+    archive_path.split('/').next().unwrap_or(archive_path)
+}
+
+/// Drops credentials from settings.json; None if the file cannot be parsed, since it might
+/// still hold them
+fn redact_settings(data: &[u8]) -> Option<Vec<u8>> {
+    let mut settings: serde_json::Value = serde_json::from_slice(data).ok()?;
+    let object = settings.as_object_mut()?;
+    for key in SECRET_SETTINGS {
+        object.remove(*key);
+    }
+    serde_json::to_vec_pretty(&settings).ok()
+}
+
+/// Carries the credentials of the current settings over into restored ones
+fn keep_secret_settings(restored: &[u8], current: &[u8]) -> Vec<u8> {
+    let (Ok(mut restored_json), Ok(current_json)) = (
+        serde_json::from_slice::<serde_json::Value>(restored),
+        serde_json::from_slice::<serde_json::Value>(current),
+    ) else {
+        return restored.to_vec();
+    };
+    if let (Some(restored_object), Some(current_object)) =
+        (restored_json.as_object_mut(), current_json.as_object())
+    {
+        for key in SECRET_SETTINGS {
+            if let Some(value) = current_object.get(*key) {
+                restored_object.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+    serde_json::to_vec_pretty(&restored_json).unwrap_or_else(|_| restored.to_vec())
+}
+
+/// Joins a relative archive path onto `base`, rejecting anything that could escape it
+fn safe_join(base: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = base.to_path_buf();
+    for part in relative.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return None;
+        }
+        path.push(part);
+    }
+    Some(path)
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// True for archives written by the scheduler, finished or not
+fn is_auto_backup(name: &str) -> bool {
+    name.starts_with(AUTO_BACKUP_PREFIX)
+        && (name.ends_with(".zip") || name.ends_with(".zip.partial"))
+}
+
+/// Lists the files of a section as (archive path, local path) pairs, skipping hidden ones,
+/// scheduled backups and the `skip_dir` folder
+fn section_files(
+    prefix: &str,
+    base: &Path,
+    exclude: &[&Path],
+    skip_dir: Option<&Path>,
+) -> Vec<(String, PathBuf)> {
+    if base.is_file() {
+        return vec![(prefix.to_string(), base.to_path_buf())];
+    }
+    if !base.is_dir() {
+        return Vec::new();
+    }
+    WalkDir::new(base)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !(is_hidden(&entry.file_name().to_string_lossy())
+                    || skip_dir.is_some_and(|dir| normalize_path(entry.path()) == dir))
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && !exclude.contains(&entry.path())
+                && !is_auto_backup(&entry.file_name().to_string_lossy())
+        })
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(base).ok()?;
+            let parts: Vec<String> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().to_string())
+                .collect();
+            Some((
+                format!("{}/{}", prefix, parts.join("/")),
+                entry.path().to_path_buf(),
+            ))
+        })
+        .collect()
+}
+
+/// Writes every section and the manifest into a zip file
+fn write_archive(
+    locations: &BackupLocations,
+    file: File,
+    exclude: &[&Path],
+    app_version: &str,
+) -> Result<BackupManifest> {
+    let mut writer = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let skip_dir = locations.backups.as_deref().map(normalize_path);
+    let mut entries = Vec::new();
+    for (prefix, base) in locations.sections() {
+        for (archive_path, local_path) in section_files(prefix, &base, exclude, skip_dir.as_deref())
+        {
+            let mut data = std::fs::read(&local_path)
+                .with_context(|| format!("Failed to read {}", local_path.display()))?;
+            if prefix == "settings.json" {
+                data = match redact_settings(&data) {
+                    Some(data) => data,
+                    None => {
+                        eprintln!(
+                            "Leaving unreadable {} out of the backup",
+                            local_path.display()
+                        );
+                        continue;
+                    }
+                };
+            }
+            writer.start_file(archive_path.as_str(), options)?;
+            writer.write_all(&data)?;
+            entries.push(ManifestEntry {
+                path: archive_path,
+                size: data.len() as u64,
+                sha256: content_hash(&data),
+            });
+        }
+    }
+
+    let manifest = BackupManifest {
+        version: MANIFEST_VERSION,
+        app_version: app_version.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        entries,
+    };
+    writer.start_file(MANIFEST_FILE, options)?;
+    writer.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    writer.finish()?;
+    Ok(manifest)
+}
+
+/// Creates a zip backup at `target` with a checksummed manifest
+pub fn create_backup(
+    locations: &BackupLocations,
+    target: &Path,
+    app_version: &str,
+) -> Result<BackupSummary> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // Write to a temporary name so a failed backup never leaves a truncated archive behind
+    let partial = target.with_extension("zip.partial");
+    let file = File::create(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    let manifest = match write_archive(locations, file, &[target, &partial], app_version) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+    std::fs::rename(&partial, target)?;
+
+    Ok(BackupSummary {
+        path: target.to_string_lossy().to_string(),
+        files: manifest.entries.len(),
+        bytes: manifest.entries.iter().map(|entry| entry.size).sum(),
+    })
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>> {
+    let mut entry = archive
+        .by_name(name)
+        .with_context(|| format!("Backup is missing {}", name))?;
+    if entry.size() > MAX_ENTRY_SIZE {
+        return Err(anyhow!("{} is too large to restore", name));
+    }
+    // The size in the header is not trusted; never read more than the limit
+    let mut data = Vec::new();
+    entry
+        .by_ref()
+        .take(MAX_ENTRY_SIZE + 1)
+        .read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_ENTRY_SIZE {
+        return Err(anyhow!("{} is too large to restore", name));
+    }
+    Ok(data)
+}
+
+/// Opens a backup and checks every file against the manifest before anything is restored
+pub fn read_manifest(source: &Path) -> Result<(ZipArchive<File>, BackupManifest)> {
+    let file =
+        File::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    let mut archive = ZipArchive::new(file).context("Not a valid backup archive")?;
+    let manifest: BackupManifest =
+        serde_json::from_slice(&read_entry(&mut archive, MANIFEST_FILE)?)
+            .context("Backup manifest is corrupted")?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(anyhow!(
+            "Backup was made by a newer version of QuickNote ({})",
+            manifest.app_version
+        ));
+    }
+
+    for entry in &manifest.entries {
+        let data = read_entry(&mut archive, &entry.path)?;
+        if data.len() as u64 != entry.size || content_hash(&data) != entry.sha256 {
+            return Err(anyhow!("Checksum mismatch for {}", entry.path));
+        }
+    }
+    Ok((archive, manifest))
+}
+
+/// Finds a free name next to `path`, e.g. "note (restored).md" or "note (restored 2).md"
+fn restored_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut counter = 1;
+    loop {
+        let suffix = if counter == 1 {
+            "restored".to_string()
+        } else {
+            format!("restored {}", counter)
+        };
+        let candidate = path.with_file_name(format!("{} ({}){}", stem, suffix, extension));
+        if !candidate.exists() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// True if an existing file already holds what the archive entry would restore
+fn is_unchanged(section: &str, existing: &[u8], data: &[u8], vault: Option<&VaultKey>) -> bool {
+    if content_hash(existing) == content_hash(data) {
+        return true;
+    }
+    match section {
+        "settings.json" => redact_settings(existing).as_deref() == Some(data),
+        // Sealing is randomised, so sealed notes are compared by their plaintext
+        "notes" if crypto::is_sealed(existing) => vault
+            .and_then(|key| crypto::unseal(key, existing).ok())
+            .is_some_and(|plaintext| plaintext.as_slice() == data),
+        _ => false,
+    }
+}
+
+/// Validates a backup and restores its files into `locations`.
+/// Notes are sealed as they are written when `vault` is given.
+pub fn restore_backup(
+    locations: &BackupLocations,
+    source: &Path,
+    policy: ConflictPolicy,
+    vault: Option<&VaultKey>,
+) -> Result<RestoreReport> {
+    let (mut archive, manifest) = read_manifest(source)?;
+    let entries: Vec<&ManifestEntry> = manifest
+        .entries
+        .iter()
+        .filter(|entry| !RETIRED_SECTIONS.contains(&section_of(&entry.path)))
+        .collect();
+
+    // Resolve every destination up front so an unusable archive restores nothing
+    let mut targets = HashMap::new();
+    for entry in &entries {
+        let local = locations.local_path(&entry.path).ok_or_else(|| {
+            if entry.path.starts_with("notes/") && locations.notes.is_none() {
+                anyhow!("No notes directory configured to restore notes into")
+            } else {
+                anyhow!("Backup contains an invalid path: {}", entry.path)
+            }
+        })?;
+        targets.insert(entry.path.as_str(), local);
+    }
+
+    let mut report = RestoreReport::default();
+    for entry in entries {
+        let section = section_of(&entry.path);
+        let mut data = read_entry(&mut archive, &entry.path)?;
+        let mut local = targets[entry.path.as_str()].clone();
+        // Copies of settings or history under another name would never be read. A replaced
+        // vault key could lock the current vault for good, so that copy is kept instead.
+        let policy = match policy {
+            ConflictPolicy::KeepBoth
+                if section != "notes" && section != "templates" && section != "vault.key" =>
+            {
+                ConflictPolicy::Overwrite
+            }
+            policy => policy,
+        };
+
+        if local.exists() {
+            let existing = std::fs::read(&local)?;
+            if is_unchanged(section, &existing, &data, vault) {
+                report.unchanged.push(entry.path.clone());
+                continue;
+            }
+            if section == "settings.json" {
+                data = keep_secret_settings(&data, &existing);
+            }
+            match policy {
+                ConflictPolicy::Skip => {
+                    report.skipped.push(entry.path.clone());
+                    continue;
+                }
+                ConflictPolicy::Overwrite => report.overwritten.push(entry.path.clone()),
+                ConflictPolicy::KeepBoth => {
+                    local = restored_name(&local);
+                    report
+                        .renamed
+                        .push((entry.path.clone(), local.to_string_lossy().to_string()));
+                }
+            }
+        } else {
+            report.restored.push(entry.path.clone());
+        }
+
+        if let Some(parent) = local.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Notes backed up from outside the vault are sealed on their way in
+        if let Some(key) = vault.filter(|_| section == "notes") {
+            if !crypto::is_sealed(&data) && !crypto::is_encrypted(&data) {
+                data = crypto::seal(key, &data)?;
+            }
+        }
+        write_atomic(&local, &data)
+            .with_context(|| format!("Failed to write {}", local.display()))?;
+    }
+    Ok(report)
+}
+
+/// File name prefix of archives written by the scheduler; only these are rotated
+pub const AUTO_BACKUP_PREFIX: &str = "QuickNote Auto Backup ";
+
+/// Name for a scheduled backup made at `now`
+pub fn auto_backup_name(now: &chrono::DateTime<chrono::Local>) -> String {
+    format!(
+        "{}{}.zip",
+        AUTO_BACKUP_PREFIX,
+        now.format("%Y-%m-%d %H%M%S")
+    )
+}
+
+/// Lists scheduled backups in `dir` with their modification times, newest first
+pub fn list_auto_backups(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut backups: Vec<(PathBuf, SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with(AUTO_BACKUP_PREFIX) && name.ends_with(".zip")
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((entry.path(), modified))
+        })
+        .collect();
+    backups.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    backups
+}
+
+/// Deletes scheduled backups beyond the newest `keep` or older than `max_age_days`
+///
+/// A limit of 0 disables that rule; the newest backup is never deleted.
+pub fn rotate_backups(
+    dir: &Path,
+    keep: usize,
+    max_age_days: u32,
+    now: SystemTime,
+) -> Result<Vec<PathBuf>> {
+    let max_age = Duration::from_secs(u64::from(max_age_days) * 24 * 60 * 60);
+    let mut removed = Vec::new();
+    for (index, (path, modified)) in list_auto_backups(dir).into_iter().enumerate() {
+        if index == 0 {
+            continue;
+        }
+        let too_many = keep > 0 && index >= keep;
+        let too_old = max_age_days > 0
+            && now
+                .duration_since(modified)
+                .map(|age| age > max_age)
+                .unwrap_or(false);
+        if too_many || too_old {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// Header format of timestamped entries, e.g. "## 2026-10-19 14:03"
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// How an appended fragment is laid out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendOptions {
+    /// Puts a "## <date time>" header above the text
+    pub timestamp: bool,
+    /// Line written between the existing content and the new entry, e.g. "---"
+    pub separator: Option<String>,
+}
+
+/// A fragment to append, as sent by the webview, the CLI or the local API
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppendRequest {
+    pub text: String,
+    /// Target file; the capture note from settings when neither this nor `tab_id` is given
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Open tab to append to instead of a file
+    #[serde(default)]
+    pub tab_id: Option<String>,
+    /// Overrides the timestamp setting
+    #[serde(default)]
+    pub timestamp: Option<bool>,
+    /// Overrides the separator setting
+    #[serde(default)]
+    pub separator: Option<String>,
+}
+
+/// Formats the fragment itself: the optional timestamp header followed by the text
+pub fn format_entry(text: &str, options: &AppendOptions, now: DateTime<Local>) -> String {
+    let text = text.trim_end_matches(['\r', '\n']);
+    if options.timestamp {
+        format!("## {}\n\n{}\n", now.format(TIMESTAMP_FORMAT), text)
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+/// Appends an entry to `existing`, starting it on a fresh line below the separator
+pub fn append_entry(existing: &str, entry: &str, options: &AppendOptions) -> String {
+    let mut content = existing.to_string();
+    if !content.is_empty() {
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+        let separator = options.separator.as_deref().unwrap_or("");
+        if !separator.is_empty() {
+            content.push('\n');
+            content.push_str(separator);
+            content.push_str("\n\n");
+        } else if options.timestamp {
+            // Keep a blank line above a timestamp header so it renders as a heading
+            content.push('\n');
+        }
+    }
+    content.push_str(entry);
+    content
+}
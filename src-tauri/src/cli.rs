@@ -0,0 +1,191 @@
+use crate::capture::AppendRequest;
+use crate::search;
+use crate::url_scheme;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Most hits printed by `--search`
+const MAX_SEARCH_HITS: usize = 200;
+
+pub const USAGE: &str = "Usage: quicknote [OPTIONS] [FILE]...
+
+Opens each FILE as a tab; `-` opens standard input as a new note.
+FILE may also be a quicknote:// link, e.g. quicknote://open?path=/notes/todo.md
+
+Options:
+  -n, --new            Create a new note
+      --append         Append standard input to the capture note
+      --to <FILE>      Append to FILE instead of the capture note
+      --line <N>       Put the cursor on line N of the opened files
+      --search <TERM>  Print the notes that contain TERM and exit
+  -h, --help           Print this help and exit
+  -V, --version        Print the version and exit";
+
+/// Parsed command-line arguments
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Cli {
+    /// Absolute paths of the files to open
+    pub files: Vec<PathBuf>,
+    /// quicknote:// links, handed over by the OS or typed by hand
+    pub urls: Vec<String>,
+    pub new_note: bool,
+    /// `-` was given, so standard input becomes a new note
+    pub stdin: bool,
+    /// Standard input is appended to a note instead of opening a tab
+    pub append: bool,
+    pub append_to: Option<PathBuf>,
+    pub search: Option<String>,
+    pub line: Option<usize>,
+    pub help: bool,
+    pub version: bool,
+}
+
+/// What a launch asks the window to do; also forwarded between instances
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchRequest {
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub urls: Vec<String>,
+    pub new_note: bool,
+    /// Content for a new, unsaved tab, e.g. read from standard input
+    pub text: Option<String>,
+    pub line: Option<usize>,
+    /// Fragment to append without bringing the window forward
+    #[serde(default)]
+    pub append: Option<AppendRequest>,
+}
+
+impl LaunchRequest {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+            && self.urls.is_empty()
+            && !self.new_note
+            && self.text.is_none()
+            && self.append.is_none()
+    }
+}
+
+fn option_value(
+    name: &str,
+    inline: Option<String>,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String> {
+    inline
+        .or_else(|| args.next())
+        .ok_or_else(|| anyhow!("{} needs a value", name))
+}
+
+/// Parses the arguments after the program name; relative file paths are resolved against `cwd`
+pub fn parse(args: impl IntoIterator<Item = String>, cwd: &Path) -> Result<Cli> {
+    let mut cli = Cli::default();
+    let mut args = args.into_iter();
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        if only_files || arg == "-" || !arg.starts_with('-') {
+            if arg == "-" && !only_files {
+                cli.stdin = true;
+            } else if !only_files && url_scheme::is_url(&arg) {
+                cli.urls.push(arg);
+            } else {
+                cli.files.push(cwd.join(&arg));
+            }
+            continue;
+        }
+        // macOS passes a process serial number to apps opened from Finder
+        if arg.starts_with("-psn_") {
+            continue;
+        }
+
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                (name.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        match name.as_str() {
+            "--" => only_files = true,
+            "-n" | "--new" => cli.new_note = true,
+            "--append" => cli.append = true,
+            "--to" => cli.append_to = Some(cwd.join(option_value(&name, inline, &mut args)?)),
+            "-h" | "--help" => cli.help = true,
+            "-V" | "--version" => cli.version = true,
+            "--search" => cli.search = Some(option_value(&name, inline, &mut args)?),
+            "--line" => {
+                let value = option_value(&name, inline, &mut args)?;
+                let line = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|line| *line > 0)
+                    .ok_or_else(|| anyhow!("--line expects a positive number, got '{}'", value))?;
+                cli.line = Some(line);
+            }
+            _ => return Err(anyhow!("Unknown option: {}", arg)),
+        }
+    }
+    if cli.append && cli.stdin {
+        return Err(anyhow!("`-` and --append both read standard input"));
+    }
+    if cli.append_to.is_some() && !cli.append {
+        return Err(anyhow!("--to only works with --append"));
+    }
+    Ok(cli)
+}
+
+impl Cli {
+    /// Turns the arguments into a request for the window, reading standard input if asked to
+    pub fn into_request(self) -> Result<LaunchRequest> {
+        let mut text = if self.stdin || self.append {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            Some(text)
+        } else {
+            None
+        };
+        let append = if self.append {
+            Some(AppendRequest {
+                text: text.take().unwrap_or_default(),
+                path: self
+                    .append_to
+                    .map(|path| path.to_string_lossy().to_string()),
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+        Ok(LaunchRequest {
+            files: self
+                .files
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            urls: self.urls,
+            new_note: self.new_note,
+            text,
+            line: self.line,
+            append,
+        })
+    }
+}
+
+/// Prints `path:line: text` for each hit and returns the exit code, 1 when nothing matched
+pub fn print_search(root: Option<&Path>, query: &str) -> i32 {
+    let root = match root {
+        Some(root) => root,
+        None => {
+            eprintln!("No notes directory configured");
+            return 2;
+        }
+    };
+    let hits = search::search_notes(root, query, MAX_SEARCH_HITS, None);
+    for hit in &hits {
+        println!("{}:{}: {}", hit.path, hit.line, hit.text);
+    }
+    if hits.is_empty() {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,197 @@
+use crate::storage::{load_state, write_private};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Longer copies, such as whole files, are not kept
+pub const MAX_ENTRY_BYTES: usize = 64 * 1024;
+
+/// Prefixes of well-known API tokens and keys
+const TOKEN_PREFIXES: &[&str] = &[
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "glpat-",
+    "sk-",
+    "sk_live_",
+    "rk_live_",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "AIza",
+];
+
+/// One copied text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipEntry {
+    pub id: u64,
+    pub text: String,
+    /// Milliseconds since the Unix epoch
+    pub copied_at: i64,
+    /// Pinned entries are listed first and never dropped to make room
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Copied texts, newest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipboardHistory {
+    next_id: u64,
+    entries: Vec<ClipEntry>,
+}
+
+impl ClipboardHistory {
+    /// Records a copy, moving an earlier copy of the same text to the top.
+    /// Returns false if the text was not recorded.
+    pub fn record(&mut self, text: &str, now: i64, limit: usize) -> bool {
+        if text.trim().is_empty() || text.len() > MAX_ENTRY_BYTES {
+            return false;
+        }
+        if self.entries.first().is_some_and(|entry| entry.text == text) {
+            return false;
+        }
+        let pinned = match self.entries.iter().position(|entry| entry.text == text) {
+            Some(index) => self.entries.remove(index).pinned,
+            None => false,
+        };
+        self.next_id += 1;
+        self.entries.insert(
+            0,
+            ClipEntry {
+                id: self.next_id,
+                text: text.to_string(),
+                copied_at: now,
+                pinned,
+            },
+        );
+        self.trim(limit);
+        true
+    }
+
+    /// Drops the oldest unpinned entries beyond `limit`
+    pub fn trim(&mut self, limit: usize) {
+        let mut kept = 0;
+        self.entries.retain(|entry| {
+            if entry.pinned {
+                return true;
+            }
+            kept += 1;
+            kept <= limit
+        });
+    }
+
+    /// Pinned entries first, then the rest, each newest first
+    pub fn list(&self, limit: Option<usize>) -> Vec<ClipEntry> {
+        let pinned = self.entries.iter().filter(|entry| entry.pinned);
+        let unpinned = self.entries.iter().filter(|entry| !entry.pinned);
+        pinned
+            .chain(unpinned)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Entries containing every word of the query, ignoring case
+    pub fn search(&self, query: &str, limit: Option<usize>) -> Vec<ClipEntry> {
+        let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+        let mut hits = self.list(None);
+        hits.retain(|entry| {
+            let text = entry.text.to_lowercase();
+            words.iter().all(|word| text.contains(word.as_str()))
+        });
+        hits.truncate(limit.unwrap_or(usize::MAX));
+        hits
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ClipEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Returns false if there is no entry with that id
+    pub fn set_pinned(&mut self, id: u64, pinned: bool) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns false if there is no entry with that id
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.len() != before
+    }
+
+    /// Forgets everything except pinned entries
+    pub fn clear(&mut self) {
+        self.entries.retain(|entry| entry.pinned);
+    }
+}
+
+/// Counts lower case, upper case, digits and symbols; punctuation common in
+/// identifiers and version numbers is not counted as a symbol
+fn char_classes(text: &str) -> usize {
+    let lower = text.chars().any(|c| c.is_lowercase());
+    let upper = text.chars().any(|c| c.is_uppercase());
+    let digit = text.chars().any(|c| c.is_ascii_digit());
+    let symbol = text
+        .chars()
+        .any(|c| !c.is_alphanumeric() && !matches!(c, '-' | '_' | '.' | ':'));
+    [lower, upper, digit, symbol]
+        .iter()
+        .filter(|present| **present)
+        .count()
+}
+
+/// Guesses whether a copied text is a password, token or private key, which are never recorded
+pub fn looks_like_secret(text: &str) -> bool {
+    if text.contains("-----BEGIN") && text.contains("PRIVATE KEY") {
+        return true;
+    }
+    let text = text.trim();
+    // Passwords and tokens are one word; URLs, paths and addresses are not secrets
+    if text.is_empty()
+        || text.chars().any(char::is_whitespace)
+        || text.contains("://")
+        || text.starts_with(['/', '~', '.'])
+        || text.contains('\\')
+    {
+        return false;
+    }
+    if TOKEN_PREFIXES
+        .iter()
+        .any(|prefix| text.starts_with(prefix) && text.len() >= prefix.len() + 16)
+    {
+        return true;
+    }
+    // JSON Web Tokens
+    if text.starts_with("eyJ") && text.matches('.').count() == 2 {
+        return true;
+    }
+
+    let length = text.chars().count();
+    if (8..=64).contains(&length) && char_classes(text) >= 3 && !text.contains('@') {
+        return true;
+    }
+    // Long random-looking keys, e.g. hex or base64
+    length >= 24
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '=' | '_' | '-'))
+        && text.chars().any(|c| c.is_ascii_digit())
+        && text.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// Loads the history, starting empty if it does not exist yet
+pub fn load(path: &Path) -> Result<ClipboardHistory> {
+    load_state(path)
+}
+
+/// Saves the history readable only by the current user
+pub fn save(path: &Path, history: &ClipboardHistory) -> Result<()> {
+    write_private(path, serde_json::to_string(history)?.as_bytes())
+}
@@ -0,0 +1,219 @@
+use crate::commands::settings_commands::{self, get_settings_path};
+use crate::commands::{capture_commands, search_commands, vault_commands};
+use crate::crypto;
+use crate::links;
+use crate::local_api::{ApiError, ApiRequest, ApiServer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+use tauri::Manager;
+
+/// How long an API request waits for the window to answer
+const FRONTEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    // The running local API server, if it is enabled
+    static ref SERVER: Mutex<Option<ApiServer>> = Mutex::new(None);
+    // Requests waiting for the frontend, by id
+    static ref PENDING: Mutex<HashMap<u64, mpsc::Sender<Value>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Port and token of the local API, written for scripts to read
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiInfo {
+    pub port: u16,
+    pub token: String,
+}
+
+fn api_info_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    get_settings_path(app)
+        .map(|settings| settings.with_file_name("local-api.json"))
+        .map_err(|e| e.to_string())
+}
+
+/// Keeps the token of an earlier run so scripts do not need a new one after every restart
+fn load_token(path: &Path) -> String {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<ApiInfo>(&content).ok())
+        .map(|info| info.token)
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(crypto::random_token)
+}
+
+/// Writes the token readable by the user only, so it is never briefly open to others
+fn write_info(path: &Path, info: &ApiInfo) -> anyhow::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files, so tighten one left by an older version
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options
+        .open(path)?
+        .write_all(serde_json::to_string_pretty(info)?.as_bytes())?;
+    Ok(())
+}
+
+/// Asks the webview for something only it knows, such as the open tabs
+pub fn ask_frontend(app: &tauri::AppHandle, kind: &str, args: Value) -> Result<Value, ApiError> {
+    let window = app
+        .get_window("main")
+        .ok_or_else(|| ApiError::new(503, "The QuickNote window is not available"))?;
+    let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, receiver) = mpsc::channel();
+    PENDING.lock().unwrap().insert(id, sender);
+
+    let sent = window.emit(
+        "api-request",
+        json!({ "id": id, "kind": kind, "args": args }),
+    );
+    let reply = sent
+        .ok()
+        .and_then(|_| receiver.recv_timeout(FRONTEND_TIMEOUT).ok());
+    PENDING.lock().unwrap().remove(&id);
+    reply.ok_or_else(|| ApiError::new(504, "The QuickNote window did not answer"))
+}
+
+/// Only note files inside the notes root can be changed through the API
+async fn note_in_root(app: &tauri::AppHandle, path: &str) -> Result<PathBuf, ApiError> {
+    let root = settings_commands::require_notes_root(app)
+        .await
+        .map_err(|e| ApiError::new(409, e))?;
+    let path = links::normalize_path(Path::new(path));
+    if !path.is_absolute() || !path.starts_with(links::normalize_path(&root)) {
+        return Err(ApiError::new(
+            403,
+            "Only notes inside the notes directory can be changed",
+        ));
+    }
+    if !links::is_note_file(&path) {
+        return Err(ApiError::new(400, "Not a note file"));
+    }
+    Ok(path)
+}
+
+async fn handle(app: &tauri::AppHandle, request: ApiRequest) -> Result<Value, ApiError> {
+    match request {
+        ApiRequest::CreateNote { title, content } => ask_frontend(
+            app,
+            "create_note",
+            json!({ "title": title, "content": content }),
+        ),
+        ApiRequest::AppendToNote(mut append) => {
+            if let Some(path) = &append.path {
+                append.path = Some(note_in_root(app, path).await?.to_string_lossy().to_string());
+            }
+            let outcome = capture_commands::append(app, append)
+                .await
+                .map_err(|e| ApiError::new(500, e))?;
+            Ok(json!(outcome))
+        }
+        ApiRequest::ListTabs => ask_frontend(app, "list_tabs", Value::Null),
+        ApiRequest::GetTab { id } => match ask_frontend(app, "get_tab", json!({ "id": id }))? {
+            Value::Null => Err(ApiError::new(404, "No tab with that id")),
+            tab => {
+                // Encrypted notes only ever leave the app through the editor
+                if let Some(path) = tab["path"].as_str() {
+                    if vault_commands::is_protected(app, path).await {
+                        return Err(ApiError::new(403, "The note in this tab is encrypted"));
+                    }
+                }
+                Ok(tab)
+            }
+        },
+        ApiRequest::Search { query, limit } => {
+            let hits = search_commands::search(app, &query, limit, false)
+                .await
+                .map_err(|e| ApiError::new(500, e))?;
+            // Vault notes not sealed yet are still plaintext on disk
+            let mut visible = Vec::with_capacity(hits.len());
+            for hit in hits {
+                if !vault_commands::is_protected(app, &hit.path).await {
+                    visible.push(hit);
+                }
+            }
+            Ok(json!(visible))
+        }
+    }
+}
+
+/// Starts, restarts or stops the local API to match the settings
+pub async fn apply_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let mut server = SERVER.lock().unwrap();
+
+    let wanted = settings.local_api_enabled;
+    let port_changed = server.as_ref().is_some_and(|running| {
+        settings.local_api_port != 0 && running.port() != settings.local_api_port
+    });
+    if let Some(running) = server.as_ref() {
+        if !wanted || port_changed {
+            running.stop();
+            *server = None;
+        }
+    }
+    if !wanted || server.is_some() {
+        return Ok(());
+    }
+
+    let info_path = api_info_path(app)?;
+    let token = load_token(&info_path);
+    let app_handle = app.clone();
+    let started = ApiServer::start(settings.local_api_port, token.clone(), move |request| {
+        tauri::async_runtime::block_on(handle(&app_handle, request))
+    })
+    .map_err(|e| e.to_string())?;
+    let info = ApiInfo {
+        port: started.port(),
+        token,
+    };
+    // Clients could never learn the token, so do not leave the server running
+    if let Err(e) = write_info(&info_path, &info) {
+        started.stop();
+        return Err(e.to_string());
+    }
+    *server = Some(started);
+    Ok(())
+}
+
+/// Starts the local API at launch if it is enabled
+pub fn start_local_api(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = apply_settings(&app).await {
+            eprintln!("Failed to start the local API: {}", e);
+        }
+    });
+}
+
+/// Delivers the frontend's answer to a waiting API request
+#[tauri::command]
+pub async fn api_reply(id: u64, result: Value) -> Result<(), String> {
+    if let Some(sender) = PENDING.lock().unwrap().remove(&id) {
+        let _ = sender.send(result);
+    }
+    Ok(())
+}
+
+/// Returns the port and token scripts need, or None while the API is off
+#[tauri::command]
+pub async fn get_local_api_info(app: tauri::AppHandle) -> Result<Option<ApiInfo>, String> {
+    let port = match SERVER.lock().unwrap().as_ref() {
+        Some(server) => server.port(),
+        None => return Ok(None),
+    };
+    let token = load_token(&api_info_path(&app)?);
+    Ok(Some(ApiInfo { port, token }))
+}
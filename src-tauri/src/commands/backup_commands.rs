@@ -0,0 +1,285 @@
+use crate::backup::{self, BackupLocations, BackupSummary, ConflictPolicy, RestoreReport};
+use crate::commands::history_commands::get_history_dir;
+use crate::commands::settings_commands::{self, get_settings_path, AppSettings};
+use crate::commands::template_commands::{self, get_templates_dir};
+use crate::commands::vault_commands;
+use crate::menu;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use tauri::api::dialog;
+use tauri::Manager;
+
+/// How often the scheduler wakes up to check whether a backup is due
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// Wait before retrying after a failed scheduled backup
+const RETRY_DELAY_MINUTES: i64 = 15;
+
+/// State of the backup scheduler, shared with the status command and tray
+#[derive(Default)]
+struct SchedulerState {
+    last_success: Option<DateTime<Local>>,
+    last_path: Option<String>,
+    last_failure: Option<DateTime<Local>>,
+    last_error: Option<String>,
+    next_run: Option<DateTime<Local>>,
+}
+
+lazy_static::lazy_static! {
+    static ref SCHEDULER_STATE: Mutex<SchedulerState> = Mutex::new(SchedulerState::default());
+}
+
+/// Scheduled backup status reported to the frontend; times are RFC 3339
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupStatus {
+    pub enabled: bool,
+    pub directory: Option<String>,
+    pub last_success: Option<String>,
+    pub last_path: Option<String>,
+    pub last_failure: Option<String>,
+    pub last_error: Option<String>,
+    pub next_run: Option<String>,
+}
+
+/// Collects the on-disk locations of everything that goes into a backup
+pub async fn backup_locations(app: &tauri::AppHandle) -> Result<BackupLocations, String> {
+    let settings = get_settings_path(app).map_err(|e| e.to_string())?;
+    let backups = settings_commands::load_settings(app.clone())
+        .await?
+        .auto_backup_dir
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    Ok(BackupLocations {
+        notes: settings_commands::get_notes_root(app).await,
+        settings,
+        templates: get_templates_dir(app).map_err(|e| e.to_string())?,
+        history: get_history_dir(app).map_err(|e| e.to_string())?,
+        vault_key: vault_commands::vault_key_path(app)?,
+        backups,
+    })
+}
+
+/// Default archive name, e.g. "QuickNote Backup 2026-10-19 1430.zip"
+pub fn default_backup_name() -> String {
+    format!(
+        "QuickNote Backup {}.zip",
+        chrono::Local::now().format("%Y-%m-%d %H%M")
+    )
+}
+
+/// Bundles notes, settings, templates and history into a zip archive
+#[tauri::command]
+pub async fn create_backup(
+    app: tauri::AppHandle,
+    target: Option<String>,
+) -> Result<Option<BackupSummary>, String> {
+    let target = match target {
+        Some(target) => PathBuf::from(target),
+        None => match dialog::blocking::FileDialogBuilder::new()
+            .set_title("Create Backup")
+            .set_file_name(&default_backup_name())
+            .add_filter("Zip Archives", &["zip"])
+            .save_file()
+        {
+            Some(target) => target,
+            None => return Ok(None), // User cancelled the dialog
+        },
+    };
+
+    let locations = backup_locations(&app).await?;
+    let version = app.package_info().version.to_string();
+    tokio::task::spawn_blocking(move || backup::create_backup(&locations, &target, &version))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(Some)
+        .map_err(|e| format!("Failed to create backup: {}", e))
+}
+
+/// Validates a backup archive and restores it
+///
+/// `on_conflict` decides what happens to existing files that differ from the backup:
+/// "skip", "overwrite" or "keep_both" (the default).
+#[tauri::command]
+pub async fn restore_backup(
+    app: tauri::AppHandle,
+    source: Option<String>,
+    on_conflict: Option<String>,
+) -> Result<Option<RestoreReport>, String> {
+    let policy = ConflictPolicy::parse(on_conflict.as_deref().unwrap_or("keep_both"))
+        .map_err(|e| e.to_string())?;
+    let source = match source {
+        Some(source) => PathBuf::from(source),
+        None => match dialog::blocking::FileDialogBuilder::new()
+            .set_title("Restore Backup")
+            .add_filter("Zip Archives", &["zip"])
+            .pick_file()
+        {
+            Some(source) => source,
+            None => return Ok(None), // User cancelled the dialog
+        },
+    };
+
+    let locations = backup_locations(&app).await?;
+    // Notes restored into a vault are sealed, so a locked vault has to be unlocked first
+    let vault = match &locations.notes {
+        Some(root) => vault_commands::vault_for(&app, &root.to_string_lossy()).await?,
+        None => None,
+    };
+    let report = tokio::task::spawn_blocking(move || {
+        backup::restore_backup(&locations, &source, policy, vault.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| format!("Failed to restore backup: {}", e))?;
+
+    // Restored templates and settings need to be picked up by the tray and the frontend
+    template_commands::refresh_template_menu(&app);
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("backup-restored", &report);
+    }
+    Ok(Some(report))
+}
+
+/// Line shown in the tray menu, or None when scheduled backups are off
+fn tray_status_line(enabled: bool, state: &SchedulerState) -> Option<String> {
+    if !enabled {
+        return None;
+    }
+    let failed_last = match (state.last_failure, state.last_success) {
+        (Some(failure), Some(success)) => failure > success,
+        (Some(_), None) => true,
+        _ => false,
+    };
+    Some(if failed_last {
+        "Last backup failed".to_string()
+    } else if let Some(success) = state.last_success {
+        format!("Last backup: {}", success.format("%Y-%m-%d %H:%M"))
+    } else {
+        "No backups yet".to_string()
+    })
+}
+
+/// Runs one scheduled backup into `dir` and rotates old archives
+async fn run_scheduled_backup(app: &tauri::AppHandle, settings: &AppSettings, dir: PathBuf) {
+    let now = Local::now();
+    let target = dir.join(backup::auto_backup_name(&now));
+    let result = match backup_locations(app).await {
+        Ok(locations) => {
+            let version = app.package_info().version.to_string();
+            let (keep, max_age_days) =
+                (settings.auto_backup_keep, settings.auto_backup_max_age_days);
+            tokio::task::spawn_blocking(move || {
+                let summary = backup::create_backup(&locations, &target, &version)?;
+                if let Err(e) = backup::rotate_backups(&dir, keep, max_age_days, SystemTime::now())
+                {
+                    eprintln!("Failed to rotate backups: {}", e);
+                }
+                Ok::<_, anyhow::Error>(summary)
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result.map_err(|e| e.to_string()))
+        }
+        Err(e) => Err(e),
+    };
+
+    let mut state = SCHEDULER_STATE.lock().unwrap();
+    match result {
+        Ok(summary) => {
+            state.last_success = Some(now);
+            state.last_path = Some(summary.path);
+        }
+        Err(e) => {
+            eprintln!("Scheduled backup failed: {}", e);
+            state.last_failure = Some(now);
+            state.last_error = Some(e);
+        }
+    }
+}
+
+/// Checks whether a scheduled backup is due and runs it
+async fn scheduler_tick(app: &tauri::AppHandle) {
+    let settings = match settings_commands::load_settings(app.clone()).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Backup scheduler could not load settings: {}", e);
+            return;
+        }
+    };
+    let dir = settings
+        .auto_backup_dir
+        .as_deref()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from);
+    let enabled = settings.auto_backup_enabled && dir.is_some();
+    let interval = chrono::Duration::hours(i64::from(settings.auto_backup_interval_hours.max(1)));
+
+    if let Some(dir) = dir.filter(|_| enabled) {
+        let (last_success, last_failure) = {
+            let mut state = SCHEDULER_STATE.lock().unwrap();
+            // After a restart, pick up from the newest archive already in the folder
+            if state.last_success.is_none() {
+                if let Some((path, modified)) = backup::list_auto_backups(&dir).into_iter().next() {
+                    state.last_success = Some(DateTime::<Local>::from(modified));
+                    state.last_path = Some(path.to_string_lossy().to_string());
+                }
+            }
+            (state.last_success, state.last_failure)
+        };
+
+        let mut due = last_success
+            .map(|last| last + interval)
+            .unwrap_or_else(Local::now);
+        if let Some(failure) = last_failure.filter(|failure| Some(*failure) > last_success) {
+            due = due.max(failure + chrono::Duration::minutes(RETRY_DELAY_MINUTES));
+        }
+        if Local::now() >= due {
+            run_scheduled_backup(app, &settings, dir).await;
+        }
+    }
+
+    let line = {
+        let mut state = SCHEDULER_STATE.lock().unwrap();
+        state.next_run = if enabled {
+            Some(
+                state
+                    .last_success
+                    .map(|last| last + interval)
+                    .unwrap_or_else(Local::now),
+            )
+        } else {
+            None
+        };
+        tray_status_line(enabled, &state)
+    };
+    menu::update_tray_menu_with_backup_status(app, line);
+}
+
+/// Starts the background task that makes scheduled backups
+pub fn start_backup_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            scheduler_tick(&app).await;
+            tokio::time::sleep(SCHEDULER_TICK).await;
+        }
+    });
+}
+
+/// Reports the last scheduled backup and when the next one is due
+#[tauri::command]
+pub async fn get_backup_status(app: tauri::AppHandle) -> Result<BackupStatus, String> {
+    let settings = settings_commands::load_settings(app).await?;
+    let state = SCHEDULER_STATE.lock().unwrap();
+    let format = |time: &Option<DateTime<Local>>| time.map(|time| time.to_rfc3339());
+    Ok(BackupStatus {
+        enabled: settings.auto_backup_enabled,
+        directory: settings.auto_backup_dir,
+        last_success: format(&state.last_success),
+        last_path: state.last_path.clone(),
+        last_failure: format(&state.last_failure),
+        last_error: state.last_error.clone(),
+        next_run: format(&state.next_run),
+    })
+}
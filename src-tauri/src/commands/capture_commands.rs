@@ -0,0 +1,165 @@
+use crate::capture::{self, AppendOptions, AppendRequest};
+use crate::commands::{api_commands, file_commands, settings_commands, vault_commands};
+use chrono::Local;
+use serde::Serialize;
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tauri::{ClipboardManager, Manager};
+
+/// Capture note used when settings do not name one
+const DEFAULT_CAPTURE_NOTE: &str = "inbox.md";
+
+/// Where a fragment ended up
+#[derive(Debug, Clone, Serialize)]
+pub struct AppendOutcome {
+    pub path: Option<String>,
+    pub tab_id: Option<String>,
+}
+
+/// Resolves the capture note from settings; relative paths are inside the notes root
+pub async fn capture_note_path(app: &tauri::AppHandle) -> Result<String, String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let configured = settings
+        .capture_note_path
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| DEFAULT_CAPTURE_NOTE.to_string());
+    let path = if Path::new(&configured).is_absolute() {
+        PathBuf::from(configured)
+    } else {
+        settings_commands::require_notes_root(app)
+            .await?
+            .join(configured)
+    };
+    Ok(path.to_string_lossy().to_string())
+}
+
+async fn options_for(
+    app: &tauri::AppHandle,
+    request: &AppendRequest,
+) -> Result<AppendOptions, String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    Ok(AppendOptions {
+        timestamp: request.timestamp.unwrap_or(settings.capture_timestamp),
+        separator: request
+            .separator
+            .clone()
+            .or(settings.capture_separator)
+            .filter(|separator| !separator.is_empty()),
+    })
+}
+
+async fn append_to_tab(
+    app: &tauri::AppHandle,
+    tab_id: String,
+    entry: &str,
+    options: &AppendOptions,
+) -> Result<AppendOutcome, String> {
+    let app_handle = app.clone();
+    let id = tab_id.clone();
+    let tab = tokio::task::spawn_blocking(move || {
+        api_commands::ask_frontend(&app_handle, "get_tab", json!({ "id": id }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.message)?;
+    if tab.is_null() {
+        return Err(format!("No tab with id {}", tab_id));
+    }
+
+    // The webview appends the entry itself, so typing since the request is not overwritten
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit(
+            "append-to-tab",
+            json!({
+                "id": tab_id,
+                "entry": entry,
+                "separator": options.separator,
+                "timestamp": options.timestamp,
+            }),
+        );
+    }
+    Ok(AppendOutcome {
+        path: None,
+        tab_id: Some(tab_id),
+    })
+}
+
+/// Appends a fragment to a file or open tab and tells the webview about the new content
+pub async fn append(
+    app: &tauri::AppHandle,
+    request: AppendRequest,
+) -> Result<AppendOutcome, String> {
+    if request.text.trim().is_empty() {
+        return Err("Nothing to append".to_string());
+    }
+    let options = options_for(app, &request).await?;
+    let entry = capture::format_entry(&request.text, &options, Local::now());
+
+    if let Some(tab_id) = request.tab_id {
+        return append_to_tab(app, tab_id, &entry, &options).await;
+    }
+    let path = match request.path.filter(|path| !path.is_empty()) {
+        Some(path) => path,
+        None => capture_note_path(app).await?,
+    };
+
+    let content = file_commands::edit_note(app, &path, |existing| {
+        Ok(capture::append_entry(existing, &entry, &options))
+    })
+    .await?;
+
+    // The merge base stays as it was, so a tab with unsaved edits merges the entry on save
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("note-appended", json!({ "path": path, "content": content }));
+    }
+    Ok(AppendOutcome {
+        path: Some(path),
+        tab_id: None,
+    })
+}
+
+/// Appends the clipboard text to the capture note, e.g. from the tray or a global shortcut
+pub async fn append_clipboard(app: &tauri::AppHandle) {
+    let text = match app.clipboard_manager().read_text() {
+        Ok(Some(text)) => text,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Failed to read the clipboard: {}", e);
+            return;
+        }
+    };
+    report_failure(
+        app,
+        append(
+            app,
+            AppendRequest {
+                text,
+                ..Default::default()
+            },
+        )
+        .await,
+    );
+}
+
+/// Logs a failed background append and shows it in the window
+pub fn report_failure(app: &tauri::AppHandle, result: Result<AppendOutcome, String>) {
+    if let Err(e) = result {
+        eprintln!("Failed to append to note: {}", e);
+        if let Some(window) = app.get_window("main") {
+            if e == vault_commands::VAULT_LOCKED {
+                let _ = window.emit("vault-unlock-required", ());
+            } else {
+                let _ = window.emit("error", format!("Failed to append to note: {}", e));
+            }
+        }
+    }
+}
+
+/// Appends text, with an optional timestamp header and separator, to a note or open tab
+#[tauri::command]
+pub async fn append_to_note(
+    app: tauri::AppHandle,
+    request: AppendRequest,
+) -> Result<AppendOutcome, String> {
+    append(&app, request).await
+}
@@ -0,0 +1,88 @@
+use crate::capture::AppendRequest;
+use crate::cli::LaunchRequest;
+use crate::commands::{capture_commands, vault_commands};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::Manager;
+
+lazy_static::lazy_static! {
+    // Files and text given on the command line, waiting for the frontend to load
+    static ref PENDING_LAUNCH: Mutex<Option<LaunchRequest>> = Mutex::new(None);
+}
+
+/// Set once the frontend has taken the launch request; later requests are sent as events
+static FRONTEND_READY: AtomicBool = AtomicBool::new(false);
+
+/// Keeps the launch request until the frontend asks for it
+pub fn set_launch_request(request: LaunchRequest) {
+    if !request.is_empty() {
+        *PENDING_LAUNCH.lock().unwrap() = Some(request);
+    }
+}
+
+fn spawn_append(app: &tauri::AppHandle, append: AppendRequest) {
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = capture_commands::append(&app_handle, append).await;
+        capture_commands::report_failure(&app_handle, result);
+    });
+}
+
+/// Runs a `--append` given to this launch; appends need no frontend, so they start right away
+pub fn start_launch_append(app: &tauri::AppHandle) {
+    let append = PENDING_LAUNCH
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|request| request.append.take());
+    if let Some(append) = append {
+        spawn_append(app, append);
+    }
+}
+
+/// Brings the window forward and opens what a second launch asked for
+pub fn handle_forwarded_request(app: &tauri::AppHandle, mut request: LaunchRequest) {
+    if let Some(append) = request.append.take() {
+        spawn_append(app, append);
+        // Capturing from a terminal should not steal focus
+        if request.is_empty() {
+            return;
+        }
+    }
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+        if !request.is_empty() {
+            let _ = window.emit("launch-request", request);
+        }
+    }
+    vault_commands::request_unlock_if_locked(app);
+}
+
+/// Opens a quicknote:// link the OS hands to the running app, e.g. as an Apple Event on macOS
+pub fn handle_url(app: &tauri::AppHandle, url: String) {
+    let request = LaunchRequest {
+        urls: vec![url],
+        ..LaunchRequest::default()
+    };
+    let mut pending = PENDING_LAUNCH.lock().unwrap();
+    if FRONTEND_READY.load(Ordering::SeqCst) {
+        drop(pending);
+        handle_forwarded_request(app, request);
+    } else {
+        // A link that starts the app arrives before the frontend can listen for it
+        pending
+            .get_or_insert_with(LaunchRequest::default)
+            .urls
+            .extend(request.urls);
+    }
+}
+
+/// Returns the command-line request of this launch once, so it is only handled on the first load
+#[tauri::command]
+pub async fn take_launch_request() -> Result<Option<LaunchRequest>, String> {
+    let mut pending = PENDING_LAUNCH.lock().unwrap();
+    FRONTEND_READY.store(true, Ordering::SeqCst);
+    Ok(pending.take())
+}
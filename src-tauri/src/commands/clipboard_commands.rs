@@ -0,0 +1,218 @@
+use crate::clipboard_history::{self, ClipEntry, ClipboardHistory};
+use crate::commands::settings_commands::{self, get_settings_path};
+use crate::commands::vault_commands;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{ClipboardManager, Manager};
+
+/// How often the clipboard is checked for new text
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    // Loaded from disk on first use
+    static ref HISTORY: Mutex<Option<ClipboardHistory>> = Mutex::new(None);
+    // Clipboard text the watcher has already handled, recorded or not
+    static ref LAST_SEEN: Mutex<Option<String>> = Mutex::new(None);
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static LIMIT: AtomicUsize = AtomicUsize::new(200);
+// Set while the active tab is an encrypted note, so nothing copied from it is kept
+static SENSITIVE_FOCUS: AtomicBool = AtomicBool::new(false);
+
+fn history_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    get_settings_path(app)
+        .map(|settings| settings.with_file_name("clipboard-history.json"))
+        .map_err(|e| e.to_string())
+}
+
+/// Runs `f` on the history, saving it afterwards when `f` reports a change
+fn with_history<T>(
+    app: &tauri::AppHandle,
+    f: impl FnOnce(&mut ClipboardHistory) -> (T, bool),
+) -> Result<T, String> {
+    let path = history_path(app)?;
+    let mut history = HISTORY.lock().unwrap();
+    if history.is_none() {
+        *history = Some(clipboard_history::load(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to load clipboard history: {}", e);
+            ClipboardHistory::default()
+        }));
+    }
+    let history = history.as_mut().unwrap();
+    let (result, changed) = f(history);
+    if changed {
+        clipboard_history::save(&path, history).map_err(|e| e.to_string())?;
+        if let Some(window) = app.get_window("main") {
+            let _ = window.emit("clipboard-history-changed", ());
+        }
+    }
+    Ok(result)
+}
+
+fn entry_text(app: &tauri::AppHandle, id: u64) -> Result<String, String> {
+    with_history(app, |history| {
+        (history.get(id).map(|entry| entry.text.clone()), false)
+    })?
+    .ok_or_else(|| format!("No clipboard entry with id {}", id))
+}
+
+/// Applies the clipboard history settings, trimming the history if the limit shrank
+pub async fn apply_settings(app: &tauri::AppHandle) -> Result<(), String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    ENABLED.store(settings.clipboard_history_enabled, Ordering::Relaxed);
+    let limit = settings.clipboard_history_limit;
+    if LIMIT.swap(limit, Ordering::Relaxed) > limit {
+        with_history(app, |history| {
+            history.trim(limit);
+            ((), true)
+        })?;
+    }
+    Ok(())
+}
+
+fn record(app: &tauri::AppHandle, text: &str) {
+    if SENSITIVE_FOCUS.load(Ordering::Relaxed) || clipboard_history::looks_like_secret(text) {
+        return;
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    let limit = LIMIT.load(Ordering::Relaxed);
+    if let Err(e) = with_history(app, |history| {
+        let recorded = history.record(text, now, limit);
+        (recorded, recorded)
+    }) {
+        eprintln!("Failed to save clipboard history: {}", e);
+    }
+}
+
+/// Watches the clipboard in the background while clipboard history is enabled in settings
+pub fn start_clipboard_watcher(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = apply_settings(&app).await {
+            eprintln!("Failed to load clipboard history settings: {}", e);
+        }
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !ENABLED.load(Ordering::Relaxed) {
+                *LAST_SEEN.lock().unwrap() = None;
+                continue;
+            }
+            let text = match app.clipboard_manager().read_text() {
+                Ok(Some(text)) => text,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Failed to read the clipboard: {}", e);
+                    continue;
+                }
+            };
+            // Text skipped while an encrypted note was focused stays skipped afterwards
+            let mut last_seen = LAST_SEEN.lock().unwrap();
+            if last_seen.as_deref() != Some(text.as_str()) {
+                record(&app, &text);
+                *last_seen = Some(text);
+            }
+        }
+    });
+}
+
+/// Lists the clipboard history, pinned entries first
+#[tauri::command]
+pub async fn list_clipboard_history(
+    app: tauri::AppHandle,
+    limit: Option<usize>,
+) -> Result<Vec<ClipEntry>, String> {
+    with_history(&app, |history| (history.list(limit), false))
+}
+
+/// Finds clipboard entries containing every word of the query
+#[tauri::command]
+pub async fn search_clipboard_history(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<ClipEntry>, String> {
+    with_history(&app, |history| (history.search(&query, limit), false))
+}
+
+/// Pins or unpins a clipboard entry; pinned entries are kept however long the history grows
+#[tauri::command]
+pub async fn pin_clipboard_entry(
+    app: tauri::AppHandle,
+    id: u64,
+    pinned: bool,
+) -> Result<(), String> {
+    let found = with_history(&app, |history| {
+        let found = history.set_pinned(id, pinned);
+        (found, found)
+    })?;
+    if !found {
+        return Err(format!("No clipboard entry with id {}", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_clipboard_entry(app: tauri::AppHandle, id: u64) -> Result<(), String> {
+    with_history(&app, |history| {
+        let removed = history.remove(id);
+        ((), removed)
+    })
+}
+
+/// Forgets all clipboard entries except pinned ones
+#[tauri::command]
+pub async fn clear_clipboard_history(app: tauri::AppHandle) -> Result<(), String> {
+    with_history(&app, |history| {
+        history.clear();
+        ((), true)
+    })
+}
+
+/// Inserts a clipboard entry at the cursor of the active tab
+#[tauri::command]
+pub async fn paste_clipboard_entry(app: tauri::AppHandle, id: u64) -> Result<(), String> {
+    let text = entry_text(&app, id)?;
+    if let Some(window) = app.get_window("main") {
+        window
+            .emit("insert-text", json!({ "text": text }))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Opens a clipboard entry in a new tab
+#[tauri::command]
+pub async fn open_clipboard_entry(app: tauri::AppHandle, id: u64) -> Result<(), String> {
+    let text = entry_text(&app, id)?;
+    if let Some(window) = app.get_window("main") {
+        window
+            .emit("new-note", json!({ "title": null, "content": text }))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Tells the watcher which note is active, so copies from encrypted and vault notes are not kept
+#[tauri::command]
+pub async fn set_clipboard_focus(
+    app: tauri::AppHandle,
+    path: Option<String>,
+) -> Result<(), String> {
+    let sensitive = match path {
+        Some(path) => vault_commands::is_protected(&app, &path).await,
+        None => false,
+    };
+    if !sensitive && SENSITIVE_FOCUS.load(Ordering::Relaxed) {
+        // Text copied from the encrypted note just before leaving it may not have been
+        // polled yet; mark it seen before the watcher stops skipping
+        if let Ok(Some(text)) = app.clipboard_manager().read_text() {
+            *LAST_SEEN.lock().unwrap() = Some(text);
+        }
+    }
+    SENSITIVE_FOCUS.store(sensitive, Ordering::Relaxed);
+    Ok(())
+}
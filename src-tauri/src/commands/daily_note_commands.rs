@@ -0,0 +1,109 @@
+use crate::commands::file_commands::{self, FilePayload};
+use crate::commands::settings_commands::{self, AppSettings};
+use crate::daily_notes::{self, DailyNoteConfig};
+use chrono::NaiveDate;
+use std::path::Path;
+use tokio::fs;
+
+/// Builds the daily note configuration from the current settings
+async fn daily_note_config(
+    app: &tauri::AppHandle,
+) -> Result<(DailyNoteConfig, AppSettings), String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let root = settings_commands::require_notes_root(app).await?;
+    daily_notes::validate_pattern(&settings.daily_note_pattern)?;
+
+    let config = DailyNoteConfig {
+        root,
+        folder: settings.daily_notes_folder.clone(),
+        pattern: settings.daily_note_pattern.clone(),
+    };
+    Ok((config, settings))
+}
+
+/// Opens the daily note for `date`, creating it from the template if it does not exist
+pub async fn open_or_create_daily_note(
+    app: &tauri::AppHandle,
+    date: NaiveDate,
+) -> Result<FilePayload, String> {
+    let (config, settings) = daily_note_config(app).await?;
+    let path = config.path_for(date);
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        let template = match settings.daily_note_template.as_deref() {
+            Some(template_path) if !template_path.is_empty() => {
+                match fs::read_to_string(template_path).await {
+                    Ok(content) => Some(content),
+                    Err(e) => {
+                        eprintln!(
+                            "Failed to read daily note template {}: {}",
+                            template_path, e
+                        );
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let title = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = daily_notes::render_daily_note(template.as_deref(), date, &title);
+        file_commands::write_note_atomically(app, &path.to_string_lossy(), &content).await?;
+    }
+
+    let path = path.to_string_lossy().to_string();
+    let content = file_commands::read_note(app, &path).await?;
+    file_commands::remember_base(&path, &content);
+    Ok(FilePayload { path, content })
+}
+
+/// Opens (or creates) the daily note for the given date, defaulting to today
+#[tauri::command]
+pub async fn open_daily_note(
+    app: tauri::AppHandle,
+    date: Option<String>,
+) -> Result<FilePayload, String> {
+    let date = match date {
+        Some(date) => daily_notes::parse_date(&date)?,
+        None => daily_notes::today(),
+    };
+    open_or_create_daily_note(&app, date).await
+}
+
+/// Opens the nearest existing daily note before or after the given one, skipping missing dates
+#[tauri::command]
+pub async fn open_adjacent_daily_note(
+    app: tauri::AppHandle,
+    path: String,
+    direction: String,
+) -> Result<Option<FilePayload>, String> {
+    let (config, _) = daily_note_config(&app).await?;
+    let current = config
+        .date_of(Path::new(&path))
+        .ok_or_else(|| format!("Not a daily note: {}", path))?;
+
+    let forward = match direction.as_str() {
+        "next" => true,
+        "previous" => false,
+        other => return Err(format!("Unknown direction: {}", other)),
+    };
+
+    match config.adjacent_date(current, forward) {
+        Some(date) => {
+            let path = config.path_for(date).to_string_lossy().to_string();
+            let content = file_commands::read_note(&app, &path).await?;
+            file_commands::remember_base(&path, &content);
+            Ok(Some(FilePayload { path, content }))
+        }
+        None => Ok(None),
+    }
+}
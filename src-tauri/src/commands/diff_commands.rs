@@ -0,0 +1,36 @@
+use crate::commands::file_commands;
+use crate::diff::{self, DiffOptions, DiffResult};
+use std::path::Path;
+
+/// Compares two texts and returns structured line and word hunks
+#[tauri::command]
+pub async fn diff_text(
+    old: String,
+    new: String,
+    options: Option<DiffOptions>,
+) -> Result<DiffResult, String> {
+    Ok(diff::diff_text(&old, &new, &options.unwrap_or_default()))
+}
+
+/// Compares the on-disk content of a file (old) with the editor buffer (new)
+#[tauri::command]
+pub async fn diff_with_disk(
+    app: tauri::AppHandle,
+    path: String,
+    content: String,
+    options: Option<DiffOptions>,
+) -> Result<DiffResult, String> {
+    // A file that no longer exists diffs as empty
+    let disk_content = if Path::new(&path).exists() {
+        file_commands::read_note(&app, &path)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?
+    } else {
+        String::new()
+    };
+    Ok(diff::diff_text(
+        &disk_content,
+        &content,
+        &options.unwrap_or_default(),
+    ))
+}
@@ -0,0 +1,218 @@
+use crate::commands::file_commands::{self, FilePayload, SaveOutcome};
+use crate::commands::{git_commands, history_commands, settings_commands};
+use crate::crypto::{self, NoteKey};
+use crate::storage::write_atomic;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+use tokio::fs;
+
+/// Error returned when an encrypted note is opened or saved without an unlocked key;
+/// the frontend asks for the passphrase and calls `open_encrypted_note`
+pub const PASSPHRASE_REQUIRED: &str = "passphrase_required";
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+struct CachedKey {
+    key: Arc<NoteKey>,
+    last_used: Instant,
+}
+
+lazy_static::lazy_static! {
+    // Keys of unlocked notes by path; they only ever live in memory
+    static ref KEY_CACHE: Mutex<HashMap<String, CachedKey>> = Mutex::new(HashMap::new());
+}
+
+fn cache_key(path: &str, key: NoteKey) {
+    KEY_CACHE.lock().unwrap().insert(
+        path.to_string(),
+        CachedKey {
+            key: Arc::new(key),
+            last_used: Instant::now(),
+        },
+    );
+}
+
+/// Drops the key of a note along with the plaintext kept as its merge base
+fn forget_key(path: &str) {
+    KEY_CACHE.lock().unwrap().remove(path);
+    file_commands::forget_base(path);
+}
+
+/// Returns the unlocked key of a note, resetting its idle timer
+fn cached_key(path: &str) -> Option<Arc<NoteKey>> {
+    let mut cache = KEY_CACHE.lock().unwrap();
+    cache.get_mut(path).map(|cached| {
+        cached.last_used = Instant::now();
+        cached.key.clone()
+    })
+}
+
+/// Checks the file header without reading the whole note
+pub fn is_encrypted_file(path: &str) -> bool {
+    let mut header = [0u8; 8];
+    match std::fs::File::open(path).and_then(|mut file| file.read(&mut header)) {
+        Ok(read) => crypto::is_encrypted(&header[..read]),
+        Err(_) => false,
+    }
+}
+
+fn plaintext_to_string(plaintext: &[u8]) -> Result<String, String> {
+    String::from_utf8(plaintext.to_vec())
+        .map_err(|_| "Encrypted note is not valid text".to_string())
+}
+
+/// Decrypts a note with its cached key, failing with PASSPHRASE_REQUIRED if it is locked
+pub async fn read_encrypted(path: &str) -> Result<String, String> {
+    let key = cached_key(path).ok_or_else(|| PASSPHRASE_REQUIRED.to_string())?;
+    let data = fs::read(path).await.map_err(|e| e.to_string())?;
+    match crypto::decrypt_with_key(&key, &data) {
+        Ok(plaintext) => plaintext_to_string(&plaintext),
+        // Re-encrypted elsewhere with another passphrase
+        Err(_) => {
+            forget_key(path);
+            Err(PASSPHRASE_REQUIRED.to_string())
+        }
+    }
+}
+
+/// Encrypts and writes a note with its cached key, replacing the file atomically
+pub async fn write_encrypted(path: &str, content: &str) -> Result<(), String> {
+    let key = cached_key(path).ok_or_else(|| PASSPHRASE_REQUIRED.to_string())?;
+    let data = crypto::encrypt(&key, content.as_bytes()).map_err(|e| e.to_string())?;
+    let path = PathBuf::from(path);
+    tokio::task::spawn_blocking(move || write_atomic(&path, &data))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Encrypts and writes a note with its cached key; plaintext skips history and the link index
+pub async fn save_encrypted(
+    app: &tauri::AppHandle,
+    path: &str,
+    content: String,
+) -> Result<SaveOutcome, String> {
+    write_encrypted(path, &content).await?;
+    file_commands::remember_base(path, &content);
+    git_commands::auto_commit(app, path);
+    Ok(SaveOutcome {
+        status: "saved".to_string(),
+        content,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Locks notes whose keys have been idle longer than the configured timeout
+fn sweep_idle_keys(app: &tauri::AppHandle, idle: Duration) {
+    let expired: Vec<String> = {
+        let mut cache = KEY_CACHE.lock().unwrap();
+        let expired: Vec<String> = cache
+            .iter()
+            .filter(|(_, cached)| cached.last_used.elapsed() >= idle)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &expired {
+            cache.remove(path);
+            file_commands::forget_base(path);
+        }
+        expired
+    };
+    if let Some(window) = app.get_window("main") {
+        for path in expired {
+            let _ = window.emit("note-locked", path);
+        }
+    }
+}
+
+/// Forgets every unlocked note key at once
+pub fn lock_all_notes(app: &tauri::AppHandle) {
+    sweep_idle_keys(app, Duration::ZERO);
+}
+
+/// Starts the background task that forgets idle keys
+pub fn start_key_cache_sweeper(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SWEEP_INTERVAL).await;
+            if KEY_CACHE.lock().unwrap().is_empty() {
+                continue;
+            }
+            let minutes = settings_commands::load_settings(app.clone())
+                .await
+                .map(|settings| settings.encryption_idle_minutes)
+                .unwrap_or(5);
+            sweep_idle_keys(&app, Duration::from_secs(u64::from(minutes.max(1)) * 60));
+        }
+    });
+}
+
+/// Saves a buffer as an encrypted note, replacing any plaintext at `path`
+#[tauri::command]
+pub async fn encrypt_note(
+    app: tauri::AppHandle,
+    path: String,
+    content: String,
+    passphrase: String,
+) -> Result<(), String> {
+    // Argon2id is deliberately slow, so keep it off the async runtime
+    let target = PathBuf::from(&path);
+    let (key, content) = tokio::task::spawn_blocking(move || {
+        let key = NoteKey::generate(&passphrase)?;
+        let data = crypto::encrypt(&key, content.as_bytes())?;
+        // Replace the plaintext atomically so a crash never leaves half a container
+        write_atomic(&target, &data)?;
+        anyhow::Ok((key, content))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    cache_key(&path, key);
+    file_commands::remember_base(&path, &content);
+    // Earlier plaintext snapshots would defeat the encryption
+    history_commands::forget_history(&app, &path);
+    // Checked before the encrypted version is committed
+    git_commands::warn_plaintext_in_history(&app, Path::new(&path)).await;
+    git_commands::auto_commit(&app, &path);
+    Ok(())
+}
+
+/// Opens an encrypted note, using the cached key or else deriving one from `passphrase`
+#[tauri::command]
+pub async fn open_encrypted_note(
+    path: String,
+    passphrase: Option<String>,
+) -> Result<FilePayload, String> {
+    let passphrase = match passphrase {
+        Some(passphrase) => passphrase,
+        None => {
+            let content = read_encrypted(&path).await?;
+            file_commands::remember_base(&path, &content);
+            return Ok(FilePayload { path, content });
+        }
+    };
+
+    let data = fs::read(&path).await.map_err(|e| e.to_string())?;
+    let (plaintext, key) = tokio::task::spawn_blocking(move || crypto::decrypt(&passphrase, &data))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    let content = plaintext_to_string(&plaintext)?;
+    cache_key(&path, key);
+    file_commands::remember_base(&path, &content);
+    Ok(FilePayload { path, content })
+}
+
+/// Forgets the key of a note right away
+#[tauri::command]
+pub async fn lock_note(app: tauri::AppHandle, path: String) -> Result<(), String> {
+    forget_key(&path);
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("note-locked", &path);
+    }
+    Ok(())
+}
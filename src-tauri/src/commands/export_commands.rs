@@ -0,0 +1,73 @@
+use crate::commands::settings_commands;
+use crate::export::pdf::{self, PdfOptions};
+use crate::export::{self, html};
+use std::path::Path;
+use tauri::api::dialog;
+use tokio::fs;
+
+/// Renders a note and writes it to a file chosen in a save dialog
+#[tauri::command]
+pub async fn export_note(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    name: String,
+    content: String,
+    format: Option<String>,
+    theme: Option<String>,
+    pdf_options: Option<PdfOptions>,
+) -> Result<Option<String>, String> {
+    let format = format.unwrap_or_else(|| "html".to_string());
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let theme = theme.unwrap_or_else(|| settings.theme.clone());
+
+    let title = export::document_title(path.as_deref(), &content);
+    let markdown = export::is_markdown(path.as_deref());
+    let (filter_name, extensions, bytes): (&str, &[&str], Vec<u8>) = match format.as_str() {
+        "html" => (
+            "HTML Files",
+            &["html", "htm"],
+            html::export_html(&title, &content, markdown, &theme).into_bytes(),
+        ),
+        "pdf" => {
+            let options = pdf_options.unwrap_or_else(|| PdfOptions {
+                page_size: settings.pdf_page_size,
+                margin_mm: settings.pdf_margin_mm,
+                font_path: settings.pdf_font_path,
+                cjk_font_path: settings.pdf_cjk_font_path,
+                ..PdfOptions::default()
+            });
+            // Font parsing and layout are CPU-bound, so keep them off the async runtime
+            let bytes = tokio::task::spawn_blocking(move || {
+                pdf::export_pdf(&title, &content, markdown, &options)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to render PDF: {}", e))?;
+            ("PDF Files", &["pdf"], bytes)
+        }
+        other => return Err(format!("Unsupported export format: {}", other)),
+    };
+
+    // Default to the note's own name with the export extension
+    let stem = Path::new(&name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(name);
+    let default_name = format!("{}.{}", stem, extensions[0]);
+
+    let mut dialog = dialog::blocking::FileDialogBuilder::new()
+        .set_title("Export Note")
+        .set_file_name(&default_name)
+        .add_filter(filter_name, extensions);
+    if let Some(parent) = path.as_deref().and_then(|path| Path::new(path).parent()) {
+        dialog = dialog.set_directory(parent);
+    }
+
+    match dialog.save_file() {
+        Some(target) => fs::write(&target, bytes)
+            .await
+            .map(|_| Some(target.to_string_lossy().to_string()))
+            .map_err(|e| e.to_string()),
+        None => Ok(None), // User cancelled the dialog
+    }
+}
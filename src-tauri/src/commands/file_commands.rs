@@ -1,9 +1,29 @@
+use crate::commands::encryption_commands::{self, PASSPHRASE_REQUIRED};
+use crate::commands::git_commands;
+use crate::commands::history_commands;
+use crate::commands::reminder_commands;
+use crate::commands::settings_commands;
+use crate::commands::vault_commands::{self, VAULT_LOCKED};
+use crate::links;
+use crate::merge::{self, ConflictRegion};
+use crate::storage::write_atomic;
+use crate::vault;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::api::dialog;
-use tauri::Window;
+use tauri::{Manager, Window};
 use tokio::fs;
 
+// Content of each open file as last read from or written to disk, used as the merge base
+lazy_static::lazy_static! {
+    static ref FILE_BASES: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    // Saves and background edits read, change and replace the whole file, so they run one at a time
+    static ref EDIT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
 /// Payload structure for file operations
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FilePayload {
@@ -11,6 +31,58 @@ pub struct FilePayload {
     pub content: String,
 }
 
+/// Result of a save, telling the frontend whether the buffer needs updating
+#[derive(Debug, Serialize)]
+pub struct SaveOutcome {
+    /// "saved", "merged" or "conflict"
+    pub status: String,
+    /// Content now on disk, or the buffer with conflict markers when status is "conflict"
+    pub content: String,
+    pub conflicts: Vec<ConflictRegion>,
+}
+
+/// Records the on-disk content of a file as seen by the editor
+pub fn remember_base(path: &str, content: &str) {
+    FILE_BASES
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), content.to_string());
+}
+
+/// Moves the merge base of a file that was renamed
+pub fn move_base(old_path: &str, new_path: &str) {
+    let mut bases = FILE_BASES.lock().unwrap();
+    if let Some(base) = bases.remove(old_path) {
+        bases.insert(new_path.to_string(), base);
+    }
+}
+
+/// Drops the remembered content of a file, e.g. when an encrypted note is locked
+pub fn forget_base(path: &str) {
+    FILE_BASES.lock().unwrap().remove(path);
+}
+
+fn base_of(path: &str) -> Option<String> {
+    FILE_BASES.lock().unwrap().get(path).cloned()
+}
+
+/// Reads a note, decrypting it if it has its own passphrase or lives in the vault
+pub async fn read_note(app: &tauri::AppHandle, path: &str) -> Result<String, String> {
+    if encryption_commands::is_encrypted_file(path) {
+        return encryption_commands::read_encrypted(path).await;
+    }
+    match vault_commands::vault_for(app, path).await? {
+        Some(key) => {
+            let path = PathBuf::from(path);
+            tokio::task::spawn_blocking(move || vault::read_file(&path, &key))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string())
+        }
+        None => fs::read_to_string(path).await.map_err(|e| e.to_string()),
+    }
+}
+
 /// Opens one or more files using a system dialog and returns their content
 #[tauri::command]
 pub async fn open_file(window: Window) -> Result<Option<Vec<FilePayload>>, String> {
@@ -32,17 +104,25 @@ pub async fn open_file(window: Window) -> Result<Option<Vec<FilePayload>>, Strin
         Some(paths) => {
             let mut files_content = Vec::new();
             for path in paths {
+                let path_str = path.to_string_lossy().to_string();
                 // Read file content asynchronously
-                match fs::read_to_string(&path).await {
+                match read_note(&window.app_handle(), &path_str).await {
                     Ok(content) => {
                         let payload = FilePayload {
                             path: path.to_string_lossy().to_string(),
                             content,
                         };
+                        remember_base(&payload.path, &payload.content);
                         files_content.push(payload);
                     }
+                    // Locked notes are opened once the frontend has asked for the passphrase
+                    Err(e) if e == PASSPHRASE_REQUIRED => {
+                        let _ = window.emit("encrypted-file", &path_str);
+                    }
+                    Err(e) if e == VAULT_LOCKED => {
+                        let _ = window.emit("vault-unlock-required", ());
+                    }
                     Err(e) => {
-                        let path_str = path.to_string_lossy();
                         eprintln!("Failed to read file {}: {}", path_str, e);
                         let _ = window.emit("error", format!("Failed to read file: {}", path_str));
                     }
@@ -60,34 +140,155 @@ pub async fn open_file(window: Window) -> Result<Option<Vec<FilePayload>>, Strin
 
 /// Opens a specific file by path and returns its content
 #[tauri::command]
-pub async fn open_specific_file(path: String) -> Result<Option<FilePayload>, String> {
+pub async fn open_specific_file(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Option<FilePayload>, String> {
     // Check if file exists
     if !std::path::Path::new(&path).exists() {
         return Err(format!("File not found: {}", path));
     }
 
     // Read file content asynchronously
-    match fs::read_to_string(&path).await {
+    match read_note(&app, &path).await {
         Ok(content) => {
+            remember_base(&path, &content);
             let payload = FilePayload {
                 path: path.to_string(),
                 content,
             };
             Ok(Some(payload))
         }
+        // The frontend recognises these and asks for a passphrase
+        Err(e) if e == PASSPHRASE_REQUIRED || e == VAULT_LOCKED => Err(e),
         Err(e) => Err(format!("Failed to read file: {}", e)),
     }
 }
 
-/// Saves content to a specified file path
+/// Replaces a note in one step, so a crash never leaves it half written.
+/// Returns true when it was stored encrypted, with its own passphrase or in the vault.
+pub async fn write_note_atomically(
+    app: &tauri::AppHandle,
+    path: &str,
+    content: &str,
+) -> Result<bool, String> {
+    if encryption_commands::is_encrypted_file(path) {
+        encryption_commands::write_encrypted(path, content).await?;
+        return Ok(true);
+    }
+    let vault_key = vault_commands::vault_for(app, path).await?;
+    let sealed = vault_key.is_some();
+    let path = PathBuf::from(path);
+    let content = content.to_string();
+    tokio::task::spawn_blocking(move || match vault_key {
+        Some(key) => vault::write_file(&path, &key, &content),
+        None => write_atomic(&path, content.as_bytes()),
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(sealed)
+}
+
+/// Changes a note outside the editor, e.g. appending to it or ticking off a task, and
+/// returns the new content. A missing note is edited as if it were empty.
+///
+/// The merge base stays as it was, so a tab with unsaved edits merges the change on save.
+pub async fn edit_note(
+    app: &tauri::AppHandle,
+    path: &str,
+    edit: impl FnOnce(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let _guard = EDIT_LOCK.lock().await;
+    let exists = Path::new(path).exists();
+    let existing = if exists {
+        read_note(app, path).await?
+    } else {
+        String::new()
+    };
+    let content = edit(&existing)?;
+    if !exists {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let sealed = write_note_atomically(app, path, &content).await?;
+    // Snapshots are stored in plaintext, so encrypted and vault notes keep no history
+    if !sealed {
+        history_commands::record_snapshot(app, path, &content);
+    }
+    git_commands::auto_commit(app, path);
+    reminder_commands::reschedule();
+    if let Some(root) = settings_commands::get_notes_root(app).await {
+        links::update_note_links(&root, Path::new(path), &content);
+    }
+    Ok(content)
+}
+
+/// Saves content to a specified file path, merging with external changes made since it was opened
 #[tauri::command]
-pub async fn save_file(path: String, content: String) -> Result<(), String> {
-    fs::write(&path, content).await.map_err(|e| e.to_string())
+pub async fn save_file(
+    app: tauri::AppHandle,
+    path: String,
+    content: String,
+    force: Option<bool>,
+) -> Result<SaveOutcome, String> {
+    // Held until the write, so an append or task edit in between is merged rather than lost
+    let _guard = EDIT_LOCK.lock().await;
+    if encryption_commands::is_encrypted_file(&path) {
+        return encryption_commands::save_encrypted(&app, &path, content).await;
+    }
+
+    let mut content = content;
+    let mut status = "saved";
+
+    if !force.unwrap_or(false) {
+        if let (Some(base), Ok(disk)) = (base_of(&path), read_note(&app, &path).await) {
+            // Another program changed the file since we last read or wrote it
+            if disk != base && disk != content {
+                let result = merge::merge3(&base, &content, &disk);
+                if !result.is_clean() {
+                    // The conflict markers include the disk side, so it becomes the new base
+                    remember_base(&path, &disk);
+                    return Ok(SaveOutcome {
+                        status: "conflict".to_string(),
+                        content: result.merged,
+                        conflicts: result.conflicts,
+                    });
+                }
+                content = result.merged;
+                status = "merged";
+            }
+        }
+    }
+
+    let sealed = write_note_atomically(&app, &path, &content).await?;
+    remember_base(&path, &content);
+    // Snapshots are stored in plaintext, so vault notes keep no history
+    if !sealed {
+        history_commands::record_snapshot(&app, &path, &content);
+    }
+    git_commands::auto_commit(&app, &path);
+    reminder_commands::reschedule();
+
+    // Keep the link graph in sync with the saved note
+    if let Some(root) = settings_commands::get_notes_root(&app).await {
+        links::update_note_links(&root, std::path::Path::new(&path), &content);
+    }
+
+    Ok(SaveOutcome {
+        status: status.to_string(),
+        content,
+        conflicts: Vec::new(),
+    })
 }
 
 /// Saves content to a file using a system save dialog
 #[tauri::command]
 pub async fn save_file_as(
+    app: tauri::AppHandle,
     content: String,
     default_format: String,
     default_name: String,
@@ -121,10 +322,20 @@ pub async fn save_file_as(
         .save_file();
 
     match file_path {
-        Some(path) => fs::write(&path, content)
-            .await
-            .map(|_| Some(path.to_string_lossy().to_string()))
-            .map_err(|e| e.to_string()),
+        Some(path) => {
+            let path = path.to_string_lossy().to_string();
+            let sealed = write_note_atomically(&app, &path, &content).await?;
+            remember_base(&path, &content);
+            if !sealed {
+                history_commands::record_snapshot(&app, &path, &content);
+            }
+            git_commands::auto_commit(&app, &path);
+            reminder_commands::reschedule();
+            if let Some(root) = settings_commands::get_notes_root(&app).await {
+                links::update_note_links(&root, Path::new(&path), &content);
+            }
+            Ok(Some(path))
+        }
         None => Ok(None), // User cancelled the dialog
     }
 }
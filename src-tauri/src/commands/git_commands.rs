@@ -0,0 +1,167 @@
+use crate::commands::settings_commands::{self, AppSettings};
+use crate::commands::vault_commands;
+use crate::crypto;
+use crate::git::{GitCommit, GitRepo, GitStatus};
+use crate::vault;
+use std::path::{Path, PathBuf};
+use tauri::api::dialog;
+use tauri::Manager;
+
+lazy_static::lazy_static! {
+    // Serializes git operations so concurrent saves do not fight over the index lock
+    static ref GIT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// Returns the settings and notes repository, failing if git integration is off
+async fn notes_repo(app: &tauri::AppHandle) -> Result<(AppSettings, GitRepo), String> {
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    if !settings.git_enabled {
+        return Err("Git integration is disabled".to_string());
+    }
+    let root = settings_commands::require_notes_root(app).await?;
+    Ok((settings, GitRepo::new(root)))
+}
+
+/// Points the configured remote at the URL from settings, if one is set
+async fn sync_remote(settings: &AppSettings, repo: &GitRepo) -> Result<(), String> {
+    match settings
+        .git_remote_url
+        .as_deref()
+        .filter(|url| !url.is_empty())
+    {
+        Some(url) => repo
+            .set_remote(&settings.git_remote_name, url)
+            .await
+            .map_err(|e| e.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Commits a saved note in the background when auto-commit is enabled
+pub fn auto_commit(app: &tauri::AppHandle, path: &str) {
+    let app = app.clone();
+    let path = PathBuf::from(path);
+    tauri::async_runtime::spawn(async move {
+        let settings = match settings_commands::load_settings(app.clone()).await {
+            Ok(settings) if settings.git_enabled && settings.git_auto_commit => settings,
+            _ => return,
+        };
+        let root = match settings.default_path.as_deref() {
+            Some(root) if path.starts_with(root) => root,
+            _ => return,
+        };
+        let repo = GitRepo::new(root);
+        let _guard = GIT_LOCK.lock().await;
+        if !repo.is_repo().await {
+            return;
+        }
+        if let Err(e) = repo.commit_file(&path).await {
+            eprintln!("Failed to auto-commit {}: {}", path.display(), e);
+        }
+    });
+}
+
+/// Turns the notes root into a git repository
+#[tauri::command]
+pub async fn git_init(app: tauri::AppHandle) -> Result<(), String> {
+    let (settings, repo) = notes_repo(&app).await?;
+    let _guard = GIT_LOCK.lock().await;
+    if !repo.is_repo().await {
+        repo.init().await.map_err(|e| e.to_string())?;
+    }
+    sync_remote(&settings, &repo).await
+}
+
+/// Lists uncommitted files and how far the branch is ahead of or behind its remote
+#[tauri::command]
+pub async fn git_status(app: tauri::AppHandle) -> Result<GitStatus, String> {
+    let (_, repo) = notes_repo(&app).await?;
+    let _guard = GIT_LOCK.lock().await;
+    repo.status().await.map_err(|e| e.to_string())
+}
+
+/// Lists the commits that changed a note, newest first
+#[tauri::command]
+pub async fn git_log_note(
+    app: tauri::AppHandle,
+    path: String,
+    limit: Option<usize>,
+) -> Result<Vec<GitCommit>, String> {
+    let (_, repo) = notes_repo(&app).await?;
+    repo.log_file(Path::new(&path), limit.unwrap_or(50))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Reads a note as it was in a given commit
+#[tauri::command]
+pub async fn git_show_note(
+    app: tauri::AppHandle,
+    path: String,
+    hash: String,
+) -> Result<String, String> {
+    let (_, repo) = notes_repo(&app).await?;
+    let data = repo
+        .show_file(Path::new(&path), &hash)
+        .await
+        .map_err(|e| e.to_string())?;
+    // Vault notes are committed sealed
+    if !crypto::is_sealed(&data) {
+        return Ok(String::from_utf8_lossy(&data).to_string());
+    }
+    let key = vault_commands::vault_for(&app, &path)
+        .await?
+        .ok_or_else(|| "This version was sealed by the vault".to_string())?;
+    vault::open(data, &key).map_err(|e| e.to_string())
+}
+
+/// True if earlier plaintext of `path`, a note or the whole notes root, sits in git commits
+async fn in_git_history(app: &tauri::AppHandle, path: &Path) -> bool {
+    let Some(root) = settings_commands::get_notes_root(app).await else {
+        return false;
+    };
+    let repo = GitRepo::new(&root);
+    if !path.starts_with(&root) || !repo.is_repo().await {
+        return false;
+    }
+    if path == root {
+        return repo.has_commits().await;
+    }
+    repo.log_file(path, 1)
+        .await
+        .is_ok_and(|commits| !commits.is_empty())
+}
+
+/// Warns that encrypting `path` leaves the plaintext committed before it in the git history
+pub async fn warn_plaintext_in_history(app: &tauri::AppHandle, path: &Path) {
+    if in_git_history(app, path).await {
+        dialog::message(
+            app.get_window("main").as_ref(),
+            "QuickNote",
+            "Earlier versions are still stored unencrypted in the git history of your notes \
+             folder. Rewrite that history if they must not be readable.",
+        );
+    }
+}
+
+/// Pulls changes from the configured remote, rebasing local commits on top
+#[tauri::command]
+pub async fn git_pull(app: tauri::AppHandle) -> Result<String, String> {
+    let (settings, repo) = notes_repo(&app).await?;
+    let _guard = GIT_LOCK.lock().await;
+    sync_remote(&settings, &repo).await?;
+    repo.pull(&settings.git_remote_name, settings.git_branch.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pushes committed notes to the configured remote
+#[tauri::command]
+pub async fn git_push(app: tauri::AppHandle) -> Result<String, String> {
+    let (settings, repo) = notes_repo(&app).await?;
+    let _guard = GIT_LOCK.lock().await;
+    sync_remote(&settings, &repo).await?;
+    repo.push(&settings.git_remote_name, settings.git_branch.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,128 @@
+use crate::commands::file_commands::{self, FilePayload};
+use crate::commands::vault_commands;
+use crate::diff::{self, DiffOptions, DiffResult};
+use crate::history::{self, HistoryStore, VersionInfo};
+use std::path::{Path, PathBuf};
+
+/// Helper function to get the version history folder under the app data directory
+pub fn get_history_dir(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+    let data_dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find app data directory"))?;
+
+    let history_dir = data_dir.join("history");
+    if !history_dir.exists() {
+        std::fs::create_dir_all(&history_dir)?;
+    }
+
+    Ok(history_dir)
+}
+
+fn history_store(app: &tauri::AppHandle) -> Result<HistoryStore, String> {
+    get_history_dir(app)
+        .map(HistoryStore::new)
+        .map_err(|e| e.to_string())
+}
+
+/// Records a snapshot of a saved file, logging instead of failing the save
+pub fn record_snapshot(app: &tauri::AppHandle, path: &str, content: &str) {
+    let result = history_store(app).and_then(|store| {
+        store
+            .record(Path::new(path), content, history::now_millis())
+            .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to record history for {}: {}", path, e);
+    }
+}
+
+/// Deletes the stored snapshots of a file, e.g. once it holds content that must not leak
+pub fn forget_history(app: &tauri::AppHandle, path: &str) {
+    let result = history_store(app)
+        .and_then(|store| store.remove(Path::new(path)).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to remove history for {}: {}", path, e);
+    }
+}
+
+/// Deletes the stored snapshots of every file under a folder
+pub fn forget_history_under(app: &tauri::AppHandle, root: &Path) {
+    let result =
+        history_store(app).and_then(|store| store.remove_under(root).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to remove history under {}: {}", root.display(), e);
+    }
+}
+
+/// Lists the stored versions of a file, newest first
+#[tauri::command]
+pub async fn list_versions(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<Vec<VersionInfo>, String> {
+    history_store(&app)?
+        .list(Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+/// Returns the content of a stored version
+#[tauri::command]
+pub async fn read_version(
+    app: tauri::AppHandle,
+    path: String,
+    version_id: String,
+) -> Result<String, String> {
+    history_store(&app)?
+        .read(Path::new(&path), &version_id)
+        .map_err(|e| e.to_string())
+}
+
+/// Compares two stored versions with the same line and word hunks as the other diff commands
+#[tauri::command]
+pub async fn diff_versions(
+    app: tauri::AppHandle,
+    path: String,
+    from_version: String,
+    to_version: String,
+    options: Option<DiffOptions>,
+) -> Result<DiffResult, String> {
+    let store = history_store(&app)?;
+    let old = store
+        .read(Path::new(&path), &from_version)
+        .map_err(|e| e.to_string())?;
+    let new = store
+        .read(Path::new(&path), &to_version)
+        .map_err(|e| e.to_string())?;
+
+    Ok(diff::diff_text(&old, &new, &options.unwrap_or_default()))
+}
+
+/// Restores a stored version to disk, snapshotting the current content first
+#[tauri::command]
+pub async fn restore_version(
+    app: tauri::AppHandle,
+    path: String,
+    version_id: String,
+) -> Result<FilePayload, String> {
+    let store = history_store(&app)?;
+    let content = store
+        .read(Path::new(&path), &version_id)
+        .map_err(|e| e.to_string())?;
+
+    // Keep the content being replaced so the restore itself can be undone
+    // Encrypted and vault notes keep no plaintext history
+    if !vault_commands::is_protected(&app, &path).await {
+        if let Ok(current) = file_commands::read_note(&app, &path).await {
+            record_snapshot(&app, &path, &current);
+        }
+    }
+
+    let sealed = file_commands::write_note_atomically(&app, &path, &content).await?;
+    file_commands::remember_base(&path, &content);
+    if !sealed {
+        record_snapshot(&app, &path, &content);
+    }
+
+    Ok(FilePayload { path, content })
+}
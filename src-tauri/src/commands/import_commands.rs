@@ -0,0 +1,52 @@
+use crate::commands::settings_commands::require_notes_root;
+use crate::commands::vault_commands;
+use crate::import::{self, ImportFormat, ImportReport};
+use std::path::{Component, Path, PathBuf};
+
+/// Folder under the notes root that imported notes go into by default
+const DEFAULT_IMPORT_FOLDER: &str = "Imported";
+
+/// Imports notes from an ENEX, HTML or JSON export into the notes directory
+///
+/// With `dry_run` set nothing is written and the report lists what would be created.
+#[tauri::command]
+pub async fn import_notes(
+    app: tauri::AppHandle,
+    source: String,
+    format: Option<String>,
+    folder: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    let root = require_notes_root(&app).await?;
+    let source = PathBuf::from(source);
+    if !source.exists() {
+        return Err(format!("Import source not found: {}", source.display()));
+    }
+
+    let format = match format.as_deref() {
+        Some(name) => ImportFormat::parse(name),
+        None => ImportFormat::detect(&source),
+    }
+    .map_err(|e| e.to_string())?;
+
+    let folder = folder.unwrap_or_else(|| DEFAULT_IMPORT_FOLDER.to_string());
+    let relative = Path::new(&folder);
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        return Err("Import folder must be a path inside the notes directory".to_string());
+    }
+    let target = root.join(relative);
+    let dry_run = dry_run.unwrap_or(false);
+    // Notes imported into the vault are sealed as they are written
+    let vault = vault_commands::vault_for(&app, &target.to_string_lossy()).await?;
+
+    // Parsing and conversion are synchronous and can take a while for large exports
+    tokio::task::spawn_blocking(move || {
+        import::import_notes(&source, format, &target, dry_run, vault.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
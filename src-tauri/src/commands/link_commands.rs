@@ -0,0 +1,87 @@
+use crate::commands::file_commands;
+use crate::commands::settings_commands::require_notes_root;
+use crate::commands::vault_commands;
+use crate::links::{self, Backlink, LinkKind};
+use std::path::{Path, PathBuf};
+
+/// Returns every note that links to the given note
+#[tauri::command]
+pub async fn get_backlinks(app: tauri::AppHandle, path: String) -> Result<Vec<Backlink>, String> {
+    let root = require_notes_root(&app).await?;
+    // While the vault is locked only its plaintext notes can be searched
+    let vault = vault_commands::vault_for(&app, &root.to_string_lossy())
+        .await
+        .unwrap_or(None);
+    Ok(links::get_backlinks(
+        &root,
+        Path::new(&path),
+        vault.as_deref(),
+    ))
+}
+
+/// Resolves a `[[Note Name]]` or relative Markdown link written in `from`
+#[tauri::command]
+pub async fn resolve_link(
+    app: tauri::AppHandle,
+    from: String,
+    target: String,
+) -> Result<Option<String>, String> {
+    let root = require_notes_root(&app).await?;
+    let target = target.trim();
+
+    let (kind, target) = match target
+        .strip_prefix("[[")
+        .and_then(|inner| inner.strip_suffix("]]"))
+    {
+        // Drop any #heading or |alias part of a wiki link
+        Some(inner) => (
+            LinkKind::Wiki,
+            inner.split(['#', '|']).next().unwrap_or(inner),
+        ),
+        None => (LinkKind::Markdown, target),
+    };
+
+    Ok(links::resolve_target(&root, Path::new(&from), kind, target)
+        .map(|path| path.to_string_lossy().to_string()))
+}
+
+/// Only note files inside the notes root may be renamed or moved
+fn note_in_root(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let path = links::normalize_path(Path::new(path));
+    if !path.is_absolute() || !path.starts_with(links::normalize_path(root)) {
+        return Err(format!(
+            "Only notes inside the notes directory can be renamed: {}",
+            path.display()
+        ));
+    }
+    if !links::is_note_file(&path) {
+        return Err(format!("Not a note file: {}", path.display()));
+    }
+    Ok(path)
+}
+
+/// Renames or moves a note and updates every link pointing at it
+#[tauri::command]
+pub async fn rename_note(
+    app: tauri::AppHandle,
+    old_path: String,
+    new_path: String,
+) -> Result<Vec<String>, String> {
+    let root = require_notes_root(&app).await?;
+    let (old, new) = (
+        note_in_root(&root, &old_path)?,
+        note_in_root(&root, &new_path)?,
+    );
+    if new.exists() {
+        return Err(format!("A file already exists at {}", new.display()));
+    }
+    // Linking notes in a locked vault cannot be rewritten, so the rename waits for an unlock
+    let vault = vault_commands::vault_for(&app, &root.to_string_lossy()).await?;
+    let updated =
+        links::rename_note(&root, &old, &new, vault.as_deref()).map_err(|e| e.to_string())?;
+    file_commands::move_base(&old_path, &new_path);
+    Ok(updated
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect())
+}
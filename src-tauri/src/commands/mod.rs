@@ -1,2 +1,23 @@
+pub mod api_commands;
+pub mod backup_commands;
+pub mod capture_commands;
+pub mod cli_commands;
+pub mod clipboard_commands;
+pub mod daily_note_commands;
+pub mod diff_commands;
+pub mod encryption_commands;
+pub mod export_commands;
 pub mod file_commands;
+pub mod git_commands;
+pub mod history_commands;
+pub mod import_commands;
+pub mod link_commands;
+pub mod reminder_commands;
+pub mod search_commands;
 pub mod settings_commands;
+pub mod snippet_commands;
+pub mod sync_commands;
+pub mod task_commands;
+pub mod template_commands;
+pub mod url_commands;
+pub mod vault_commands;
@@ -0,0 +1,202 @@
+use crate::commands::settings_commands::{self, get_settings_path};
+use crate::commands::task_commands::edit_task_line;
+use crate::commands::vault_commands;
+use crate::storage::{load_state, save_state};
+use crate::tasks::{self, TaskLine};
+use chrono::{Duration as ChronoDuration, Local, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::api::notification::Notification;
+use tauri::Manager;
+
+/// How often the notes are rescanned for added or changed reminders
+const RESCAN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Minutes a reminder is pushed back when snoozed without a duration
+const DEFAULT_SNOOZE_MINUTES: u32 = 10;
+
+/// Format of the last check time in reminders.json
+const STATE_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+lazy_static::lazy_static! {
+    // Open tasks with a due date, soonest first
+    static ref SCHEDULE: Mutex<Vec<Reminder>> = Mutex::new(Vec::new());
+    // Wakes the scheduler to rescan, e.g. after a note was saved
+    static ref RESCHEDULE: tokio::sync::Notify = tokio::sync::Notify::new();
+}
+
+/// An open task with a due date
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub path: String,
+    #[serde(flatten)]
+    pub task: TaskLine,
+}
+
+impl Reminder {
+    fn due(&self) -> NaiveDateTime {
+        self.task.due.unwrap_or_default()
+    }
+}
+
+/// When reminders were last checked, so ones that fell due while the app was closed still fire
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ReminderState {
+    last_check: Option<String>,
+}
+
+fn state_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    get_settings_path(app)
+        .map(|settings| settings.with_file_name("reminders.json"))
+        .map_err(|e| e.to_string())
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// Finds the open tasks with a due date in the notes root
+async fn scan(app: &tauri::AppHandle) -> Result<Vec<Reminder>, String> {
+    let root = match settings_commands::get_notes_root(app).await {
+        Some(root) => root,
+        None => return Ok(Vec::new()),
+    };
+    let vault = vault_commands::vault_for(app, &root.to_string_lossy())
+        .await
+        .unwrap_or(None);
+    let notes = tokio::task::spawn_blocking(move || tasks::scan_notes(&root, vault.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut reminders: Vec<Reminder> = notes
+        .into_iter()
+        .flat_map(|(path, tasks)| {
+            let path = path.to_string_lossy().to_string();
+            tasks
+                .into_iter()
+                .filter(|task| !task.done && task.due.is_some())
+                .map(move |task| Reminder {
+                    path: path.clone(),
+                    task,
+                })
+        })
+        .collect();
+    reminders.sort_by_key(Reminder::due);
+    Ok(reminders)
+}
+
+async fn notify(app: &tauri::AppHandle, reminder: &Reminder) {
+    let name = Path::new(&reminder.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    // Notifications stay on screen and in the system's history, so encrypted tasks stay hidden
+    let body = if vault_commands::is_protected(app, &reminder.path).await {
+        "A task in an encrypted note is due"
+    } else {
+        reminder.task.text.as_str()
+    };
+    if let Err(e) = Notification::new(&app.config().tauri.bundle.identifier)
+        .title(format!("Reminder: {}", name))
+        .body(body)
+        .show()
+    {
+        eprintln!("Failed to show reminder notification: {}", e);
+    }
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("reminder-due", reminder);
+    }
+}
+
+/// Wakes the scheduler so it picks up changed due dates right away
+pub fn reschedule() {
+    RESCHEDULE.notify_one();
+}
+
+/// Fires a notification when each open task in the notes root falls due
+pub fn start_reminder_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state_path = match state_path(&app) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Failed to start reminders: {}", e);
+                return;
+            }
+        };
+        let state: ReminderState = load_state(&state_path).unwrap_or_default();
+        let mut last_check = state
+            .last_check
+            .and_then(|time| NaiveDateTime::parse_from_str(&time, STATE_TIME_FORMAT).ok())
+            .unwrap_or_else(now);
+
+        loop {
+            let reminders = scan(&app).await.unwrap_or_else(|e| {
+                eprintln!("Failed to scan notes for reminders: {}", e);
+                Vec::new()
+            });
+            let checked_at = now();
+            for reminder in &reminders {
+                if reminder.due() > last_check && reminder.due() <= checked_at {
+                    notify(&app, reminder).await;
+                }
+            }
+            last_check = checked_at;
+            let state = ReminderState {
+                last_check: Some(last_check.format(STATE_TIME_FORMAT).to_string()),
+            };
+            if let Err(e) = save_state(&state_path, &state) {
+                eprintln!("Failed to save reminder state: {}", e);
+            }
+
+            // Sleep until the next reminder is due, rescanning now and then for edits
+            let wait = reminders
+                .iter()
+                .map(Reminder::due)
+                .find(|due| *due > checked_at)
+                .and_then(|due| (due - checked_at).to_std().ok())
+                .map_or(RESCAN_INTERVAL, |until_due| until_due.min(RESCAN_INTERVAL));
+            *SCHEDULE.lock().unwrap() = reminders;
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = RESCHEDULE.notified() => {}
+            }
+        }
+    });
+}
+
+/// Lists the open tasks with a due date, soonest first
+#[tauri::command]
+pub async fn list_reminders() -> Result<Vec<Reminder>, String> {
+    Ok(SCHEDULE.lock().unwrap().clone())
+}
+
+/// Ticks off the task behind a reminder; `raw` is the line as it was listed
+#[tauri::command]
+pub async fn complete_reminder(
+    app: tauri::AppHandle,
+    path: String,
+    line: usize,
+    raw: String,
+) -> Result<(), String> {
+    edit_task_line(&app, &path, line, &raw, |text| tasks::set_done(text, true)).await?;
+    Ok(())
+}
+
+/// Moves a reminder's due date to `minutes` from now, 10 by default
+#[tauri::command]
+pub async fn snooze_reminder(
+    app: tauri::AppHandle,
+    path: String,
+    line: usize,
+    raw: String,
+    minutes: Option<u32>,
+) -> Result<(), String> {
+    let minutes = minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES).max(1);
+    // Due dates are written to the minute, so round up rather than fire early
+    let due =
+        now().with_second(0).unwrap_or_else(now) + ChronoDuration::minutes(i64::from(minutes) + 1);
+    edit_task_line(&app, &path, line, &raw, |text| tasks::set_due(text, due)).await?;
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::commands::{settings_commands, vault_commands};
+use crate::search::{self, SearchHit};
+
+/// Hits returned when the caller does not ask for a limit
+const DEFAULT_LIMIT: usize = 100;
+
+/// Searches the notes root, including vault notes while the vault is unlocked if
+/// `include_vault` is set
+pub async fn search(
+    app: &tauri::AppHandle,
+    query: &str,
+    limit: Option<usize>,
+    include_vault: bool,
+) -> Result<Vec<SearchHit>, String> {
+    let root = settings_commands::require_notes_root(app).await?;
+    let vault = if include_vault {
+        vault_commands::vault_for(app, &root.to_string_lossy())
+            .await
+            .unwrap_or(None)
+    } else {
+        None
+    };
+    let query = query.to_string();
+    tokio::task::spawn_blocking(move || {
+        search::search_notes(
+            &root,
+            &query,
+            limit.unwrap_or(DEFAULT_LIMIT),
+            vault.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Finds the lines of notes that contain `query`, ignoring case
+#[tauri::command]
+pub async fn search_notes(
+    app: tauri::AppHandle,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    search(&app, &query, limit, true).await
+}
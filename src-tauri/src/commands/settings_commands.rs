@@ -1,3 +1,4 @@
+use crate::commands::{api_commands, clipboard_commands};
 use crate::menu;
 use crate::shortcuts;
 use serde::{Deserialize, Serialize};
@@ -5,6 +6,7 @@ use std::path::PathBuf;
 use tokio::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub theme: String,
     pub language: String,
@@ -13,6 +15,61 @@ pub struct AppSettings {
     pub new_note_shortcut: String,
     pub close_tab_shortcut: String,
     pub toggle_window_shortcut: String,
+    pub daily_note_shortcut: String,
+    /// Folder under the notes root that holds daily notes
+    pub daily_notes_folder: String,
+    /// strftime-style pattern used to name daily notes, e.g. "%Y-%m-%d"
+    pub daily_note_pattern: String,
+    /// Optional file used as the body of newly created daily notes
+    pub daily_note_template: Option<String>,
+    /// Page size used for PDF export: "a4", "a5", "letter" or "legal"
+    pub pdf_page_size: String,
+    pub pdf_margin_mm: f32,
+    /// Font embedded in exported PDFs; a system font is used when unset
+    pub pdf_font_path: Option<String>,
+    /// Fallback font for characters the main font lacks, e.g. CJK
+    pub pdf_cjk_font_path: Option<String>,
+    pub auto_backup_enabled: bool,
+    /// Folder scheduled backups are written to
+    pub auto_backup_dir: Option<String>,
+    pub auto_backup_interval_hours: u32,
+    /// Number of scheduled backups to keep; 0 keeps all
+    pub auto_backup_keep: usize,
+    /// Scheduled backups older than this are deleted; 0 disables the limit
+    pub auto_backup_max_age_days: u32,
+    /// Use the notes root as a git repository
+    pub git_enabled: bool,
+    /// Commit each note when it is saved
+    pub git_auto_commit: bool,
+    pub git_remote_name: String,
+    pub git_remote_url: Option<String>,
+    /// Remote branch to pull from and push to; the current branch when unset
+    pub git_branch: Option<String>,
+    /// WebDAV collection the notes root is synced with
+    pub webdav_url: Option<String>,
+    pub webdav_username: Option<String>,
+    pub webdav_password: Option<String>,
+    /// Folder, e.g. inside Dropbox or Syncthing, the notes root is synced with
+    pub sync_folder_path: Option<String>,
+    /// Minutes an unlocked encrypted note stays unlocked without being used
+    pub encryption_idle_minutes: u32,
+    /// Minutes the unlocked vault may sit unused before it locks; 0 never locks it
+    pub vault_idle_minutes: u32,
+    /// Serves the local scripting API on the loopback interface
+    pub local_api_enabled: bool,
+    /// Port of the local API; 0 picks a free one, see local-api.json in the config dir
+    pub local_api_port: u16,
+    /// Note that captured fragments are appended to; relative paths are inside the notes root
+    pub capture_note_path: Option<String>,
+    /// Puts a timestamp header above each captured fragment
+    pub capture_timestamp: bool,
+    /// Line written between captured fragments, e.g. "---"
+    pub capture_separator: Option<String>,
+    pub capture_shortcut: String,
+    /// Keeps a history of copied text; passwords and copies from encrypted notes are skipped
+    pub clipboard_history_enabled: bool,
+    /// Number of unpinned clipboard entries to keep
+    pub clipboard_history_limit: usize,
 }
 
 impl Default for AppSettings {
@@ -25,12 +82,44 @@ impl Default for AppSettings {
             new_note_shortcut: "CmdOrCtrl+Option+T".to_string(),
             close_tab_shortcut: "CmdOrCtrl+Option+Y".to_string(),
             toggle_window_shortcut: "CmdOrCtrl+Option+U".to_string(),
+            daily_note_shortcut: "CmdOrCtrl+Option+D".to_string(),
+            daily_notes_folder: "daily".to_string(),
+            daily_note_pattern: "%Y-%m-%d".to_string(),
+            daily_note_template: None,
+            pdf_page_size: "a4".to_string(),
+            pdf_margin_mm: 20.0,
+            pdf_font_path: None,
+            pdf_cjk_font_path: None,
+            auto_backup_enabled: false,
+            auto_backup_dir: None,
+            auto_backup_interval_hours: 24,
+            auto_backup_keep: 10,
+            auto_backup_max_age_days: 30,
+            git_enabled: false,
+            git_auto_commit: true,
+            git_remote_name: "origin".to_string(),
+            git_remote_url: None,
+            git_branch: None,
+            webdav_url: None,
+            webdav_username: None,
+            webdav_password: None,
+            sync_folder_path: None,
+            encryption_idle_minutes: 5,
+            vault_idle_minutes: 15,
+            local_api_enabled: false,
+            local_api_port: 0,
+            capture_note_path: None,
+            capture_timestamp: true,
+            capture_separator: None,
+            capture_shortcut: "CmdOrCtrl+Option+A".to_string(),
+            clipboard_history_enabled: false,
+            clipboard_history_limit: 200,
         }
     }
 }
 
 /// Helper function to get the path to the settings.json file
-fn get_settings_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+pub fn get_settings_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
     let config_dir = app
         .path_resolver()
         .app_config_dir()
@@ -44,6 +133,23 @@ fn get_settings_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
     Ok(config_dir.join("settings.json"))
 }
 
+/// Returns the configured notes root, if a default path has been set
+pub async fn get_notes_root(app: &tauri::AppHandle) -> Option<PathBuf> {
+    load_settings(app.clone())
+        .await
+        .ok()
+        .and_then(|settings| settings.default_path)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Returns the notes root or an error if no default path is configured
+pub async fn require_notes_root(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    get_notes_root(app)
+        .await
+        .ok_or_else(|| "No notes directory configured".to_string())
+}
+
 /// Loads application settings from settings.json
 #[tauri::command]
 pub async fn load_settings(app: tauri::AppHandle) -> Result<AppSettings, String> {
@@ -72,6 +178,10 @@ pub async fn save_settings(app: tauri::AppHandle, settings: AppSettings) -> Resu
         .await
         .map_err(|e| e.to_string())?;
 
+    // Turning the local API or clipboard history on or off takes effect right away
+    api_commands::apply_settings(&app).await?;
+    clipboard_commands::apply_settings(&app).await?;
+
     Ok(())
 }
 
@@ -85,6 +195,8 @@ pub async fn update_shortcut(
     match action.as_deref() {
         Some("close_tab") => shortcuts::register_close_tab_shortcut(&app, &shortcut),
         Some("toggle_window") => shortcuts::register_toggle_window_shortcut(&app, &shortcut),
+        Some("daily_note") => shortcuts::register_daily_note_shortcut(&app, &shortcut),
+        Some("capture") => shortcuts::register_capture_shortcut(&app, &shortcut),
         Some("new_note") | None => shortcuts::register_new_note_shortcut(&app, &shortcut),
         Some(other) => {
             eprintln!("Unknown shortcut action: {}", other);
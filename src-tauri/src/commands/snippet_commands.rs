@@ -0,0 +1,119 @@
+use crate::menu;
+use crate::snippets::{self, ExpandedSnippet, Snippet, SnippetContext};
+use crate::storage::{load_state, save_state};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{ClipboardManager, Manager};
+
+lazy_static::lazy_static! {
+    // Counters are read, bumped and written back, so expansions run one at a time
+    static ref COUNTERS_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Helper function to get the snippets folder under the config directory
+pub fn get_snippets_dir(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+    let config_dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find app config directory"))?;
+
+    let snippets_dir = config_dir.join("snippets");
+    if !snippets_dir.exists() {
+        std::fs::create_dir_all(&snippets_dir)?;
+    }
+
+    Ok(snippets_dir)
+}
+
+fn counters_path(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+    Ok(get_snippets_dir(app)?.with_file_name("snippet-counters.json"))
+}
+
+/// Rescans the snippets folder and refreshes the tray submenu
+pub fn refresh_snippet_menu(app: &tauri::AppHandle) -> Vec<Snippet> {
+    let snippets = match get_snippets_dir(app) {
+        Ok(dir) => snippets::list_snippets(&dir),
+        Err(e) => {
+            eprintln!("Failed to read snippets folder: {}", e);
+            Vec::new()
+        }
+    };
+    menu::update_tray_menu_with_snippets(
+        app,
+        snippets
+            .iter()
+            .map(|snippet| snippet.name.clone())
+            .collect(),
+    );
+    snippets
+}
+
+/// Expands a snippet, found by name or trigger, bumping the counters it uses
+pub async fn expand_named_snippet(
+    app: &tauri::AppHandle,
+    key: &str,
+    fields: HashMap<String, String>,
+) -> Result<ExpandedSnippet, String> {
+    let dir = get_snippets_dir(app).map_err(|e| e.to_string())?;
+    let all = snippets::list_snippets(&dir);
+    let snippet =
+        snippets::find_snippet(&all, key).ok_or_else(|| format!("Snippet not found: {}", key))?;
+
+    let mut context = SnippetContext::new();
+    context.fields = fields;
+    // Only touch the clipboard when the snippet asks for it
+    if snippets::uses_placeholder(&snippet.body, "clipboard") {
+        context.clipboard = app.clipboard_manager().read_text().unwrap_or(None);
+    }
+    if !snippets::uses_placeholder(&snippet.body, "counter") {
+        let mut counters = BTreeMap::new();
+        return Ok(snippets::expand_snippet(
+            &snippet.body,
+            &context,
+            &mut counters,
+        ));
+    }
+
+    let path = counters_path(app).map_err(|e| e.to_string())?;
+    let _guard = COUNTERS_LOCK.lock().unwrap();
+    let mut counters: BTreeMap<String, u64> = load_state(&path).map_err(|e| e.to_string())?;
+    let expanded = snippets::expand_snippet(&snippet.body, &context, &mut counters);
+    save_state(&path, &counters).map_err(|e| e.to_string())?;
+    Ok(expanded)
+}
+
+/// Asks the frontend to fill in the snippet's fields and insert it into the active tab
+pub fn emit_insert_snippet(app: &tauri::AppHandle, name: &str) {
+    let window = match app.get_window("main") {
+        Some(window) => window,
+        None => return,
+    };
+    let fields = get_snippets_dir(app)
+        .map(|dir| snippets::list_snippets(&dir))
+        .unwrap_or_default()
+        .into_iter()
+        .find(|snippet| snippet.name == name)
+        .map(|snippet| snippet.fields)
+        .unwrap_or_default();
+    let _ = window.show();
+    let _ = window.set_focus();
+    let _ = window.emit("insert-snippet", json!({ "name": name, "fields": fields }));
+}
+
+/// Lists the snippets in the snippets folder with their triggers and fields
+#[tauri::command]
+pub async fn list_snippets(app: tauri::AppHandle) -> Result<Vec<Snippet>, String> {
+    Ok(refresh_snippet_menu(&app))
+}
+
+/// Expands a snippet, found by name or trigger, with values for its fields
+#[tauri::command]
+pub async fn expand_snippet(
+    app: tauri::AppHandle,
+    name: String,
+    fields: Option<HashMap<String, String>>,
+) -> Result<ExpandedSnippet, String> {
+    expand_named_snippet(&app, &name, fields.unwrap_or_default()).await
+}
@@ -0,0 +1,151 @@
+use crate::commands::{settings_commands, vault_commands};
+use crate::history::content_hash;
+use crate::sync::folder::FolderSync;
+use crate::sync::webdav::{WebDavConfig, WebDavSync};
+use crate::sync::{SyncEvent, SyncReport};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::Manager;
+
+#[derive(Default)]
+struct SyncState {
+    last_run: Option<DateTime<Local>>,
+    last_report: Option<SyncReport>,
+    last_error: Option<String>,
+}
+
+lazy_static::lazy_static! {
+    // Held for the duration of a sync so two runs never touch the same files at once
+    static ref SYNC_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+    static ref SYNC_STATE: Mutex<SyncState> = Mutex::new(SyncState::default());
+}
+
+/// Outcome of the last sync reported to the frontend; times are RFC 3339
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncStatus {
+    pub running: bool,
+    pub last_run: Option<String>,
+    pub last_report: Option<SyncReport>,
+    pub last_error: Option<String>,
+}
+
+/// Helper function to get the folder holding sync manifests under the app data directory
+fn get_sync_dir(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+    let data_dir = app
+        .path_resolver()
+        .app_data_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find app data directory"))?;
+
+    let sync_dir = data_dir.join("sync");
+    if !sync_dir.exists() {
+        std::fs::create_dir_all(&sync_dir)?;
+    }
+
+    Ok(sync_dir)
+}
+
+/// Forwards sync progress to the main window as "sync-status" events
+fn emit_progress(app: &tauri::AppHandle) -> impl FnMut(SyncEvent) {
+    let window = app.get_window("main");
+    move |event| {
+        if let Some(window) = &window {
+            let _ = window.emit("sync-status", &event);
+        }
+    }
+}
+
+fn record_result(result: &Result<SyncReport, String>) {
+    let mut state = SYNC_STATE.lock().unwrap();
+    state.last_run = Some(Local::now());
+    match result {
+        Ok(report) => {
+            state.last_report = Some(report.clone());
+            state.last_error = None;
+        }
+        Err(e) => state.last_error = Some(e.clone()),
+    }
+}
+
+/// Mirrors the notes root with the configured WebDAV server in both directions
+#[tauri::command]
+pub async fn sync_webdav(app: tauri::AppHandle) -> Result<SyncReport, String> {
+    let _guard = SYNC_LOCK
+        .try_lock()
+        .map_err(|_| "Sync already running".to_string())?;
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let url = settings
+        .webdav_url
+        .filter(|url| !url.trim().is_empty())
+        .ok_or_else(|| "No WebDAV server configured".to_string())?;
+    let root = settings_commands::require_notes_root(&app).await?;
+    // Downloads into a vault are sealed, so a locked vault has to be unlocked first
+    let vault = vault_commands::vault_for(&app, &root.to_string_lossy()).await?;
+    // One manifest per server so switching servers does not confuse change detection
+    let manifest_path = get_sync_dir(&app).map_err(|e| e.to_string())?.join(format!(
+        "webdav-{}.json",
+        &content_hash(url.as_bytes())[..16]
+    ));
+    let config = WebDavConfig {
+        url,
+        username: settings.webdav_username.filter(|name| !name.is_empty()),
+        password: settings.webdav_password,
+    };
+
+    let mut on_event = emit_progress(&app);
+    let result = tokio::task::spawn_blocking(move || {
+        WebDavSync::new(&config, &root, &manifest_path, vault.as_deref())?.run(&mut on_event)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string());
+
+    record_result(&result);
+    result
+}
+
+/// Mirrors the notes root with the configured sync folder in both directions
+#[tauri::command]
+pub async fn sync_folder(app: tauri::AppHandle) -> Result<SyncReport, String> {
+    let _guard = SYNC_LOCK
+        .try_lock()
+        .map_err(|_| "Sync already running".to_string())?;
+    let settings = settings_commands::load_settings(app.clone()).await?;
+    let mirror = settings
+        .sync_folder_path
+        .filter(|path| !path.trim().is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| "No sync folder configured".to_string())?;
+    let root = settings_commands::require_notes_root(&app).await?;
+    let vault = vault_commands::vault_for(&app, &root.to_string_lossy()).await?;
+    // Each mirror folder has its own state and merge bases
+    let state_dir = get_sync_dir(&app).map_err(|e| e.to_string())?.join(format!(
+        "folder-{}",
+        &content_hash(mirror.to_string_lossy().as_bytes())[..16]
+    ));
+
+    let mut on_event = emit_progress(&app);
+    let result = tokio::task::spawn_blocking(move || {
+        FolderSync::new(&root, &mirror, &state_dir, vault.as_deref())?.run(&mut on_event)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string());
+
+    record_result(&result);
+    result
+}
+
+/// Reports whether a sync is running and how the last one went
+#[tauri::command]
+pub async fn get_sync_status() -> Result<SyncStatus, String> {
+    let running = SYNC_LOCK.try_lock().is_err();
+    let state = SYNC_STATE.lock().unwrap();
+    Ok(SyncStatus {
+        running,
+        last_run: state.last_run.map(|time| time.to_rfc3339()),
+        last_report: state.last_report.clone(),
+        last_error: state.last_error.clone(),
+    })
+}
@@ -0,0 +1,195 @@
+use crate::commands::{api_commands, file_commands, settings_commands, vault_commands};
+use crate::links;
+use crate::tasks::{self, TaskFilter, TaskLine, TaskQuery};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::Manager;
+
+/// A task with where it was found
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexedTask {
+    /// File the task is in, if the tab has been saved
+    pub path: Option<String>,
+    /// Open tab the task was read from instead of the file, e.g. because it has unsaved edits
+    pub tab_id: Option<String>,
+    #[serde(flatten)]
+    pub task: TaskLine,
+}
+
+/// An open tab as described by the webview
+#[derive(Debug, Deserialize)]
+struct TabSource {
+    id: String,
+    path: Option<String>,
+    content: String,
+    unsaved: bool,
+}
+
+fn open_tabs(app: &tauri::AppHandle) -> Vec<TabSource> {
+    match api_commands::ask_frontend(app, "tab_contents", Value::Null) {
+        Ok(value) => serde_json::from_value(value).unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to read open tabs for tasks: {}", e.message);
+            Vec::new()
+        }
+    }
+}
+
+/// Rewrites a task line in a note inside the notes root and tells the webview
+pub async fn edit_task_line(
+    app: &tauri::AppHandle,
+    path: &str,
+    line: usize,
+    raw: &str,
+    edit: impl FnOnce(&str) -> Option<String>,
+) -> Result<String, String> {
+    let root = settings_commands::require_notes_root(app).await?;
+    let normalized = links::normalize_path(Path::new(path));
+    if !normalized.starts_with(links::normalize_path(&root)) || !links::is_note_file(&normalized) {
+        return Err("Only notes inside the notes directory can be changed".to_string());
+    }
+
+    let content = file_commands::edit_note(app, path, |content| {
+        if content.is_empty() {
+            return Err("The note no longer exists".to_string());
+        }
+        tasks::edit_task(content, line, raw, edit).map_err(|e| e.to_string())
+    })
+    .await?;
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("note-changed", json!({ "path": path, "content": content }));
+    }
+    Ok(content)
+}
+
+/// Ticks or unticks a task in an unsaved tab, leaving the tab unsaved
+fn toggle_tab_task(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    line: usize,
+    raw: &str,
+    done: Option<bool>,
+) -> Result<(), String> {
+    let tab = api_commands::ask_frontend(app, "get_tab", json!({ "id": tab_id }))
+        .map_err(|e| e.message)?;
+    let existing = match &tab {
+        Value::Null => return Err(format!("No tab with id {}", tab_id)),
+        tab => tab["content"].as_str().unwrap_or_default(),
+    };
+    // Checked here so a missing task is reported; the webview then edits the text as it is
+    // by the time the event arrives, so typing in between is kept
+    tasks::edit_task(existing, line, raw, |text| toggle(text, line, done))
+        .map_err(|e| e.to_string())?;
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit(
+            "toggle-tab-task",
+            json!({ "id": tab_id, "line": line, "raw": raw, "done": done }),
+        );
+    }
+    Ok(())
+}
+
+/// Sets a task line's checkbox, flipping it when `done` is not given
+fn toggle(text: &str, line: usize, done: Option<bool>) -> Option<String> {
+    let current = tasks::parse_task_line(text, line)?;
+    tasks::set_done(text, done.unwrap_or(!current.done))
+}
+
+/// Collects the checkbox items of the notes root and open tabs that match `filter`.
+/// Unsaved tabs are read instead of their file, since they hold the newer text.
+#[tauri::command]
+pub async fn list_tasks(
+    app: tauri::AppHandle,
+    filter: Option<TaskFilter>,
+) -> Result<Vec<IndexedTask>, String> {
+    let query = TaskQuery::new(&filter.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let root = settings_commands::get_notes_root(&app).await;
+    let app_handle = app.clone();
+    let tabs = tokio::task::spawn_blocking(move || open_tabs(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Saved tabs of notes in the root are covered by the scan below
+    let normalized_root = root.as_deref().map(links::normalize_path);
+    let in_root = |path: &str| {
+        normalized_root
+            .as_ref()
+            .is_some_and(|root| links::normalize_path(Path::new(path)).starts_with(root))
+    };
+    let tabs: Vec<TabSource> = tabs
+        .into_iter()
+        .filter(|tab| tab.unsaved || !tab.path.as_deref().is_some_and(in_root))
+        .collect();
+
+    let mut found = Vec::new();
+    for tab in &tabs {
+        found.extend(
+            tasks::parse_tasks(&tab.content)
+                .into_iter()
+                .filter(|task| query.matches(task))
+                .map(|task| IndexedTask {
+                    path: tab.path.clone(),
+                    tab_id: Some(tab.id.clone()),
+                    task,
+                }),
+        );
+    }
+
+    if let Some(root) = root {
+        let covered: HashSet<String> = tabs.into_iter().filter_map(|tab| tab.path).collect();
+        let vault = vault_commands::vault_for(&app, &root.to_string_lossy())
+            .await
+            .unwrap_or(None);
+        let notes = tokio::task::spawn_blocking(move || tasks::scan_notes(&root, vault.as_deref()))
+            .await
+            .map_err(|e| e.to_string())?;
+        for (path, note_tasks) in notes {
+            let path = path.to_string_lossy().to_string();
+            if covered.contains(&path) {
+                continue;
+            }
+            found.extend(
+                note_tasks
+                    .into_iter()
+                    .filter(|task| query.matches(task))
+                    .map(|task| IndexedTask {
+                        path: Some(path.clone()),
+                        tab_id: None,
+                        task,
+                    }),
+            );
+        }
+    }
+    Ok(found)
+}
+
+/// Ticks or unticks a task, flipping it when `done` is not given. `raw` is the line as it
+/// was listed; the edit is refused if that line has changed since.
+#[tauri::command]
+pub async fn toggle_task(
+    app: tauri::AppHandle,
+    path: Option<String>,
+    tab_id: Option<String>,
+    line: usize,
+    raw: String,
+    done: Option<bool>,
+) -> Result<(), String> {
+    match (tab_id, path) {
+        (Some(tab_id), _) => {
+            let app_handle = app.clone();
+            tokio::task::spawn_blocking(move || {
+                toggle_tab_task(&app_handle, &tab_id, line, &raw, done)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        }
+        (None, Some(path)) => {
+            edit_task_line(&app, &path, line, &raw, |text| toggle(text, line, done))
+                .await
+                .map(|_| ())
+        }
+        (None, None) => Err("A task needs a path or a tab".to_string()),
+    }
+}
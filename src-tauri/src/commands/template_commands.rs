@@ -0,0 +1,94 @@
+use crate::menu;
+use crate::templates::{self, ExpandedTemplate, TemplateContext, TemplateInfo};
+use std::path::PathBuf;
+use tauri::{ClipboardManager, Manager};
+use tokio::fs;
+
+/// Helper function to get the templates folder under the config directory
+pub fn get_templates_dir(app: &tauri::AppHandle) -> anyhow::Result<PathBuf> {
+    let config_dir = app
+        .path_resolver()
+        .app_config_dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find app config directory"))?;
+
+    let templates_dir = config_dir.join("templates");
+    if !templates_dir.exists() {
+        std::fs::create_dir_all(&templates_dir)?;
+    }
+
+    Ok(templates_dir)
+}
+
+/// Expands the named template into the content of a new note
+pub async fn expand_named_template(
+    app: &tauri::AppHandle,
+    name: &str,
+    title: Option<String>,
+) -> Result<ExpandedTemplate, String> {
+    let dir = get_templates_dir(app).map_err(|e| e.to_string())?;
+    let path = templates::find_template(&dir, name)
+        .ok_or_else(|| format!("Template not found: {}", name))?;
+    let template = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read template: {}", e))?;
+
+    let mut context = TemplateContext::new(title.unwrap_or_else(|| name.to_string()));
+    // Only touch the clipboard when the template asks for it
+    if templates::uses_placeholder(&template, "clipboard") {
+        context.clipboard = app.clipboard_manager().read_text().unwrap_or(None);
+    }
+
+    Ok(templates::expand_template(&template, &context))
+}
+
+/// Expands a template and asks the frontend to open it in a new tab
+pub async fn emit_note_from_template(app: &tauri::AppHandle, name: &str) {
+    let window = match app.get_window("main") {
+        Some(window) => window,
+        None => return,
+    };
+    match expand_named_template(app, name, None).await {
+        Ok(note) => {
+            let _ = window.emit("new-note-from-template", note);
+        }
+        Err(e) => {
+            eprintln!("Failed to create note from template {}: {}", name, e);
+            let _ = window.emit("error", e);
+        }
+    }
+}
+
+/// Rescans the templates folder and refreshes the tray submenu
+pub fn refresh_template_menu(app: &tauri::AppHandle) -> Vec<TemplateInfo> {
+    let templates = match get_templates_dir(app) {
+        Ok(dir) => templates::list_templates(&dir),
+        Err(e) => {
+            eprintln!("Failed to read templates folder: {}", e);
+            Vec::new()
+        }
+    };
+    menu::update_tray_menu_with_templates(
+        app,
+        templates
+            .iter()
+            .map(|template| template.name.clone())
+            .collect(),
+    );
+    templates
+}
+
+/// Lists the templates available in the templates folder
+#[tauri::command]
+pub async fn list_templates(app: tauri::AppHandle) -> Result<Vec<TemplateInfo>, String> {
+    Ok(refresh_template_menu(&app))
+}
+
+/// Expands a template into the content of a new, unsaved note
+#[tauri::command]
+pub async fn new_note_from_template(
+    app: tauri::AppHandle,
+    name: String,
+    title: Option<String>,
+) -> Result<ExpandedTemplate, String> {
+    expand_named_template(&app, &name, title).await
+}
@@ -0,0 +1,117 @@
+use crate::commands::{file_commands, search_commands, settings_commands};
+use crate::links;
+use crate::search::SearchHit;
+use crate::url_scheme::{self, UrlAction};
+use serde::Serialize;
+use std::path::Path;
+use tauri::api::dialog;
+use tauri::Window;
+
+/// What the frontend should do for a quicknote:// URL
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UrlOutcome {
+    NewNote {
+        title: Option<String>,
+        content: String,
+    },
+    Open {
+        path: String,
+        line: Option<usize>,
+    },
+    Search {
+        query: String,
+        hits: Vec<SearchHit>,
+    },
+    /// The user declined a URL that writes files
+    Cancelled,
+}
+
+/// Creates the note a `new` URL asks for, which must be a new note file inside the notes root
+async fn create_note(app: &tauri::AppHandle, path: &Path, content: &str) -> Result<(), String> {
+    let root = settings_commands::require_notes_root(app).await?;
+    let path = links::normalize_path(path);
+    if !path.starts_with(links::normalize_path(&root)) {
+        return Err(format!(
+            "Links may only create notes inside the notes directory: {}",
+            path.display()
+        ));
+    }
+    if !links::is_note_file(&path) {
+        return Err(format!("Not a note file: {}", path.display()));
+    }
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    // Claim the name first so a link can never replace a note that appeared meanwhile
+    tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                format!("File already exists: {}", path.display())
+            }
+            _ => e.to_string(),
+        })?;
+    if let Err(e) =
+        file_commands::write_note_atomically(app, &path.to_string_lossy(), content).await
+    {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Validates a quicknote:// URL and carries it out, asking first if it writes files
+#[tauri::command]
+pub async fn open_url(
+    app: tauri::AppHandle,
+    window: Window,
+    url: String,
+) -> Result<UrlOutcome, String> {
+    let action = url_scheme::parse(&url).map_err(|e| e.to_string())?;
+
+    if action.writes_files() {
+        let message = format!(
+            "A link wants to write to your notes:\n\n{}\n\nAllow it?",
+            url
+        );
+        if !dialog::blocking::ask(Some(&window), "QuickNote", message) {
+            return Ok(UrlOutcome::Cancelled);
+        }
+    }
+
+    match action {
+        UrlAction::New {
+            text,
+            path: Some(path),
+            ..
+        } => {
+            create_note(&app, &path, &text).await?;
+            Ok(UrlOutcome::Open {
+                path: links::normalize_path(&path).to_string_lossy().to_string(),
+                line: None,
+            })
+        }
+        UrlAction::New {
+            text,
+            title,
+            path: None,
+        } => Ok(UrlOutcome::NewNote {
+            title,
+            content: text,
+        }),
+        UrlAction::Open { path, line } => Ok(UrlOutcome::Open {
+            path: path.to_string_lossy().to_string(),
+            line,
+        }),
+        UrlAction::Search { query } => {
+            let hits = search_commands::search(&app, &query, None, true).await?;
+            Ok(UrlOutcome::Search { query, hits })
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::commands::settings_commands::{self, get_settings_path};
+use crate::commands::{encryption_commands, git_commands, history_commands};
+use crate::crypto::VaultKey;
+use crate::vault;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::Manager;
+
+/// Error returned when a vault file is opened or saved while the vault is locked
+pub const VAULT_LOCKED: &str = "vault_locked";
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+struct UnlockedVault {
+    key: Arc<VaultKey>,
+    last_used: Instant,
+}
+
+lazy_static::lazy_static! {
+    // The vault key while unlocked; it only ever lives in memory
+    static ref VAULT: Mutex<Option<UnlockedVault>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+}
+
+/// The wrapped vault key lives next to settings.json; the vault is enabled when it exists
+pub fn vault_key_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    get_settings_path(app)
+        .map(|settings| settings.with_file_name("vault.key"))
+        .map_err(|e| e.to_string())
+}
+
+pub fn is_enabled(app: &tauri::AppHandle) -> bool {
+    vault_key_path(app)
+        .map(|path| path.exists())
+        .unwrap_or(false)
+}
+
+/// True when the vault is set up but has not been unlocked yet
+pub fn needs_unlock(app: &tauri::AppHandle) -> bool {
+    is_enabled(app) && VAULT.lock().unwrap().is_none()
+}
+
+/// True for files the vault covers, whether or not it is unlocked
+async fn in_vault(app: &tauri::AppHandle, path: &str) -> bool {
+    is_enabled(app)
+        && settings_commands::get_notes_root(app)
+            .await
+            .is_some_and(|root| Path::new(path).starts_with(root))
+}
+
+/// True for notes kept encrypted on disk, with their own passphrase or in the vault
+pub async fn is_protected(app: &tauri::AppHandle, path: &str) -> bool {
+    let file = path.to_string();
+    let encrypted =
+        tokio::task::spawn_blocking(move || encryption_commands::is_encrypted_file(&file))
+            .await
+            .unwrap_or(true);
+    encrypted || in_vault(app, path).await
+}
+
+/// Returns the vault key for a file inside the vault, or None for files outside it
+pub async fn vault_for(
+    app: &tauri::AppHandle,
+    path: &str,
+) -> Result<Option<Arc<VaultKey>>, String> {
+    if !in_vault(app, path).await {
+        return Ok(None);
+    }
+    let mut vault = VAULT.lock().unwrap();
+    match vault.as_mut() {
+        Some(unlocked) => {
+            unlocked.last_used = Instant::now();
+            Ok(Some(unlocked.key.clone()))
+        }
+        None => Err(VAULT_LOCKED.to_string()),
+    }
+}
+
+/// Seals anything left in plaintext under the notes root, then forgets the key
+async fn lock(app: &tauri::AppHandle) -> Result<(), String> {
+    if !is_enabled(app) {
+        return Ok(());
+    }
+    let key = VAULT.lock().unwrap().take().map(|unlocked| unlocked.key);
+    if let Some(key) = key {
+        // Other programs may have dropped plaintext notes into the folder meanwhile
+        if let Some(root) = settings_commands::get_notes_root(app).await {
+            tokio::task::spawn_blocking(move || vault::seal_tree(&root, &key))
+                .await
+                .map_err(|e| e.to_string())?
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    if let Some(root) = settings_commands::get_notes_root(app).await {
+        if let Some(window) = app.get_window("main") {
+            let _ = window.emit("vault-locked", root);
+        }
+    }
+    Ok(())
+}
+
+/// Locks the vault once it has sat unused for the configured number of minutes
+pub fn start_vault_idle_lock(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            let idle = match VAULT.lock().unwrap().as_ref() {
+                Some(unlocked) => unlocked.last_used.elapsed(),
+                None => continue,
+            };
+            let minutes = settings_commands::load_settings(app.clone())
+                .await
+                .map(|settings| settings.vault_idle_minutes)
+                .unwrap_or(15);
+            // 0 keeps the vault unlocked until it is locked by hand
+            if minutes == 0 || idle < Duration::from_secs(u64::from(minutes) * 60) {
+                continue;
+            }
+            if let Err(e) = lock(&app).await {
+                eprintln!("Failed to lock vault: {}", e);
+            }
+            if let Some(window) = app.get_window("main") {
+                let _ = window.hide();
+            }
+        }
+    });
+}
+
+/// Locks the vault and every unlocked encrypted note, e.g. from the tray "Lock" item
+pub async fn lock_all(app: &tauri::AppHandle) -> Result<(), String> {
+    encryption_commands::lock_all_notes(app);
+    lock(app).await
+}
+
+/// Locks everything and then exits, so no unlocked vault or note key outlives the app
+pub async fn lock_and_exit(app: &tauri::AppHandle) {
+    if let Err(e) = lock_all(app).await {
+        eprintln!("Failed to lock before quitting: {}", e);
+    }
+    app.exit(0);
+}
+
+/// Turns on the vault: creates its key and encrypts the notes root
+#[tauri::command]
+pub async fn enable_vault(app: tauri::AppHandle, passphrase: String) -> Result<(), String> {
+    let root = settings_commands::require_notes_root(&app).await?;
+    let key_path = vault_key_path(&app)?;
+    let key = tokio::task::spawn_blocking(move || {
+        let key = vault::create(&key_path, &passphrase)?;
+        vault::seal_tree(&root, &key)?;
+        anyhow::Ok(key)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    // Snapshots taken before the vault existed hold plaintext copies of the notes
+    if let Some(root) = settings_commands::get_notes_root(&app).await {
+        history_commands::forget_history_under(&app, &root);
+        git_commands::warn_plaintext_in_history(&app, &root).await;
+    }
+    *VAULT.lock().unwrap() = Some(UnlockedVault {
+        key: Arc::new(key),
+        last_used: Instant::now(),
+    });
+    Ok(())
+}
+
+/// Unlocks the vault for this session
+#[tauri::command]
+pub async fn unlock_vault(app: tauri::AppHandle, passphrase: String) -> Result<(), String> {
+    let key_path = vault_key_path(&app)?;
+    let key = tokio::task::spawn_blocking(move || vault::unlock(&key_path, &passphrase))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    *VAULT.lock().unwrap() = Some(UnlockedVault {
+        key: Arc::new(key),
+        last_used: Instant::now(),
+    });
+    if let Some(window) = app.get_window("main") {
+        let _ = window.emit("vault-unlocked", ());
+    }
+    Ok(())
+}
+
+/// Locks the vault right away
+#[tauri::command]
+pub async fn lock_vault(app: tauri::AppHandle) -> Result<(), String> {
+    lock(&app).await
+}
+
+/// Changes the vault passphrase without re-encrypting the notes
+#[tauri::command]
+pub async fn change_passphrase(
+    app: tauri::AppHandle,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), String> {
+    let key_path = vault_key_path(&app)?;
+    tokio::task::spawn_blocking(move || {
+        vault::change_passphrase(&key_path, &old_passphrase, &new_passphrase)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Reports whether the vault is set up and unlocked
+#[tauri::command]
+pub async fn get_vault_status(app: tauri::AppHandle) -> Result<VaultStatus, String> {
+    Ok(VaultStatus {
+        enabled: is_enabled(&app),
+        unlocked: VAULT.lock().unwrap().is_some(),
+    })
+}
+
+/// Asks the frontend for the vault passphrase when the window is shown while locked
+pub fn request_unlock_if_locked(app: &tauri::AppHandle) {
+    if needs_unlock(app) {
+        if let Some(window) = app.get_window("main") {
+            let _ = window.emit("vault-unlock-required", ());
+        }
+    }
+}
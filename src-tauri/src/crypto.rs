@@ -0,0 +1,354 @@
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+/// Marks an encrypted container; the last byte is the format version
+const MAGIC: &[u8; 6] = b"QNENC\x01";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+/// Magic, three little-endian u32 Argon2 parameters, salt and nonce
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN + NONCE_LEN;
+
+/// Argon2id cost used for new containers: 64 MiB, 3 passes, 1 lane
+const DEFAULT_MEMORY_KIB: u32 = 64 * 1024;
+const DEFAULT_PASSES: u32 = 3;
+const DEFAULT_LANES: u32 = 1;
+
+/// Upper bounds on costs read from a file, so a crafted header cannot exhaust memory
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_PASSES: u32 = 16;
+const MAX_LANES: u32 = 16;
+
+/// Argon2id cost parameters stored in each container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KdfParams {
+    memory_kib: u32,
+    passes: u32,
+    lanes: u32,
+}
+
+/// A key derived from a passphrase, together with the salt and costs that produced it
+pub struct NoteKey {
+    salt: [u8; SALT_LEN],
+    params: KdfParams,
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl NoteKey {
+    /// Derives a key for a new container with a fresh random salt
+    pub fn generate(passphrase: &str) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(anyhow!("Passphrase must not be empty"));
+        }
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(
+            passphrase,
+            salt,
+            KdfParams {
+                memory_kib: DEFAULT_MEMORY_KIB,
+                passes: DEFAULT_PASSES,
+                lanes: DEFAULT_LANES,
+            },
+        )
+    }
+
+    fn derive(passphrase: &str, salt: [u8; SALT_LEN], params: KdfParams) -> Result<Self> {
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(params.memory_kib, params.passes, params.lanes, Some(32))
+                .map_err(|e| anyhow!("Invalid key derivation parameters: {}", e))?,
+        );
+        let mut key = Zeroizing::new([0u8; 32]);
+        argon2
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+            .map_err(|e| anyhow!("Key derivation failed: {}", e))?;
+        Ok(Self { salt, params, key })
+    }
+}
+
+/// Parsed container header; the raw bytes double as associated data
+struct Header<'a> {
+    bytes: &'a [u8],
+    params: KdfParams,
+    salt: [u8; SALT_LEN],
+    nonce: &'a [u8],
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn parse_header(data: &[u8]) -> Result<Header<'_>> {
+    if !is_encrypted(data) {
+        return Err(anyhow!("Not an encrypted note"));
+    }
+    if data.len() < HEADER_LEN {
+        return Err(anyhow!("Encrypted note is truncated"));
+    }
+    let offset = MAGIC.len();
+    let params = KdfParams {
+        memory_kib: read_u32(data, offset),
+        passes: read_u32(data, offset + 4),
+        lanes: read_u32(data, offset + 8),
+    };
+    if params.memory_kib > MAX_MEMORY_KIB || params.passes > MAX_PASSES || params.lanes > MAX_LANES
+    {
+        return Err(anyhow!(
+            "Encrypted note uses unsupported key derivation costs"
+        ));
+    }
+    let salt_start = offset + 12;
+    let nonce_start = salt_start + SALT_LEN;
+    Ok(Header {
+        bytes: &data[..HEADER_LEN],
+        params,
+        salt: data[salt_start..nonce_start].try_into().unwrap(),
+        nonce: &data[nonce_start..HEADER_LEN],
+    })
+}
+
+/// Returns true if `data` starts like an encrypted container
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Seals `plaintext` into a container with a fresh nonce
+pub fn encrypt(key: &NoteKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&key.params.memory_kib.to_le_bytes());
+    header.extend_from_slice(&key.params.passes.to_le_bytes());
+    header.extend_from_slice(&key.params.lanes.to_le_bytes());
+    header.extend_from_slice(&key.salt);
+    header.extend_from_slice(&nonce);
+
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    header.extend_from_slice(&ciphertext);
+    Ok(header)
+}
+
+fn open(key: &NoteKey, header: &Header, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let cipher = XChaCha20Poly1305::new(key.key.as_ref().into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(header.nonce),
+            Payload {
+                msg: &data[HEADER_LEN..],
+                aad: header.bytes,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("Wrong passphrase or damaged file"))
+}
+
+/// Opens a container with a passphrase, returning the plaintext and the key for re-encrypting it
+pub fn decrypt(passphrase: &str, data: &[u8]) -> Result<(Zeroizing<Vec<u8>>, NoteKey)> {
+    let header = parse_header(data)?;
+    let key = NoteKey::derive(passphrase, header.salt, header.params)?;
+    let plaintext = open(&key, &header, data)?;
+    Ok((plaintext, key))
+}
+
+/// Opens a container with an already derived key; fails if it was sealed with another salt
+pub fn decrypt_with_key(key: &NoteKey, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    let header = parse_header(data)?;
+    if header.salt != key.salt || header.params != key.params {
+        return Err(anyhow!("Encrypted with a different passphrase"));
+    }
+    open(key, &header, data)
+}
+
+/// Random hex string for shared secrets such as local connection tokens
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Marks a file sealed with the vault key; the last byte is the format version
+const VAULT_MAGIC: &[u8; 6] = b"QNVLT\x01";
+const VAULT_ID_LEN: usize = 16;
+/// Magic, vault id and nonce
+const VAULT_HEADER_LEN: usize = VAULT_MAGIC.len() + VAULT_ID_LEN + NONCE_LEN;
+
+/// Random key that encrypts every file in the vault; stored wrapped under the passphrase
+pub struct VaultKey {
+    id: [u8; VAULT_ID_LEN],
+    key: Zeroizing<[u8; 32]>,
+}
+
+impl VaultKey {
+    pub fn generate() -> Self {
+        let mut id = [0u8; VAULT_ID_LEN];
+        OsRng.fill_bytes(&mut id);
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        Self { id, key }
+    }
+
+    /// Encrypts the key under a passphrase, so changing the passphrase never touches the files
+    pub fn wrap(&self, passphrase_key: &NoteKey) -> Result<Vec<u8>> {
+        let mut raw = Zeroizing::new(Vec::with_capacity(VAULT_ID_LEN + 32));
+        raw.extend_from_slice(&self.id);
+        raw.extend_from_slice(self.key.as_ref());
+        encrypt(passphrase_key, &raw)
+    }
+
+    pub fn unwrap(passphrase: &str, wrapped: &[u8]) -> Result<Self> {
+        let (raw, _) = decrypt(passphrase, wrapped)?;
+        if raw.len() != VAULT_ID_LEN + 32 {
+            return Err(anyhow!("Vault key file is damaged"));
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&raw[VAULT_ID_LEN..]);
+        Ok(Self {
+            id: raw[..VAULT_ID_LEN].try_into().unwrap(),
+            key,
+        })
+    }
+}
+
+/// Returns true if `data` was sealed with a vault key
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(VAULT_MAGIC)
+}
+
+/// Seals a file's content with the vault key
+pub fn seal(vault: &VaultKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let mut header = Vec::with_capacity(VAULT_HEADER_LEN);
+    header.extend_from_slice(VAULT_MAGIC);
+    header.extend_from_slice(&vault.id);
+    header.extend_from_slice(&nonce);
+
+    let cipher = XChaCha20Poly1305::new(vault.key.as_ref().into());
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: &header,
+            },
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    header.extend_from_slice(&ciphertext);
+    Ok(header)
+}
+
+/// Opens content sealed with the vault key
+pub fn unseal(vault: &VaultKey, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if !is_sealed(data) || data.len() < VAULT_HEADER_LEN {
+        return Err(anyhow!("Not a vault file"));
+    }
+    if data[VAULT_MAGIC.len()..VAULT_MAGIC.len() + VAULT_ID_LEN] != vault.id {
+        return Err(anyhow!("File belongs to a different vault"));
+    }
+    let cipher = XChaCha20Poly1305::new(vault.key.as_ref().into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(&data[VAULT_MAGIC.len() + VAULT_ID_LEN..VAULT_HEADER_LEN]),
+            Payload {
+                msg: &data[VAULT_HEADER_LEN..],
+                aad: &data[..VAULT_HEADER_LEN],
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("Vault file is damaged"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Same format as `NoteKey::generate`, with costs low enough for tests
+    fn cheap_key(passphrase: &str) -> NoteKey {
+        let params = KdfParams {
+            memory_kib: 8,
+            passes: 1,
+            lanes: 1,
+        };
+        NoteKey::derive(passphrase, [7; SALT_LEN], params).unwrap()
+    }
+
+    #[test]
+    fn decrypts_what_it_encrypts() {
+        let key = cheap_key("hunter2");
+        let data = encrypt(&key, "secret ünïcode".as_bytes()).unwrap();
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(6).any(|window| window == b"secret"));
+
+        let (plaintext, derived) = decrypt("hunter2", &data).unwrap();
+        assert_eq!(&plaintext[..], "secret ünïcode".as_bytes());
+        let again = encrypt(&derived, b"second").unwrap();
+        assert_eq!(&decrypt_with_key(&key, &again).unwrap()[..], b"second");
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        let data = encrypt(&cheap_key("hunter2"), b"secret").unwrap();
+        assert!(decrypt("hunter3", &data).is_err());
+        assert!(decrypt_with_key(&cheap_key("hunter3"), &data).is_err());
+    }
+
+    #[test]
+    fn rejects_tampered_containers() {
+        let data = encrypt(&cheap_key("hunter2"), b"secret").unwrap();
+        let tampered = |index: usize| {
+            let mut copy = data.clone();
+            copy[index] ^= 1;
+            copy
+        };
+        // Last ciphertext byte, a salt byte and a nonce byte
+        for index in [data.len() - 1, MAGIC.len() + 12, HEADER_LEN - 1] {
+            assert!(decrypt("hunter2", &tampered(index)).is_err(), "{}", index);
+        }
+        assert!(decrypt("hunter2", &data[..HEADER_LEN - 1]).is_err());
+
+        let mut costly = data.clone();
+        costly[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = decrypt("hunter2", &costly).err().unwrap();
+        assert!(error.to_string().contains("unsupported"));
+    }
+
+    #[test]
+    fn seals_and_unseals_with_the_vault_key() {
+        let vault = VaultKey::generate();
+        let sealed = seal(&vault, b"vault note").unwrap();
+        assert!(is_sealed(&sealed) && !is_encrypted(&sealed));
+        assert_eq!(&unseal(&vault, &sealed).unwrap()[..], b"vault note");
+
+        // Last ciphertext byte and a nonce byte
+        for index in [sealed.len() - 1, VAULT_HEADER_LEN - 1] {
+            let mut tampered = sealed.clone();
+            tampered[index] ^= 1;
+            assert!(unseal(&vault, &tampered).is_err(), "{}", index);
+        }
+        assert!(unseal(&VaultKey::generate(), &sealed).is_err());
+    }
+
+    #[test]
+    fn wraps_and_unwraps_the_vault_key() {
+        let vault = VaultKey::generate();
+        let wrapped = vault.wrap(&cheap_key("hunter2")).unwrap();
+        let sealed = seal(&vault, b"vault note").unwrap();
+
+        let unwrapped = VaultKey::unwrap("hunter2", &wrapped).unwrap();
+        assert_eq!(&unseal(&unwrapped, &sealed).unwrap()[..], b"vault note");
+        assert!(VaultKey::unwrap("hunter3", &wrapped).is_err());
+    }
+}
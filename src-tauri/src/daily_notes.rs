@@ -0,0 +1,104 @@
+use crate::templates::{self, TemplateContext};
+use chrono::{Local, NaiveDate, TimeZone};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Extension used for newly created daily notes
+const DAILY_NOTE_EXTENSION: &str = "md";
+
+/// Default body for a new daily note when no template is configured
+const DEFAULT_DAILY_TEMPLATE: &str = "# {{title}}\n\n";
+
+/// Naming options for daily notes, taken from `AppSettings`
+#[derive(Debug, Clone)]
+pub struct DailyNoteConfig {
+    pub root: PathBuf,
+    pub folder: String,
+    pub pattern: String,
+}
+
+impl DailyNoteConfig {
+    /// Directory that contains all daily notes
+    pub fn directory(&self) -> PathBuf {
+        if self.folder.trim().is_empty() {
+            self.root.clone()
+        } else {
+            self.root.join(self.folder.trim())
+        }
+    }
+
+    /// Path of the daily note for the given date
+    pub fn path_for(&self, date: NaiveDate) -> PathBuf {
+        let name = date.format(&self.pattern).to_string();
+        self.directory()
+            .join(format!("{}.{}", name, DAILY_NOTE_EXTENSION))
+    }
+
+    /// Parses the date back out of a daily note path, if it follows the pattern
+    pub fn date_of(&self, path: &Path) -> Option<NaiveDate> {
+        let relative = path.strip_prefix(self.directory()).ok()?;
+        let name = relative.with_extension("");
+        let name = name.to_string_lossy().replace('\\', "/");
+        NaiveDate::parse_from_str(&name, &self.pattern).ok()
+    }
+
+    /// Lists the dates of every existing daily note, oldest first
+    pub fn existing_dates(&self) -> Vec<NaiveDate> {
+        let mut dates: Vec<NaiveDate> = WalkDir::new(self.directory())
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| self.date_of(entry.path()))
+            .collect();
+        dates.sort();
+        dates.dedup();
+        dates
+    }
+
+    /// Finds the closest existing daily note before or after `date`
+    pub fn adjacent_date(&self, date: NaiveDate, forward: bool) -> Option<NaiveDate> {
+        let dates = self.existing_dates();
+        if forward {
+            dates.into_iter().find(|candidate| *candidate > date)
+        } else {
+            dates.into_iter().rev().find(|candidate| *candidate < date)
+        }
+    }
+}
+
+/// Returns today's date in the local timezone
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Parses a `YYYY-MM-DD` date string
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date '{}': {}", date, e))
+}
+
+/// Builds the initial content of a daily note from a template
+pub fn render_daily_note(template: Option<&str>, date: NaiveDate, title: &str) -> String {
+    // Placeholders such as {{date}} refer to the note's day rather than today
+    let mut context = TemplateContext::new(title);
+    if let Some(moment) = Local
+        .from_local_datetime(&date.and_time(context.now.time()))
+        .earliest()
+    {
+        context.now = moment;
+    }
+    templates::expand_template(template.unwrap_or(DEFAULT_DAILY_TEMPLATE), &context).content
+}
+
+/// Validates that a strftime pattern can be used for file names
+pub fn validate_pattern(pattern: &str) -> Result<(), String> {
+    use chrono::format::{Item, StrftimeItems};
+
+    if pattern.trim().is_empty() {
+        return Err("Daily note pattern cannot be empty".to_string());
+    }
+    if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid daily note pattern: {}", pattern));
+    }
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, group_diff_ops, Algorithm, ChangeTag, DiffOp, TextDiff};
+
+/// Options accepted by the diff commands
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiffOptions {
+    /// "myers" or "patience"
+    pub algorithm: String,
+    pub ignore_whitespace: bool,
+    pub ignore_line_endings: bool,
+    /// Number of unchanged lines shown around each change
+    pub context: usize,
+    pub word_diff: bool,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            algorithm: "myers".to_string(),
+            ignore_whitespace: false,
+            ignore_line_endings: true,
+            context: 3,
+            word_diff: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+impl From<ChangeTag> for ChangeKind {
+    fn from(tag: ChangeTag) -> Self {
+        match tag {
+            ChangeTag::Equal => ChangeKind::Equal,
+            ChangeTag::Insert => ChangeKind::Insert,
+            ChangeTag::Delete => ChangeKind::Delete,
+        }
+    }
+}
+
+/// A run of words inside a changed line
+#[derive(Debug, Clone, Serialize)]
+pub struct WordSegment {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+/// One line of a hunk; line numbers are 1-based
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    pub kind: ChangeKind,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+    /// Word-level changes for lines that were modified rather than added or removed
+    pub words: Option<Vec<WordSegment>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffResult {
+    pub identical: bool,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// Splits text into lines without their line terminators
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n')
+        .map(|line| line.strip_suffix('\n').unwrap_or(line))
+        .collect()
+}
+
+/// Builds the key a line is compared by, honouring the ignore options. `line` keeps its
+/// terminator, so a missing final newline counts as a change.
+fn comparison_key(line: &str, options: &DiffOptions) -> String {
+    if options.ignore_whitespace {
+        line.chars().filter(|c| !c.is_whitespace()).collect()
+    } else if options.ignore_line_endings {
+        let (text, newline) = match line.strip_suffix('\n') {
+            Some(text) => (text, "\n"),
+            None => (line, ""),
+        };
+        format!("{}{}", text.strip_suffix('\r').unwrap_or(text), newline)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Comparison keys for every line of `text`
+fn comparison_keys(text: &str, options: &DiffOptions) -> Vec<String> {
+    text.split_inclusive('\n')
+        .map(|line| comparison_key(line, options))
+        .collect()
+}
+
+fn parse_algorithm(name: &str) -> Algorithm {
+    match name.to_lowercase().as_str() {
+        "patience" => Algorithm::Patience,
+        _ => Algorithm::Myers,
+    }
+}
+
+/// Computes word-level segments between an old and a new version of a line
+fn word_segments(
+    old: &str,
+    new: &str,
+    algorithm: Algorithm,
+) -> (Vec<WordSegment>, Vec<WordSegment>) {
+    let diff = TextDiff::configure()
+        .algorithm(algorithm)
+        .diff_words(old, new);
+
+    let mut old_segments = Vec::new();
+    let mut new_segments = Vec::new();
+    for change in diff.iter_all_changes() {
+        let kind = ChangeKind::from(change.tag());
+        if kind != ChangeKind::Insert {
+            push_segment(&mut old_segments, kind, change.value());
+        }
+        if kind != ChangeKind::Delete {
+            push_segment(&mut new_segments, kind, change.value());
+        }
+    }
+    (old_segments, new_segments)
+}
+
+/// Appends text to the last segment if it has the same kind, otherwise starts a new one
+fn push_segment(segments: &mut Vec<WordSegment>, kind: ChangeKind, text: &str) {
+    match segments.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => segments.push(WordSegment {
+            kind,
+            text: text.to_string(),
+        }),
+    }
+}
+
+/// Produces line- and word-level hunks between two texts
+pub fn diff_text(old: &str, new: &str, options: &DiffOptions) -> DiffResult {
+    let old_lines = split_lines(old);
+    let new_lines = split_lines(new);
+    let old_keys = comparison_keys(old, options);
+    let new_keys = comparison_keys(new, options);
+
+    let algorithm = parse_algorithm(&options.algorithm);
+    let ops = capture_diff_slices(algorithm, &old_keys, &new_keys);
+
+    let mut insertions = 0;
+    let mut deletions = 0;
+    let mut hunks = Vec::new();
+
+    for group in group_diff_ops(ops, options.context) {
+        let mut lines = Vec::new();
+        for op in &group {
+            match *op {
+                DiffOp::Equal {
+                    old_index,
+                    new_index,
+                    len,
+                } => {
+                    for i in 0..len {
+                        lines.push(DiffLine {
+                            kind: ChangeKind::Equal,
+                            old_line: Some(old_index + i + 1),
+                            new_line: Some(new_index + i + 1),
+                            text: new_lines[new_index + i].to_string(),
+                            words: None,
+                        });
+                    }
+                }
+                DiffOp::Delete {
+                    old_index, old_len, ..
+                } => {
+                    deletions += old_len;
+                    for i in old_index..old_index + old_len {
+                        lines.push(deleted_line(&old_lines, i, None));
+                    }
+                }
+                DiffOp::Insert {
+                    new_index, new_len, ..
+                } => {
+                    insertions += new_len;
+                    for i in new_index..new_index + new_len {
+                        lines.push(inserted_line(&new_lines, i, None));
+                    }
+                }
+                DiffOp::Replace {
+                    old_index,
+                    old_len,
+                    new_index,
+                    new_len,
+                } => {
+                    deletions += old_len;
+                    insertions += new_len;
+
+                    // Pair replaced lines up in order to get word-level changes
+                    let mut old_words: Vec<Option<Vec<WordSegment>>> = vec![None; old_len];
+                    let mut new_words: Vec<Option<Vec<WordSegment>>> = vec![None; new_len];
+                    if options.word_diff {
+                        let pairs = old_words.iter_mut().zip(new_words.iter_mut());
+                        for (i, (old_slot, new_slot)) in pairs.enumerate() {
+                            let (old_segments, new_segments) = word_segments(
+                                old_lines[old_index + i],
+                                new_lines[new_index + i],
+                                algorithm,
+                            );
+                            *old_slot = Some(old_segments);
+                            *new_slot = Some(new_segments);
+                        }
+                    }
+                    for (i, words) in old_words.into_iter().enumerate() {
+                        lines.push(deleted_line(&old_lines, old_index + i, words));
+                    }
+                    for (i, words) in new_words.into_iter().enumerate() {
+                        lines.push(inserted_line(&new_lines, new_index + i, words));
+                    }
+                }
+            }
+        }
+
+        let (old_range, new_range) = match (group.first(), group.last()) {
+            (Some(first), Some(last)) => (
+                first.old_range().start..last.old_range().end,
+                first.new_range().start..last.new_range().end,
+            ),
+            _ => continue,
+        };
+        hunks.push(DiffHunk {
+            old_start: old_range.start + 1,
+            old_len: old_range.len(),
+            new_start: new_range.start + 1,
+            new_len: new_range.len(),
+            lines,
+        });
+    }
+
+    DiffResult {
+        identical: hunks.is_empty(),
+        insertions,
+        deletions,
+        hunks,
+    }
+}
+
+fn deleted_line(lines: &[&str], index: usize, words: Option<Vec<WordSegment>>) -> DiffLine {
+    DiffLine {
+        kind: ChangeKind::Delete,
+        old_line: Some(index + 1),
+        new_line: None,
+        text: lines[index].to_string(),
+        words,
+    }
+}
+
+fn inserted_line(lines: &[&str], index: usize, words: Option<Vec<WordSegment>>) -> DiffLine {
+    DiffLine {
+        kind: ChangeKind::Insert,
+        old_line: None,
+        new_line: Some(index + 1),
+        text: lines[index].to_string(),
+        words,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_missing_final_newline_is_a_change() {
+        let options = DiffOptions::default();
+        let result = diff_text("a\nb", "a\nb\n", &options);
+        assert!(!result.identical);
+        assert_eq!((result.deletions, result.insertions), (1, 1));
+        assert!(diff_text("a\nb\n", "a\nb\n", &options).identical);
+    }
+
+    #[test]
+    fn line_endings_are_ignored_by_default() {
+        assert!(diff_text("a\r\nb\r\n", "a\nb\n", &DiffOptions::default()).identical);
+        let strict = DiffOptions {
+            ignore_line_endings: false,
+            ..DiffOptions::default()
+        };
+        assert!(!diff_text("a\r\nb\n", "a\nb\n", &strict).identical);
+    }
+}
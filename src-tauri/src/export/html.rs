@@ -0,0 +1,168 @@
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+use syntect::highlighting::ThemeSet;
+use syntect::html::highlighted_html_for_string;
+use syntect::parsing::SyntaxSet;
+
+lazy_static! {
+    // Loading the bundled syntax definitions is slow, so do it once
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+}
+
+const LIGHT_CSS: &str = r#"
+:root { color-scheme: light; }
+body { background: #ffffff; color: #24292f; }
+a { color: #0969da; }
+code { background: #f3f4f6; }
+pre { background: #f6f8fa; border: 1px solid #d0d7de; }
+blockquote { color: #57606a; border-left-color: #d0d7de; }
+th, td { border-color: #d0d7de; }
+tr:nth-child(2n) { background: #f6f8fa; }
+hr { border-color: #d0d7de; }
+"#;
+
+const DARK_CSS: &str = r#"
+:root { color-scheme: dark; }
+body { background: #1e1e1e; color: #d4d4d4; }
+a { color: #4fa3ff; }
+code { background: #2d2d2d; }
+pre { background: #2b303b; border: 1px solid #3c3c3c; }
+blockquote { color: #a0a0a0; border-left-color: #3c3c3c; }
+th, td { border-color: #3c3c3c; }
+tr:nth-child(2n) { background: #252526; }
+hr { border-color: #3c3c3c; }
+"#;
+
+const BASE_CSS: &str = r#"
+body {
+  font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", "PingFang TC", "Noto Sans CJK TC", Helvetica, Arial, sans-serif;
+  font-size: 16px;
+  line-height: 1.6;
+  max-width: 860px;
+  margin: 0 auto;
+  padding: 32px 24px;
+}
+h1, h2, h3, h4, h5, h6 { line-height: 1.25; margin: 1.5em 0 0.5em; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 0.9em; padding: 0.15em 0.35em; border-radius: 4px; }
+pre { padding: 12px 16px; border-radius: 6px; overflow-x: auto; }
+pre code { background: none; padding: 0; }
+blockquote { margin: 0; padding: 0 1em; border-left: 4px solid; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid; padding: 6px 12px; }
+img { max-width: 100%; }
+hr { border: 0; border-top: 1px solid; }
+ul.contains-task-list { list-style: none; padding-left: 1.2em; }
+@media print { body { max-width: none; padding: 0; } }
+"#;
+
+/// Escapes text for use inside HTML
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn is_dark(theme: &str) -> bool {
+    theme.eq_ignore_ascii_case("dark")
+}
+
+/// Highlights a code block with inline styles, falling back to plain escaped text
+fn highlight_code(code: &str, language: &str, theme: &str) -> String {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let theme_name = if is_dark(theme) {
+        "base16-ocean.dark"
+    } else {
+        "InspiredGitHub"
+    };
+
+    match THEME_SET.themes.get(theme_name) {
+        Some(syntect_theme) => {
+            highlighted_html_for_string(code, &SYNTAX_SET, syntax, syntect_theme)
+                .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>", escape_html(code)))
+        }
+        None => format!("<pre><code>{}</code></pre>", escape_html(code)),
+    }
+}
+
+/// Renders Markdown to an HTML fragment with tables, task lists and highlighted code
+pub fn render_markdown(markdown: &str, theme: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, String)> = None;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().unwrap_or("").to_string()
+                    }
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((language, String::new()));
+            }
+            Event::Text(text) if code_block.is_some() => {
+                if let Some((_, code)) = code_block.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, code)) = code_block.take() {
+                    let highlighted = highlight_code(&code, &language, theme);
+                    events.push(Event::Html(CowStr::from(highlighted)));
+                }
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    output
+}
+
+/// Renders a plain-text note as preformatted HTML
+pub fn render_plain_text(text: &str) -> String {
+    format!("<pre class=\"plain-text\">{}</pre>", escape_html(text))
+}
+
+/// Wraps an HTML fragment in a standalone document with embedded CSS
+pub fn standalone_document(title: &str, body: &str, theme: &str) -> String {
+    let theme_css = if is_dark(theme) { DARK_CSS } else { LIGHT_CSS };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"generator\" content=\"QuickNote\">\n\
+         <title>{}</title>\n<style>{}{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        BASE_CSS,
+        theme_css,
+        body
+    )
+}
+
+/// Renders a note to a standalone HTML document
+pub fn export_html(title: &str, content: &str, markdown: bool, theme: &str) -> String {
+    let body = if markdown {
+        render_markdown(content, theme)
+    } else {
+        render_plain_text(content)
+    };
+    standalone_document(title, &body, theme)
+}
@@ -0,0 +1,30 @@
+pub mod html;
+pub mod pdf;
+
+use std::path::Path;
+
+/// Returns true if a note should be rendered as Markdown rather than plain text
+pub fn is_markdown(path: Option<&str>) -> bool {
+    match path.and_then(|path| Path::new(path).extension()) {
+        Some(ext) => matches!(
+            ext.to_string_lossy().to_lowercase().as_str(),
+            "md" | "markdown"
+        ),
+        // Unsaved buffers are assumed to be Markdown
+        None => true,
+    }
+}
+
+/// Derives a document title from the note path or its first heading
+pub fn document_title(path: Option<&str>, content: &str) -> String {
+    if let Some(heading) = content
+        .lines()
+        .map(str::trim)
+        .find(|line| line.starts_with("# "))
+    {
+        return heading.trim_start_matches("# ").trim().to_string();
+    }
+    path.and_then(|path| Path::new(path).file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
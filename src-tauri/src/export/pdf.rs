@@ -0,0 +1,612 @@
+use anyhow::{anyhow, Result};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+    Point,
+};
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Fonts tried in order when no primary font is configured
+const PRIMARY_FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// Fonts with CJK coverage, used for characters the primary font lacks
+const CJK_FONT_CANDIDATES: &[&str] = &[
+    "/System/Library/Fonts/Supplemental/Arial Unicode.ttf",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/truetype/arphic/uming.ttf",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansTC-Regular.otf",
+    "/usr/share/fonts/noto-cjk/NotoSansTC-Regular.otf",
+    "C:\\Windows\\Fonts\\msjh.ttf",
+];
+
+const POINTS_PER_MM: f32 = 72.0 / 25.4;
+
+/// Page and font options for PDF export
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PdfOptions {
+    /// "a4", "a5", "letter" or "legal"
+    pub page_size: String,
+    pub landscape: bool,
+    pub margin_mm: f32,
+    pub font_size: f32,
+    pub font_path: Option<String>,
+    pub cjk_font_path: Option<String>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: "a4".to_string(),
+            landscape: false,
+            margin_mm: 20.0,
+            font_size: 11.0,
+            font_path: None,
+            cjk_font_path: None,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// Page width and height in millimetres
+    fn page_dimensions(&self) -> Result<(f32, f32)> {
+        let (width, height) = match self.page_size.to_lowercase().as_str() {
+            "a4" => (210.0, 297.0),
+            "a5" => (148.0, 210.0),
+            "letter" => (215.9, 279.4),
+            "legal" => (215.9, 355.6),
+            other => return Err(anyhow!("Unsupported page size: {}", other)),
+        };
+        Ok(if self.landscape {
+            (height, width)
+        } else {
+            (width, height)
+        })
+    }
+}
+
+/// Font files read from disk, kept alive for the faces parsed from them
+struct FontFiles {
+    primary: Option<(PathBuf, Vec<u8>)>,
+    cjk: Option<(PathBuf, Vec<u8>)>,
+}
+
+impl FontFiles {
+    fn read(options: &PdfOptions) -> Self {
+        let read = |configured: Option<&str>, candidates: &[&str]| {
+            let path = find_font(configured, candidates)?;
+            let data = std::fs::read(&path).ok()?;
+            Some((path, data))
+        };
+        Self {
+            primary: read(options.font_path.as_deref(), PRIMARY_FONT_CANDIDATES),
+            cjk: read(options.cjk_font_path.as_deref(), CJK_FONT_CANDIDATES),
+        }
+    }
+}
+
+/// An embedded font, parsed once for glyph coverage and width measurement
+struct LoadedFont<'a> {
+    face: ttf_parser::Face<'a>,
+    reference: IndirectFontRef,
+}
+
+impl LoadedFont<'_> {
+    fn has_glyph(&self, c: char) -> bool {
+        self.face.glyph_index(c).is_some()
+    }
+
+    /// Advance width of a character in points
+    fn char_width(&self, c: char, size: f32) -> f32 {
+        let units_per_em = self.face.units_per_em();
+        let advance = self
+            .face
+            .glyph_index(c)
+            .and_then(|glyph| self.face.glyph_hor_advance(glyph))
+            .unwrap_or(units_per_em / 2);
+        advance as f32 / units_per_em as f32 * size
+    }
+}
+
+/// The embedded fonts, or the built-in Helvetica if no font file was found
+struct FontSet<'a> {
+    primary: Option<LoadedFont<'a>>,
+    cjk: Option<LoadedFont<'a>>,
+    builtin: IndirectFontRef,
+}
+
+/// Which font a run of text is drawn with
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FontChoice {
+    Primary,
+    Cjk,
+    Builtin,
+}
+
+impl<'a> FontSet<'a> {
+    fn load(doc: &PdfDocumentReference, files: &'a FontFiles) -> Result<Self> {
+        let builtin = doc.add_builtin_font(BuiltinFont::Helvetica)?;
+        let primary = files
+            .primary
+            .as_ref()
+            .and_then(|(path, data)| load_font(doc, path, data));
+        let cjk = files
+            .cjk
+            .as_ref()
+            .and_then(|(path, data)| load_font(doc, path, data));
+        Ok(Self {
+            primary,
+            cjk,
+            builtin,
+        })
+    }
+
+    fn choose(&self, c: char) -> FontChoice {
+        match (&self.primary, &self.cjk) {
+            (Some(primary), _) if primary.has_glyph(c) || c.is_whitespace() => FontChoice::Primary,
+            (_, Some(cjk)) if cjk.has_glyph(c) => FontChoice::Cjk,
+            (Some(_), _) => FontChoice::Primary,
+            (None, _) => FontChoice::Builtin,
+        }
+    }
+
+    fn reference(&self, choice: FontChoice) -> &IndirectFontRef {
+        match choice {
+            FontChoice::Primary => self.primary.as_ref().map(|f| &f.reference),
+            FontChoice::Cjk => self.cjk.as_ref().map(|f| &f.reference),
+            FontChoice::Builtin => None,
+        }
+        .unwrap_or(&self.builtin)
+    }
+
+    fn char_width(&self, c: char, size: f32) -> f32 {
+        match self.choose(c) {
+            FontChoice::Primary => self.primary.as_ref().map(|f| f.char_width(c, size)),
+            FontChoice::Cjk => self.cjk.as_ref().map(|f| f.char_width(c, size)),
+            FontChoice::Builtin => None,
+        }
+        // Rough Helvetica average when using the built-in font
+        .unwrap_or(size * 0.55)
+    }
+
+    fn text_width(&self, text: &str, size: f32) -> f32 {
+        text.chars().map(|c| self.char_width(c, size)).sum()
+    }
+}
+
+/// Returns the configured font if it exists, otherwise the first installed candidate
+fn find_font(configured: Option<&str>, candidates: &[&str]) -> Option<PathBuf> {
+    if let Some(path) = configured.filter(|path| !path.is_empty()) {
+        if Path::new(path).is_file() {
+            return Some(PathBuf::from(path));
+        }
+        eprintln!("Configured PDF font not found: {}", path);
+    }
+    candidates
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
+}
+
+fn load_font<'a>(
+    doc: &PdfDocumentReference,
+    path: &Path,
+    data: &'a [u8],
+) -> Option<LoadedFont<'a>> {
+    // Skip files ttf-parser cannot read rather than failing the export
+    let face = ttf_parser::Face::parse(data, 0).ok()?;
+    match doc.add_external_font(data) {
+        Ok(reference) => Some(LoadedFont { face, reference }),
+        Err(e) => {
+            eprintln!("Failed to embed font {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// A block of text laid out with a single style
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Text {
+        text: String,
+        scale: f32,
+        indent_mm: f32,
+        space_before: f32,
+    },
+    Rule,
+}
+
+impl Block {
+    fn text(text: impl Into<String>, scale: f32, indent_mm: f32, space_before: f32) -> Self {
+        Block::Text {
+            text: text.into(),
+            scale,
+            indent_mm,
+            space_before,
+        }
+    }
+}
+
+fn heading_scale(level: HeadingLevel) -> f32 {
+    match level {
+        HeadingLevel::H1 => 1.8,
+        HeadingLevel::H2 => 1.5,
+        HeadingLevel::H3 => 1.25,
+        _ => 1.1,
+    }
+}
+
+/// Flattens Markdown into styled text blocks
+fn markdown_blocks(markdown: &str) -> Vec<Block> {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut blocks = Vec::new();
+    let mut text = String::new();
+    let mut scale = 1.0;
+    let mut list_stack: Vec<Option<u64>> = Vec::new();
+    let mut quote_depth = 0usize;
+    let mut table_cells: Vec<String> = Vec::new();
+
+    let indent =
+        |list_depth: usize, quote_depth: usize| list_depth as f32 * 6.0 + quote_depth as f32 * 6.0;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => scale = heading_scale(level),
+            Event::End(TagEnd::Heading(_)) => {
+                blocks.push(Block::text(std::mem::take(&mut text), scale, 0.0, 1.0));
+                scale = 1.0;
+            }
+            Event::End(TagEnd::Paragraph) => {
+                let depth = list_stack.len();
+                blocks.push(Block::text(
+                    std::mem::take(&mut text),
+                    1.0,
+                    indent(depth, quote_depth),
+                    if depth > 0 { 0.0 } else { 0.6 },
+                ));
+            }
+            Event::Start(Tag::List(start)) => {
+                // Flush text of a parent item before its nested list
+                if !text.trim().is_empty() {
+                    let depth = list_stack.len();
+                    blocks.push(Block::text(
+                        std::mem::take(&mut text),
+                        1.0,
+                        indent(depth, quote_depth),
+                        0.0,
+                    ));
+                }
+                list_stack.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                list_stack.pop();
+            }
+            Event::Start(Tag::Item) => {
+                let marker = match list_stack.last_mut() {
+                    Some(Some(number)) => {
+                        let marker = format!("{}. ", number);
+                        *number += 1;
+                        marker
+                    }
+                    _ => "• ".to_string(),
+                };
+                text.push_str(&marker);
+            }
+            Event::End(TagEnd::Item) if !text.trim().is_empty() => {
+                let depth = list_stack.len();
+                blocks.push(Block::text(
+                    std::mem::take(&mut text),
+                    1.0,
+                    indent(depth, quote_depth),
+                    0.0,
+                ));
+            }
+            Event::TaskListMarker(done) => text.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::Start(Tag::BlockQuote(_)) => quote_depth += 1,
+            Event::End(TagEnd::BlockQuote(_)) => quote_depth = quote_depth.saturating_sub(1),
+            Event::End(TagEnd::CodeBlock) => {
+                for line in std::mem::take(&mut text).trim_end_matches('\n').lines() {
+                    blocks.push(Block::text(
+                        line,
+                        0.9,
+                        indent(list_stack.len(), quote_depth) + 4.0,
+                        0.0,
+                    ));
+                }
+            }
+            Event::End(TagEnd::TableCell) => table_cells.push(std::mem::take(&mut text)),
+            Event::End(TagEnd::TableHead) | Event::End(TagEnd::TableRow) => {
+                let row = std::mem::take(&mut table_cells).join("  |  ");
+                blocks.push(Block::text(row, 1.0, indent(0, quote_depth), 0.0));
+            }
+            Event::Rule => blocks.push(Block::Rule),
+            Event::Text(value) | Event::Code(value) => text.push_str(&value),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            _ => {}
+        }
+    }
+
+    if !text.trim().is_empty() {
+        blocks.push(Block::text(text, 1.0, 0.0, 0.0));
+    }
+    blocks
+}
+
+/// Treats each line of a plain-text note as its own block
+fn plain_text_blocks(text: &str) -> Vec<Block> {
+    text.lines()
+        .map(|line| Block::text(line.replace('\t', "    "), 1.0, 0.0, 0.0))
+        .collect()
+}
+
+/// Returns true for characters after which a line may break without a space
+fn is_breakable(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
+
+/// Wraps a paragraph to lines no wider than `max_width` points
+fn wrap_text(fonts: &FontSet, text: &str, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut width = 0.0;
+        // Position in `line` just after the last break opportunity
+        let mut break_at: Option<usize> = None;
+
+        for c in paragraph.chars() {
+            let char_width = fonts.char_width(c, size);
+            if width + char_width > max_width && !line.is_empty() {
+                let rest = match break_at.filter(|index| *index > 0 && *index < line.len()) {
+                    Some(index) => line.split_off(index),
+                    None => String::new(),
+                };
+                lines.push(line.trim_end().to_string());
+                line = rest.trim_start().to_string();
+                width = fonts.text_width(&line, size);
+                break_at = None;
+            }
+            line.push(c);
+            width += char_width;
+            if c.is_whitespace() || is_breakable(c) {
+                break_at = Some(line.len());
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Tracks the current page and vertical position while laying out
+struct Cursor<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    width: f32,
+    height: f32,
+    margin: f32,
+    y: f32,
+    pages: usize,
+}
+
+impl Cursor<'_> {
+    /// Moves down by `height` millimetres, starting a new page if needed
+    fn advance(&mut self, height: f32) {
+        if self.y - height < self.margin {
+            let (page, layer) = self.doc.add_page(
+                Mm(self.width),
+                Mm(self.height),
+                format!("Page {}", self.pages + 1),
+            );
+            self.layer = self.doc.get_page(page).get_layer(layer);
+            self.pages += 1;
+            self.y = self.height - self.margin;
+        }
+        self.y -= height;
+    }
+}
+
+/// Draws a line of text, switching fonts for characters the primary font lacks
+fn draw_line(layer: &PdfLayerReference, fonts: &FontSet, text: &str, size: f32, x: f32, y: f32) {
+    let mut run = String::new();
+    let mut run_font: Option<FontChoice> = None;
+    let mut run_x = x;
+
+    let flush = |run: &mut String, choice: FontChoice, run_x: &mut f32| {
+        if run.is_empty() {
+            return;
+        }
+        let text: String = if choice == FontChoice::Builtin {
+            // The built-in font only covers Windows-1252
+            run.chars()
+                .map(|c| if (c as u32) < 0x100 { c } else { '?' })
+                .collect()
+        } else {
+            run.clone()
+        };
+        layer.use_text(text, size, Mm(*run_x), Mm(y), fonts.reference(choice));
+        *run_x += fonts.text_width(run, size) / POINTS_PER_MM;
+        run.clear();
+    };
+
+    for c in text.chars() {
+        let choice = fonts.choose(c);
+        if let Some(current) = run_font.filter(|current| *current != choice) {
+            flush(&mut run, current, &mut run_x);
+        }
+        run_font = Some(choice);
+        run.push(c);
+    }
+    if let Some(choice) = run_font {
+        flush(&mut run, choice, &mut run_x);
+    }
+}
+
+/// Renders a note to a paginated PDF document
+pub fn export_pdf(
+    title: &str,
+    content: &str,
+    markdown: bool,
+    options: &PdfOptions,
+) -> Result<Vec<u8>> {
+    let (width, height) = options.page_dimensions()?;
+    let margin = options.margin_mm.clamp(0.0, width.min(height) / 3.0);
+    let base_size = options.font_size.clamp(6.0, 48.0);
+
+    let (doc, page, layer) = PdfDocument::new(title, Mm(width), Mm(height), "Page 1");
+    let font_files = FontFiles::read(options);
+    let fonts = FontSet::load(&doc, &font_files)?;
+    let mut cursor = Cursor {
+        doc: &doc,
+        layer: doc.get_page(page).get_layer(layer),
+        width,
+        height,
+        margin,
+        y: height - margin,
+        pages: 1,
+    };
+
+    let blocks = if markdown {
+        markdown_blocks(content)
+    } else {
+        plain_text_blocks(content)
+    };
+
+    for block in blocks {
+        match block {
+            Block::Text {
+                text,
+                scale,
+                indent_mm,
+                space_before,
+            } => {
+                let size = base_size * scale;
+                let line_height = size * 1.4 / POINTS_PER_MM;
+                let max_width = (width - 2.0 * margin - indent_mm).max(10.0) * POINTS_PER_MM;
+
+                cursor.advance(space_before * line_height);
+                for line in wrap_text(&fonts, &text, size, max_width) {
+                    cursor.advance(line_height);
+                    draw_line(
+                        &cursor.layer,
+                        &fonts,
+                        &line,
+                        size,
+                        margin + indent_mm,
+                        cursor.y,
+                    );
+                }
+            }
+            Block::Rule => {
+                let gap = base_size / POINTS_PER_MM;
+                cursor.advance(gap);
+                cursor.layer.set_outline_thickness(0.5);
+                cursor.layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(margin), Mm(cursor.y)), false),
+                        (Point::new(Mm(width - margin), Mm(cursor.y)), false),
+                    ],
+                    is_closed: false,
+                });
+            }
+        }
+    }
+
+    Ok(doc.save_to_bytes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin_fonts(doc: &PdfDocumentReference) -> FontSet<'static> {
+        FontSet {
+            primary: None,
+            cjk: None,
+            builtin: doc.add_builtin_font(BuiltinFont::Helvetica).unwrap(),
+        }
+    }
+
+    #[test]
+    fn page_sizes() {
+        let mut options = PdfOptions::default();
+        assert_eq!(options.page_dimensions().unwrap(), (210.0, 297.0));
+        options.page_size = "Letter".to_string();
+        options.landscape = true;
+        assert_eq!(options.page_dimensions().unwrap(), (279.4, 215.9));
+        options.page_size = "b9".to_string();
+        assert!(options.page_dimensions().is_err());
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let doc = PdfDocument::empty("test");
+        let fonts = builtin_fonts(&doc);
+        let text = "the quick brown fox jumps over the lazy dog ".repeat(5);
+        let lines = wrap_text(&fonts, text.trim_end(), 11.0, 100.0);
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(fonts.text_width(line, 11.0) <= 100.0, "{:?}", line);
+            assert!(!line.starts_with(' ') && !line.ends_with(' '));
+        }
+        assert_eq!(lines.join(" "), text.trim_end());
+    }
+
+    #[test]
+    fn wraps_cjk_between_characters() {
+        let doc = PdfDocument::empty("test");
+        let fonts = builtin_fonts(&doc);
+        let text = "中文段落".repeat(10);
+        let lines = wrap_text(&fonts, &text, 11.0, 60.0);
+        assert!(lines.len() > 1);
+        assert_eq!(lines.concat(), text);
+    }
+
+    #[test]
+    fn keeps_blank_lines() {
+        let doc = PdfDocument::empty("test");
+        let fonts = builtin_fonts(&doc);
+        assert_eq!(wrap_text(&fonts, "a\n\nb", 11.0, 100.0), ["a", "", "b"]);
+    }
+
+    #[test]
+    fn measures_with_installed_font() {
+        let files = FontFiles::read(&PdfOptions::default());
+        if files.primary.is_none() {
+            eprintln!("No PDF font installed, skipping");
+            return;
+        }
+        let doc = PdfDocument::empty("test");
+        let fonts = FontSet::load(&doc, &files).unwrap();
+        let primary = fonts.primary.as_ref().unwrap();
+        assert!(primary.has_glyph('W'));
+        assert!(fonts.char_width('W', 11.0) > fonts.char_width('i', 11.0));
+        let width = fonts.text_width("WW", 22.0);
+        assert!((width - 4.0 * fonts.char_width('W', 11.0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn exports_markdown_and_plain_text() {
+        let markdown = "# Title\n\n- item\n\n> quote\n\n---\n\n中文\n".repeat(40);
+        let bytes = export_pdf("Note", &markdown, true, &PdfOptions::default()).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+
+        let options = PdfOptions {
+            font_path: Some("/nonexistent/font.ttf".to_string()),
+            ..PdfOptions::default()
+        };
+        let bytes = export_pdf("Note", "plain\ttext", false, &options).unwrap();
+        assert!(bytes.starts_with(b"%PDF"));
+    }
+}
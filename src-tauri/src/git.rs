@@ -0,0 +1,486 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Identity used for auto-commits when the user has not configured one
+const FALLBACK_NAME: &str = "QuickNote";
+const FALLBACK_EMAIL: &str = "quicknote@localhost";
+
+/// Separators used in `git log` output so messages can contain anything
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// A file with uncommitted changes
+#[derive(Debug, Clone, Serialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    /// Porcelain status letters for the index and the working tree, e.g. "M", "?", "A"
+    pub index: String,
+    pub worktree: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitStatus {
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub files: Vec<GitFileStatus>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GitCommit {
+    pub hash: String,
+    pub short_hash: String,
+    pub author: String,
+    /// ISO 8601 author date
+    pub date: String,
+    pub message: String,
+}
+
+/// A git working tree, driven through the `git` command line
+pub struct GitRepo {
+    root: PathBuf,
+}
+
+impl GitRepo {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Runs git in the repository and returns stdout, failing with stderr on a non-zero exit
+    async fn run(&self, args: &[&str]) -> Result<String> {
+        let stdout = self.run_raw(args).await?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+
+    /// Like `run`, but keeps stdout as bytes for file content that may not be text
+    async fn run_raw(&self, args: &[&str]) -> Result<Vec<u8>> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .await
+            .context("Failed to run git; is it installed?")?;
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            // Name the subcommand, skipping any leading "-c key=value" options
+            let command = args
+                .iter()
+                .find(|arg| !arg.starts_with('-') && !arg.contains('='))
+                .unwrap_or(&"");
+            Err(anyhow!("git {} failed: {}", command, stderr.trim()))
+        }
+    }
+
+    /// Runs git and only reports whether it exited successfully
+    async fn succeeds(&self, args: &[&str]) -> Result<bool> {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .output()
+            .await
+            .context("Failed to run git; is it installed?")?
+            .status;
+        Ok(status.success())
+    }
+
+    pub async fn is_repo(&self) -> bool {
+        self.succeeds(&["rev-parse", "--is-inside-work-tree"])
+            .await
+            .unwrap_or(false)
+    }
+
+    /// True once the repository has at least one commit
+    pub async fn has_commits(&self) -> bool {
+        self.succeeds(&["rev-parse", "--verify", "HEAD"])
+            .await
+            .unwrap_or(false)
+    }
+
+    pub async fn init(&self) -> Result<()> {
+        self.run(&["init"]).await.map(|_| ())
+    }
+
+    /// Path of `path` relative to the repository root, with forward slashes
+    fn relative(&self, path: &Path) -> Result<String> {
+        let relative = path
+            .strip_prefix(&self.root)
+            .map_err(|_| anyhow!("{} is not inside the notes repository", path.display()))?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().to_string())
+            .collect();
+        Ok(parts.join("/"))
+    }
+
+    /// Identity flags to pass when no user.name/user.email is configured
+    async fn identity_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (key, fallback) in [("user.name", FALLBACK_NAME), ("user.email", FALLBACK_EMAIL)] {
+            let configured = self
+                .run(&["config", key])
+                .await
+                .map(|value| !value.trim().is_empty())
+                .unwrap_or(false);
+            if !configured {
+                args.push("-c".to_string());
+                args.push(format!("{}={}", key, fallback));
+            }
+        }
+        args
+    }
+
+    /// Commits the current content of one file; returns the new commit hash, or None if unchanged
+    pub async fn commit_file(&self, path: &Path) -> Result<Option<String>> {
+        let relative = self.relative(path)?;
+        let tracked = self
+            .succeeds(&["ls-files", "--error-unmatch", "--", &relative])
+            .await?;
+        self.run(&["add", "--", &relative]).await?;
+        if self
+            .succeeds(&["diff", "--cached", "--quiet", "--", &relative])
+            .await?
+        {
+            return Ok(None);
+        }
+
+        let message = if tracked {
+            format!("Update {}", relative)
+        } else {
+            format!("Add {}", relative)
+        };
+        let mut args = self.identity_args().await;
+        args.extend(
+            ["commit", "--quiet", "-m", &message, "--", &relative]
+                .iter()
+                .map(|arg| arg.to_string()),
+        );
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        self.run(&args).await?;
+        Ok(Some(
+            self.run(&["rev-parse", "HEAD"]).await?.trim().to_string(),
+        ))
+    }
+
+    /// Current branch, upstream tracking info and dirty files
+    pub async fn status(&self) -> Result<GitStatus> {
+        let output = self
+            .run(&["status", "--porcelain=v1", "--branch", "-z"])
+            .await?;
+        Ok(parse_status(&output))
+    }
+
+    /// Commits that touched a file, newest first, following renames
+    pub async fn log_file(&self, path: &Path, limit: usize) -> Result<Vec<GitCommit>> {
+        let relative = self.relative(path)?;
+        let format = format!(
+            "--format=%H{0}%h{0}%an{0}%aI{0}%s{1}",
+            FIELD_SEPARATOR, RECORD_SEPARATOR
+        );
+        let limit = format!("--max-count={}", limit.max(1));
+        let output = match self
+            .run(&["log", "--follow", &format, &limit, "--", &relative])
+            .await
+        {
+            Ok(output) => output,
+            // A repository without commits has no history yet
+            Err(_) if !self.succeeds(&["rev-parse", "--verify", "HEAD"]).await? => {
+                return Ok(Vec::new())
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(output
+            .split(RECORD_SEPARATOR)
+            .filter_map(|record| {
+                let fields: Vec<&str> = record.trim().split(FIELD_SEPARATOR).collect();
+                match fields.as_slice() {
+                    [hash, short_hash, author, date, message] => Some(GitCommit {
+                        hash: hash.to_string(),
+                        short_hash: short_hash.to_string(),
+                        author: author.to_string(),
+                        date: date.to_string(),
+                        message: message.to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .collect())
+    }
+
+    /// Content of a file as of a commit
+    pub async fn show_file(&self, path: &Path, hash: &str) -> Result<Vec<u8>> {
+        // An empty hash would show the staged version instead
+        if hash.len() < 4 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow!("Invalid commit hash: {}", hash));
+        }
+        let relative = self.relative(path)?;
+        self.run_raw(&["show", &format!("{}:./{}", hash, relative)])
+            .await
+    }
+
+    /// Points `remote` at `url`, adding it if needed
+    pub async fn set_remote(&self, remote: &str, url: &str) -> Result<()> {
+        match self.run(&["remote", "get-url", remote]).await {
+            Ok(current) if current.trim() == url => Ok(()),
+            Ok(_) => self
+                .run(&["remote", "set-url", remote, url])
+                .await
+                .map(|_| ()),
+            Err(_) => self.run(&["remote", "add", remote, url]).await.map(|_| ()),
+        }
+    }
+
+    async fn current_branch(&self) -> Result<String> {
+        let branch = self.run(&["symbolic-ref", "--short", "HEAD"]).await?;
+        Ok(branch.trim().to_string())
+    }
+
+    /// Rebases local commits onto the remote branch, aborting cleanly on conflicts
+    pub async fn pull(&self, remote: &str, branch: Option<&str>) -> Result<String> {
+        let branch = match branch {
+            Some(branch) => branch.to_string(),
+            None => self.current_branch().await?,
+        };
+        self.run(&["fetch", remote, &branch]).await?;
+        let remote_ref = format!("{}/{}", remote, branch);
+        // An empty local repository simply adopts the remote branch
+        if !self.succeeds(&["rev-parse", "--verify", "HEAD"]).await? {
+            self.run(&["reset", "--hard", &remote_ref]).await?;
+            return Ok(format!("Checked out {}", remote_ref));
+        }
+
+        let mut args = self.identity_args().await;
+        args.extend(
+            ["rebase", "--autostash", &remote_ref]
+                .iter()
+                .map(|arg| arg.to_string()),
+        );
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match self.run(&args).await {
+            Ok(_) => Ok(format!("Synced with {}", remote_ref)),
+            Err(e) => {
+                let _ = self.run(&["rebase", "--abort"]).await;
+                Err(anyhow!("Pull needs manual conflict resolution: {}", e))
+            }
+        }
+    }
+
+    /// Pushes the current branch and sets it to track the remote
+    pub async fn push(&self, remote: &str, branch: Option<&str>) -> Result<String> {
+        let local = self.current_branch().await?;
+        let refspec = format!("HEAD:refs/heads/{}", branch.unwrap_or(&local));
+        let output = self
+            .run(&["push", "--porcelain", "--set-upstream", remote, &refspec])
+            .await?;
+        Ok(output.trim().to_string())
+    }
+}
+
+/// Parses the output of `git status --porcelain=v1 --branch -z`
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus {
+        branch: None,
+        upstream: None,
+        ahead: 0,
+        behind: 0,
+        files: Vec::new(),
+    };
+
+    let mut records = output.split('\0').filter(|record| !record.is_empty());
+    while let Some(record) = records.next() {
+        if let Some(header) = record.strip_prefix("## ") {
+            parse_branch_header(header, &mut status);
+            continue;
+        }
+        if record.len() < 4 {
+            continue;
+        }
+        let (codes, path) = record.split_at(3);
+        let index = codes[0..1].trim().to_string();
+        // Renames are followed by a second record holding the original path
+        if index == "R" || index == "C" {
+            records.next();
+        }
+        status.files.push(GitFileStatus {
+            path: path.to_string(),
+            index,
+            worktree: codes[1..2].trim().to_string(),
+        });
+    }
+    status
+}
+
+/// Parses "main...origin/main [ahead 1, behind 2]" from `git status --branch`
+fn parse_branch_header(header: &str, status: &mut GitStatus) {
+    let (names, tracking) = match header.split_once(" [") {
+        Some((names, tracking)) => (names, tracking.trim_end_matches(']')),
+        None => (header, ""),
+    };
+    let names = names
+        .strip_prefix("No commits yet on ")
+        .or_else(|| names.strip_prefix("Initial commit on "))
+        .unwrap_or(names);
+    match names.split_once("...") {
+        Some((branch, upstream)) => {
+            status.branch = Some(branch.to_string());
+            status.upstream = Some(upstream.to_string());
+        }
+        None if names != "HEAD (no branch)" => status.branch = Some(names.to_string()),
+        None => {}
+    }
+    for part in tracking.split(", ") {
+        if let Some(count) = part.strip_prefix("ahead ") {
+            status.ahead = count.parse().unwrap_or(0);
+        } else if let Some(count) = part.strip_prefix("behind ") {
+            status.behind = count.parse().unwrap_or(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_status() -> GitStatus {
+        parse_status("")
+    }
+
+    fn git_installed() -> bool {
+        std::process::Command::new("git")
+            .arg("--version")
+            .output()
+            .is_ok()
+    }
+
+    /// A fresh directory under the system temp folder
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("quicknote-git-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn branch_header_with_tracking() {
+        let mut status = empty_status();
+        parse_branch_header("main...origin/main [ahead 1, behind 2]", &mut status);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (1, 2));
+    }
+
+    #[test]
+    fn branch_header_without_upstream() {
+        let mut status = empty_status();
+        parse_branch_header("No commits yet on main", &mut status);
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream, None);
+
+        let mut status = empty_status();
+        parse_branch_header("HEAD (no branch)", &mut status);
+        assert_eq!(status.branch, None);
+    }
+
+    #[test]
+    fn status_records() {
+        let output = "## main...origin/main [behind 3]\0 M notes/a.md\0R  new name.md\0old name.md\0?? draft.md\0";
+        let status = parse_status(output);
+        assert_eq!(status.behind, 3);
+        let files: Vec<(&str, &str, &str)> = status
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.path.as_str(),
+                    file.index.as_str(),
+                    file.worktree.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            [
+                ("notes/a.md", "", "M"),
+                ("new name.md", "R", ""),
+                ("draft.md", "?", "?"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn show_file_needs_a_hash() {
+        let repo = GitRepo::new(std::env::temp_dir());
+        let path = std::env::temp_dir().join("note.md");
+        for hash in ["", "abc", "HEAD", "abcd:../x"] {
+            assert!(repo.show_file(&path, hash).await.is_err(), "{:?}", hash);
+        }
+    }
+
+    #[tokio::test]
+    async fn commits_pushes_and_pulls_through_a_bare_repository() {
+        if !git_installed() {
+            eprintln!("git is not installed, skipping");
+            return;
+        }
+        let dir = temp_dir("sync");
+        let remote = dir.join("remote.git");
+        let remote_url = remote.to_string_lossy().to_string();
+        GitRepo::new(&dir)
+            .run(&["init", "--bare", "--quiet", &remote_url])
+            .await
+            .unwrap();
+
+        let first = GitRepo::new(dir.join("first"));
+        std::fs::create_dir_all(dir.join("first/sub")).unwrap();
+        first.init().await.unwrap();
+        let note = dir.join("first/sub/a note.md");
+        std::fs::write(&note, "one").unwrap();
+        let hash = first.commit_file(&note).await.unwrap().unwrap();
+        assert_eq!(first.commit_file(&note).await.unwrap(), None);
+        std::fs::write(&note, "two").unwrap();
+        first.commit_file(&note).await.unwrap().unwrap();
+
+        let log = first.log_file(&note, 10).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[1].hash, hash);
+        assert_eq!(first.show_file(&note, &hash).await.unwrap(), b"one");
+
+        first.set_remote("origin", &remote_url).await.unwrap();
+        first.push("origin", None).await.unwrap();
+        let branch = first.status().await.unwrap().branch.unwrap();
+
+        let second = GitRepo::new(dir.join("second"));
+        std::fs::create_dir_all(dir.join("second")).unwrap();
+        second.init().await.unwrap();
+        second.set_remote("origin", &remote_url).await.unwrap();
+        second.pull("origin", Some(&branch)).await.unwrap();
+        let copy = dir.join("second/sub/a note.md");
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "two");
+
+        std::fs::write(&copy, "three").unwrap();
+        second.commit_file(&copy).await.unwrap();
+        second.push("origin", None).await.unwrap();
+        first.pull("origin", None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "three");
+
+        // Conflicting edits leave the local commit in place
+        std::fs::write(&note, "local").unwrap();
+        first.commit_file(&note).await.unwrap();
+        std::fs::write(&copy, "remote").unwrap();
+        second.commit_file(&copy).await.unwrap();
+        second.push("origin", None).await.unwrap();
+        assert!(first.pull("origin", None).await.is_err());
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "local");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::storage::write_atomic;
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of most recent snapshots that are always kept
+const KEEP_LAST: usize = 20;
+/// Snapshots younger than this are thinned to one per hour
+const HOURLY_WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+/// Snapshots younger than this are thinned to one per day
+const DAILY_WINDOW_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * HOUR_MS;
+
+const INDEX_FILE: &str = "index.json";
+
+/// Metadata about one stored snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub id: String,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: i64,
+    pub size: u64,
+    pub hash: String,
+}
+
+/// Per-file list of snapshots, stored as index.json next to them
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryIndex {
+    path: String,
+    versions: Vec<VersionInfo>,
+}
+
+/// Snapshot store rooted in the app data directory
+pub struct HistoryStore {
+    root: PathBuf,
+}
+
+/// Hashes content so identical saves do not create new snapshots
+pub fn content_hash(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+/// Returns the current time in milliseconds since the Unix epoch
+pub fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+impl HistoryStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Folder holding the snapshots of a single file
+    fn file_dir(&self, path: &Path) -> PathBuf {
+        let key = content_hash(path.to_string_lossy().as_bytes());
+        self.root.join(&key[..32])
+    }
+
+    fn load_index(&self, path: &Path) -> Result<HistoryIndex> {
+        let index_path = self.file_dir(path).join(INDEX_FILE);
+        if !index_path.exists() {
+            return Ok(HistoryIndex {
+                path: path.to_string_lossy().to_string(),
+                versions: Vec::new(),
+            });
+        }
+        let content = std::fs::read_to_string(&index_path)?;
+        serde_json::from_str(&content).context("History index is corrupted")
+    }
+
+    fn save_index(&self, path: &Path, index: &HistoryIndex) -> Result<()> {
+        let content = serde_json::to_string_pretty(index)?;
+        write_atomic(&self.file_dir(path).join(INDEX_FILE), content.as_bytes())
+    }
+
+    /// Stores a compressed snapshot of `content`, skipping it if nothing changed
+    pub fn record(
+        &self,
+        path: &Path,
+        content: &str,
+        timestamp: i64,
+    ) -> Result<Option<VersionInfo>> {
+        let mut index = self.load_index(path)?;
+        let hash = content_hash(content.as_bytes());
+        if index.versions.last().map(|v| v.hash.as_str()) == Some(hash.as_str()) {
+            return Ok(None);
+        }
+
+        // Ids are millisecond timestamps, bumped if two saves land in the same millisecond
+        let mut stamp = timestamp;
+        while index.versions.iter().any(|v| v.id == stamp.to_string()) {
+            stamp += 1;
+        }
+        let version = VersionInfo {
+            id: stamp.to_string(),
+            timestamp,
+            size: content.len() as u64,
+            hash,
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content.as_bytes())?;
+        let snapshot = self.file_dir(path).join(format!("{}.gz", version.id));
+        write_atomic(&snapshot, &encoder.finish()?)?;
+
+        index.path = path.to_string_lossy().to_string();
+        index.versions.push(version.clone());
+        self.prune(path, &mut index, timestamp);
+        self.save_index(path, &index)?;
+        Ok(Some(version))
+    }
+
+    /// Lists stored snapshots for a file, newest first
+    pub fn list(&self, path: &Path) -> Result<Vec<VersionInfo>> {
+        let mut versions = self.load_index(path)?.versions;
+        versions.reverse();
+        Ok(versions)
+    }
+
+    /// Reads the content of one snapshot
+    pub fn read(&self, path: &Path, id: &str) -> Result<String> {
+        let index = self.load_index(path)?;
+        if !index.versions.iter().any(|v| v.id == id) {
+            return Err(anyhow!("Version not found: {}", id));
+        }
+        let compressed = std::fs::read(self.file_dir(path).join(format!("{}.gz", id)))?;
+        let mut content = String::new();
+        GzDecoder::new(compressed.as_slice()).read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    /// Deletes every snapshot of a file
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        let dir = self.file_dir(path);
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the snapshots of every file under `root`
+    pub fn remove_under(&self, root: &Path) -> Result<()> {
+        if !self.root.exists() {
+            return Ok(());
+        }
+        for entry in std::fs::read_dir(&self.root)? {
+            let dir = entry?.path();
+            let index: HistoryIndex = match std::fs::read_to_string(dir.join(INDEX_FILE))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+            {
+                Some(index) => index,
+                None => continue,
+            };
+            if Path::new(&index.path).starts_with(root) {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the retention rules: last N, hourly for a day, daily for a month
+    fn prune(&self, path: &Path, index: &mut HistoryIndex, now: i64) {
+        let keep = retained_ids(&index.versions, now);
+        let dir = self.file_dir(path);
+        for version in index.versions.iter().filter(|v| !keep.contains(&v.id)) {
+            let _ = std::fs::remove_file(dir.join(format!("{}.gz", version.id)));
+        }
+        index.versions.retain(|v| keep.contains(&v.id));
+    }
+}
+
+/// Decides which snapshots survive the retention rules
+fn retained_ids(versions: &[VersionInfo], now: i64) -> HashSet<String> {
+    let mut keep = HashSet::new();
+    let mut hours_seen = HashSet::new();
+    let mut days_seen = HashSet::new();
+
+    // Walk newest to oldest so the latest snapshot of each bucket wins
+    for (position, version) in versions.iter().rev().enumerate() {
+        let age = now - version.timestamp;
+        let hour = version.timestamp.div_euclid(HOUR_MS);
+        let day = version.timestamp.div_euclid(DAY_MS);
+
+        let kept = position < KEEP_LAST
+            || (age <= HOURLY_WINDOW_MS && !hours_seen.contains(&hour))
+            || (age > HOURLY_WINDOW_MS && age <= DAILY_WINDOW_MS && !days_seen.contains(&day));
+        if kept {
+            keep.insert(version.id.clone());
+        }
+        hours_seen.insert(hour);
+        days_seen.insert(day);
+    }
+    keep
+}
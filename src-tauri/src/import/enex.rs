@@ -0,0 +1,94 @@
+use super::{html, ImportedNote, ParsedNotes, SkippedNote};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use roxmltree::{Document, Node, ParsingOptions};
+
+lazy_static! {
+    static ref TODO_RE: Regex = Regex::new(r#"<en-todo\b([^>]*?)/?>(\s*</en-todo>)?"#).unwrap();
+    // html2md escapes the list marker we put in front of converted checkboxes
+    static ref ESCAPED_TASK_RE: Regex = Regex::new(r"(?m)^\\- \[([ x])\] ").unwrap();
+    static ref MEDIA_RE: Regex = Regex::new(r#"<en-media\b[^>]*?(/>|>\s*</en-media>)"#).unwrap();
+}
+
+/// Parses ENEX timestamps such as "20231015T120000Z"
+fn parse_enex_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|date| date.and_utc())
+}
+
+fn child_text<'a>(note: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    note.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+}
+
+/// Converts an ENML body to Markdown, keeping checkboxes and marking attachments
+fn enml_to_markdown(enml: &str) -> String {
+    let with_todos = TODO_RE.replace_all(enml, |caps: &regex::Captures| {
+        if caps[1].contains("checked=\"true\"") {
+            "- [x] "
+        } else {
+            "- [ ] "
+        }
+    });
+    let with_media = MEDIA_RE.replace_all(&with_todos, "<p><em>[attachment not imported]</em></p>");
+    let markdown = html::html_to_markdown(&with_media);
+    ESCAPED_TASK_RE
+        .replace_all(&markdown, "- [$1] ")
+        .to_string()
+}
+
+/// Reads every note in an Evernote ENEX export
+pub fn parse_enex(xml: &str) -> Result<ParsedNotes> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let document = Document::parse_with_options(xml, options).context("Invalid ENEX file")?;
+    let mut parsed = ParsedNotes::default();
+
+    for (index, note) in document
+        .descendants()
+        .filter(|node| node.has_tag_name("note"))
+        .enumerate()
+    {
+        let title = child_text(note, "title")
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Untitled {}", index + 1));
+
+        let content = match child_text(note, "content") {
+            Some(content) => content,
+            None => {
+                parsed.skipped.push(SkippedNote {
+                    source: title,
+                    reason: "Note has no content".to_string(),
+                });
+                continue;
+            }
+        };
+
+        let tags = note
+            .children()
+            .filter(|child| child.has_tag_name("tag"))
+            .filter_map(|child| child.text())
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        parsed.notes.push(ImportedNote {
+            title,
+            body: enml_to_markdown(content),
+            created: child_text(note, "created").and_then(parse_enex_date),
+            updated: child_text(note, "updated").and_then(parse_enex_date),
+            tags,
+            folder: None,
+        });
+    }
+
+    Ok(parsed)
+}
@@ -0,0 +1,136 @@
+use super::{title_from_body, ImportedNote, ParsedNotes, SkippedNote};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+use walkdir::WalkDir;
+
+lazy_static! {
+    static ref TITLE_RE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref H1_RE: Regex = Regex::new(r"(?is)<h1[^>]*>(.*?)</h1>").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"<[^>]+>").unwrap();
+    static ref HASHTAG_RE: Regex =
+        Regex::new(r"(?:^|\s)#([\p{L}\p{N}_][\p{L}\p{N}_\-/]*)").unwrap();
+    static ref HEAD_RE: Regex =
+        Regex::new(r"(?is)<head\b.*?</head>|<style\b.*?</style>|<script\b.*?</script>").unwrap();
+    static ref SETEXT_RE: Regex = Regex::new(r"(?m)^(\S.*)\n(=+|-+)[ \t]*$").unwrap();
+    static ref BLANK_LINES_RE: Regex = Regex::new(r"\n{3,}").unwrap();
+}
+
+/// Decodes the handful of entities that appear in exported titles
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Converts an HTML fragment or document to Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let body = HEAD_RE.replace_all(html, "");
+    let markdown = html2md::parse_html(&body).replace('\u{a0}', " ");
+    // Prefer ATX headings, which is what the rest of the app writes
+    let markdown = SETEXT_RE.replace_all(&markdown, |caps: &regex::Captures| {
+        let level = if caps[2].starts_with('=') { "#" } else { "##" };
+        format!("{} {}", level, &caps[1])
+    });
+    BLANK_LINES_RE
+        .replace_all(markdown.trim(), "\n\n")
+        .to_string()
+}
+
+/// Extracts the text of the first match of `re`, without inner tags
+fn first_text(re: &Regex, html: &str) -> Option<String> {
+    re.captures(html)
+        .map(|caps| decode_entities(&TAG_RE.replace_all(&caps[1], "")))
+        .map(|text| text.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|text| !text.is_empty())
+}
+
+/// Apple Notes keeps tags inline as #hashtags
+fn hashtags(body: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for caps in HASHTAG_RE.captures_iter(body) {
+        let tag = caps[1].trim_end_matches(['-', '/']).to_string();
+        if !tag.is_empty() && !tag.chars().all(|c| c.is_ascii_digit()) && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn is_html_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            matches!(
+                ext.to_string_lossy().to_lowercase().as_str(),
+                "html" | "htm"
+            )
+        })
+        .unwrap_or(false)
+}
+
+/// Reads one exported HTML note, using file times for its dates
+fn parse_html_file(path: &Path, root: &Path) -> Result<ImportedNote> {
+    let html = std::fs::read_to_string(path)?;
+    let body = html_to_markdown(&html);
+    let title = first_text(&TITLE_RE, &html)
+        .or_else(|| first_text(&H1_RE, &html))
+        .or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| title_from_body(&body));
+
+    let metadata = std::fs::metadata(path)?;
+    let created = metadata.created().ok().map(DateTime::<Utc>::from);
+    let updated = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+    // Keep the export's folder structure, e.g. one folder per Apple Notes folder
+    let folder = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .filter(|relative| !relative.as_os_str().is_empty())
+        .map(Path::to_path_buf);
+
+    Ok(ImportedNote {
+        title,
+        tags: hashtags(&body),
+        body,
+        created,
+        updated,
+        folder,
+    })
+}
+
+/// Reads a single HTML note or every HTML file under a folder
+pub fn parse_html_source(source: &Path) -> Result<ParsedNotes> {
+    let (root, files) = if source.is_dir() {
+        let files = WalkDir::new(source)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file() && is_html_file(entry.path()))
+            .map(|entry| entry.into_path())
+            .collect();
+        (source.to_path_buf(), files)
+    } else {
+        let root = source.parent().unwrap_or(source).to_path_buf();
+        (root, vec![source.to_path_buf()])
+    };
+
+    let mut parsed = ParsedNotes::default();
+    for file in files {
+        match parse_html_file(&file, &root) {
+            Ok(note) => parsed.notes.push(note),
+            Err(e) => parsed.skipped.push(SkippedNote {
+                source: file.to_string_lossy().to_string(),
+                reason: e.to_string(),
+            }),
+        }
+    }
+    Ok(parsed)
+}
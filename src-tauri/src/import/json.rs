@@ -0,0 +1,138 @@
+use super::{title_from_body, ImportedNote, ParsedNotes, SkippedNote};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn parse_date(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    match value? {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|date| date.with_timezone(&Utc)),
+        // Older Simplenote exports use Unix seconds
+        Value::Number(number) => {
+            DateTime::from_timestamp_millis((number.as_f64()? * 1000.0) as i64)
+        }
+        _ => None,
+    }
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|tag| tag.trim().to_string())
+                .filter(|tag| !tag.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Simplenote export: `activeNotes` and `trashedNotes`, titled by their first line
+fn parse_simplenote(root: &Value) -> ParsedNotes {
+    let mut parsed = ParsedNotes::default();
+    for note in root["activeNotes"].as_array().into_iter().flatten() {
+        let body = note["content"].as_str().unwrap_or("").to_string();
+        if body.trim().is_empty() {
+            parsed.skipped.push(SkippedNote {
+                source: note["id"].as_str().unwrap_or("note").to_string(),
+                reason: "Note is empty".to_string(),
+            });
+            continue;
+        }
+        parsed.notes.push(ImportedNote {
+            title: title_from_body(&body),
+            created: parse_date(note.get("creationDate")),
+            updated: parse_date(note.get("lastModified")),
+            tags: string_list(note.get("tags")),
+            body,
+            folder: None,
+        });
+    }
+
+    let trashed = root["trashedNotes"].as_array().map_or(0, Vec::len);
+    if trashed > 0 {
+        parsed.skipped.push(SkippedNote {
+            source: "trashedNotes".to_string(),
+            reason: format!("{} trashed notes are not imported", trashed),
+        });
+    }
+    parsed
+}
+
+/// Standard Notes backup: a flat `items` list where tags reference their notes
+fn parse_standard_notes(root: &Value) -> ParsedNotes {
+    let mut parsed = ParsedNotes::default();
+    let items: &[Value] = root["items"].as_array().map_or(&[], Vec::as_slice);
+
+    // Map each note uuid to the titles of the tags that reference it
+    let mut tags_by_note: HashMap<&str, Vec<String>> = HashMap::new();
+    for item in items.iter().filter(|item| item["content_type"] == "Tag") {
+        let title = match item["content"]["title"].as_str() {
+            Some(title) => title.trim().to_string(),
+            None => continue,
+        };
+        for reference in item["content"]["references"]
+            .as_array()
+            .into_iter()
+            .flatten()
+        {
+            if let Some(uuid) = reference["uuid"].as_str() {
+                tags_by_note.entry(uuid).or_default().push(title.clone());
+            }
+        }
+    }
+
+    for item in items.iter().filter(|item| item["content_type"] == "Note") {
+        let uuid = item["uuid"].as_str().unwrap_or("");
+        if item["deleted"].as_bool() == Some(true) || item["content"]["trashed"] == true {
+            parsed.skipped.push(SkippedNote {
+                source: uuid.to_string(),
+                reason: "Note is deleted or trashed".to_string(),
+            });
+            continue;
+        }
+        // Encrypted backups store content as an opaque string
+        if !item["content"].is_object() {
+            parsed.skipped.push(SkippedNote {
+                source: uuid.to_string(),
+                reason: "Note is encrypted; export a decrypted backup".to_string(),
+            });
+            continue;
+        }
+
+        let body = item["content"]["text"].as_str().unwrap_or("").to_string();
+        let title = item["content"]["title"]
+            .as_str()
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| title_from_body(&body));
+        parsed.notes.push(ImportedNote {
+            title,
+            body,
+            created: parse_date(item.get("created_at")),
+            updated: parse_date(item.get("updated_at")),
+            tags: tags_by_note.remove(uuid).unwrap_or_default(),
+            folder: None,
+        });
+    }
+    parsed
+}
+
+/// Reads a Simplenote or Standard Notes JSON backup
+pub fn parse_json(text: &str) -> Result<ParsedNotes> {
+    let root: Value = serde_json::from_str(text).context("Invalid JSON backup")?;
+    if root.get("activeNotes").is_some() {
+        Ok(parse_simplenote(&root))
+    } else if root.get("items").is_some() {
+        Ok(parse_standard_notes(&root))
+    } else {
+        Err(anyhow!(
+            "Unrecognised JSON backup; expected a Simplenote or Standard Notes export"
+        ))
+    }
+}
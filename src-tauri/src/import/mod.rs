@@ -0,0 +1,296 @@
+pub mod enex;
+pub mod html;
+pub mod json;
+
+use crate::crypto::VaultKey;
+use crate::vault;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Longest file name stem generated from a note title
+const MAX_STEM_CHARS: usize = 100;
+
+/// A note read from another application, with its body already converted to Markdown
+#[derive(Debug, Clone, Default)]
+pub struct ImportedNote {
+    pub title: String,
+    pub body: String,
+    pub created: Option<DateTime<Utc>>,
+    pub updated: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    /// Subfolder of the import target the note is written to
+    pub folder: Option<PathBuf>,
+}
+
+/// A note in the source that could not be imported
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedNote {
+    pub source: String,
+    pub reason: String,
+}
+
+/// Everything a parser found in a source file or folder
+#[derive(Debug, Default)]
+pub struct ParsedNotes {
+    pub notes: Vec<ImportedNote>,
+    pub skipped: Vec<SkippedNote>,
+}
+
+/// A note that was (or in a dry run, would be) created
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedEntry {
+    pub title: String,
+    pub path: String,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub tags: Vec<String>,
+    /// True if the file name was changed to avoid overwriting an existing file
+    pub renamed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub format: String,
+    pub created: Vec<ImportedEntry>,
+    pub skipped: Vec<SkippedNote>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Evernote ENEX export
+    Enex,
+    /// Folder or file of HTML notes, e.g. an Apple Notes export
+    Html,
+    /// Simplenote or Standard Notes JSON backup
+    Json,
+}
+
+impl ImportFormat {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "enex" | "evernote" => Ok(ImportFormat::Enex),
+            "html" | "apple-notes" => Ok(ImportFormat::Html),
+            "json" | "simplenote" | "standard-notes" => Ok(ImportFormat::Json),
+            other => Err(anyhow!("Unsupported import format: {}", other)),
+        }
+    }
+
+    /// Guesses the format from the source path
+    pub fn detect(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            return Ok(ImportFormat::Html);
+        }
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "enex" => Ok(ImportFormat::Enex),
+            "html" | "htm" => Ok(ImportFormat::Html),
+            "json" => Ok(ImportFormat::Json),
+            _ => Err(anyhow!("Cannot detect import format of {}", path.display())),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportFormat::Enex => "enex",
+            ImportFormat::Html => "html",
+            ImportFormat::Json => "json",
+        }
+    }
+}
+
+/// Reads all notes from a source file or folder
+pub fn parse_source(source: &Path, format: ImportFormat) -> Result<ParsedNotes> {
+    match format {
+        ImportFormat::Enex => {
+            let xml = std::fs::read_to_string(source)
+                .with_context(|| format!("Failed to read {}", source.display()))?;
+            enex::parse_enex(&xml)
+        }
+        ImportFormat::Html => html::parse_html_source(source),
+        ImportFormat::Json => {
+            let text = std::fs::read_to_string(source)
+                .with_context(|| format!("Failed to read {}", source.display()))?;
+            json::parse_json(&text)
+        }
+    }
+}
+
+fn format_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Quotes a front matter value when YAML would otherwise misread it
+fn yaml_value(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(|c: char| c.is_whitespace() || "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        && !value.ends_with(char::is_whitespace)
+        && !value.contains(": ")
+        && !value.contains(" #");
+    if plain {
+        value.to_string()
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Renders a note as Markdown with a front matter block holding its metadata
+pub fn render_note(note: &ImportedNote) -> String {
+    let mut output = String::from("---\n");
+    output.push_str(&format!("title: {}\n", yaml_value(&note.title)));
+    if let Some(created) = &note.created {
+        output.push_str(&format!("created: {}\n", format_date(created)));
+    }
+    if let Some(updated) = &note.updated {
+        output.push_str(&format!("updated: {}\n", format_date(updated)));
+    }
+    if !note.tags.is_empty() {
+        let tags: Vec<String> = note.tags.iter().map(|tag| yaml_value(tag)).collect();
+        output.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    }
+    output.push_str("---\n\n");
+
+    // Add the title as a heading unless the body already starts with it
+    let body = note.body.trim();
+    let first_line = body.lines().next().unwrap_or("");
+    if first_line.trim_start_matches('#').trim() != note.title.trim() {
+        output.push_str(&format!("# {}\n\n", note.title.trim()));
+    }
+    output.push_str(body);
+    output.push('\n');
+    output
+}
+
+/// Turns a note title into a safe file name stem
+pub fn sanitize_file_name(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .take(MAX_STEM_CHARS)
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// Picks a file path that neither exists on disk nor was already planned in this import
+fn unique_path(dir: &Path, stem: &str, planned: &mut HashSet<String>) -> (PathBuf, bool) {
+    let mut counter = 1;
+    loop {
+        let name = if counter == 1 {
+            format!("{}.md", stem)
+        } else {
+            format!("{} {}.md", stem, counter)
+        };
+        let path = dir.join(&name);
+        let key = path.to_string_lossy().to_lowercase();
+        if !path.exists() && !planned.contains(&key) {
+            planned.insert(key);
+            return (path, counter > 1);
+        }
+        counter += 1;
+    }
+}
+
+/// Writes a note, sealed when `vault` is given, and sets its modification time to the original one
+fn write_note(
+    path: &Path,
+    content: &str,
+    updated: Option<&DateTime<Utc>>,
+    vault: Option<&VaultKey>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match vault {
+        Some(key) => vault::write_file(path, key, content)?,
+        None => std::fs::write(path, content)?,
+    }
+    if let Some(updated) = updated {
+        let modified = SystemTime::from(*updated);
+        if let Err(e) = std::fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+        {
+            eprintln!(
+                "Failed to set modification time of {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Converts notes from `source` into Markdown files under `target`; a dry run only reports.
+/// With `vault` given the notes are written sealed.
+pub fn import_notes(
+    source: &Path,
+    format: ImportFormat,
+    target: &Path,
+    dry_run: bool,
+    vault: Option<&VaultKey>,
+) -> Result<ImportReport> {
+    let parsed = parse_source(source, format)?;
+    let mut planned = HashSet::new();
+    let mut created = Vec::new();
+    let mut skipped = parsed.skipped;
+
+    for note in parsed.notes {
+        let dir = match &note.folder {
+            Some(folder) => target.join(folder),
+            None => target.to_path_buf(),
+        };
+        let (path, renamed) = unique_path(&dir, &sanitize_file_name(&note.title), &mut planned);
+
+        if !dry_run {
+            if let Err(e) = write_note(&path, &render_note(&note), note.updated.as_ref(), vault) {
+                skipped.push(SkippedNote {
+                    source: note.title.clone(),
+                    reason: format!("Failed to write {}: {}", path.display(), e),
+                });
+                continue;
+            }
+        }
+
+        created.push(ImportedEntry {
+            title: note.title,
+            path: path.to_string_lossy().to_string(),
+            created: note.created.as_ref().map(format_date),
+            updated: note.updated.as_ref().map(format_date),
+            tags: note.tags,
+            renamed,
+        });
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        format: format.name().to_string(),
+        created,
+        skipped,
+    })
+}
+
+/// Uses the first non-empty line of a body as its title
+pub fn title_from_body(body: &str) -> String {
+    body.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.chars().take(MAX_STEM_CHARS).collect())
+        .unwrap_or_else(|| "Untitled".to_string())
+}
@@ -1,11 +1,77 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+mod backup;
+mod capture;
+mod cli;
+mod clipboard_history;
 mod commands;
+mod crypto;
+mod daily_notes;
+mod diff;
+mod export;
+mod git;
+mod history;
+mod import;
+mod links;
+mod local_api;
 mod menu;
+mod merge;
+mod search;
 mod shortcuts;
+mod single_instance;
+mod snippets;
+mod storage;
+mod sync;
+mod tasks;
+mod templates;
+mod url_scheme;
+mod vault;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let context = tauri::generate_context!();
+
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let cli = match cli::parse(std::env::args().skip(1), &cwd) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if cli.version {
+        println!("quicknote {}", context.package_info().version);
+        return;
+    }
+    if let Some(query) = &cli.search {
+        let root = commands::settings_commands::notes_root_from_config(context.config());
+        std::process::exit(cli::print_search(root.as_deref(), query));
+    }
+    let request = cli.into_request().unwrap_or_else(|e| {
+        eprintln!("Failed to read standard input: {}", e);
+        cli::LaunchRequest::default()
+    });
+
+    // A second launch hands its arguments to the running instance instead of starting up
+    let lock_name = single_instance::lock_name(&context.config().tauri.bundle.identifier);
+    let listener =
+        match single_instance::acquire(&single_instance::runtime_dir(), &lock_name, &request) {
+            Ok(single_instance::Instance::Forwarded) => return,
+            Ok(single_instance::Instance::Primary(listener)) => Some(listener),
+            Err(e) => {
+                eprintln!("Failed to check for a running instance: {}", e);
+                None
+            }
+        };
+    commands::cli_commands::set_launch_request(request);
+    // Only the primary instance gets here, so the plugin never forwards a launch itself;
+    // later launches keep going through single_instance
+    tauri_plugin_deep_link::prepare(&context.config().tauri.bundle.identifier);
+
     tauri::Builder::default()
         .menu(menu::create_app_menu())
         .on_menu_event(|event| {
@@ -38,6 +104,55 @@ pub fn run() {
             shortcuts::register_new_note_shortcut(&app.handle(), "CmdOrCtrl+Option+T");
             shortcuts::register_close_tab_shortcut(&app.handle(), "CmdOrCtrl+Option+Y");
             shortcuts::register_toggle_window_shortcut(&app.handle(), "CmdOrCtrl+Option+U");
+            shortcuts::register_daily_note_shortcut(&app.handle(), "CmdOrCtrl+Option+D");
+            shortcuts::register_capture_shortcut(&app.handle(), "CmdOrCtrl+Option+A");
+
+            // Append what `--append` read from standard input
+            commands::cli_commands::start_launch_append(&app.handle());
+
+            // Register quicknote:// with the OS and open links it delivers while running
+            let app_handle = app.handle();
+            if let Err(e) = tauri_plugin_deep_link::register(url_scheme::SCHEME, move |url| {
+                commands::cli_commands::handle_url(&app_handle, url);
+            }) {
+                eprintln!(
+                    "Failed to register the {} link scheme: {}",
+                    url_scheme::SCHEME,
+                    e
+                );
+            }
+
+            // Open files from later launches in this window
+            if let Some(listener) = listener {
+                let app_handle = app.handle();
+                single_instance::serve(listener, move |request| {
+                    commands::cli_commands::handle_forwarded_request(&app_handle, request);
+                });
+            }
+
+            // Populate the tray "New from Template" submenu
+            commands::template_commands::refresh_template_menu(&app.handle());
+
+            // Populate the tray "Insert Snippet" submenu
+            commands::snippet_commands::refresh_snippet_menu(&app.handle());
+
+            // Make scheduled backups in the background when enabled in settings
+            commands::backup_commands::start_backup_scheduler(app.handle());
+
+            // Forget the keys of encrypted notes once they sit unused
+            commands::encryption_commands::start_key_cache_sweeper(app.handle());
+
+            // Serve the local scripting API when it is enabled in settings
+            commands::api_commands::start_local_api(app.handle());
+
+            // Record copied text when clipboard history is enabled in settings
+            commands::clipboard_commands::start_clipboard_watcher(app.handle());
+
+            // Notify when tasks with a due date in the notes fall due
+            commands::reminder_commands::start_reminder_scheduler(app.handle());
+
+            // Lock the vault after it sits unused for the configured time
+            commands::vault_commands::start_vault_idle_lock(app.handle());
 
             // Load settings and update shortcuts if different
             let app_handle = app.handle();
@@ -57,6 +172,18 @@ pub fn run() {
                             &settings.close_tab_shortcut,
                         );
                     }
+                    if settings.daily_note_shortcut != "CmdOrCtrl+Option+D" {
+                        shortcuts::register_daily_note_shortcut(
+                            &app_handle,
+                            &settings.daily_note_shortcut,
+                        );
+                    }
+                    if settings.capture_shortcut != "CmdOrCtrl+Option+A" {
+                        shortcuts::register_capture_shortcut(
+                            &app_handle,
+                            &settings.capture_shortcut,
+                        );
+                    }
                 }
             });
 
@@ -68,16 +195,84 @@ pub fn run() {
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            commands::api_commands::api_reply,
+            commands::api_commands::get_local_api_info,
+            commands::backup_commands::create_backup,
+            commands::backup_commands::get_backup_status,
+            commands::backup_commands::restore_backup,
+            commands::capture_commands::append_to_note,
+            commands::cli_commands::take_launch_request,
+            commands::clipboard_commands::clear_clipboard_history,
+            commands::clipboard_commands::delete_clipboard_entry,
+            commands::clipboard_commands::list_clipboard_history,
+            commands::clipboard_commands::open_clipboard_entry,
+            commands::clipboard_commands::paste_clipboard_entry,
+            commands::clipboard_commands::pin_clipboard_entry,
+            commands::clipboard_commands::search_clipboard_history,
+            commands::clipboard_commands::set_clipboard_focus,
+            commands::daily_note_commands::open_daily_note,
+            commands::daily_note_commands::open_adjacent_daily_note,
+            commands::diff_commands::diff_text,
+            commands::diff_commands::diff_with_disk,
+            commands::encryption_commands::encrypt_note,
+            commands::encryption_commands::open_encrypted_note,
+            commands::encryption_commands::lock_note,
+            commands::export_commands::export_note,
             commands::file_commands::open_file,
             commands::file_commands::save_file,
             commands::file_commands::save_file_as,
             commands::file_commands::open_specific_file,
+            commands::git_commands::git_init,
+            commands::git_commands::git_status,
+            commands::git_commands::git_log_note,
+            commands::git_commands::git_show_note,
+            commands::git_commands::git_pull,
+            commands::git_commands::git_push,
+            commands::history_commands::list_versions,
+            commands::history_commands::read_version,
+            commands::history_commands::diff_versions,
+            commands::history_commands::restore_version,
+            commands::import_commands::import_notes,
+            commands::link_commands::get_backlinks,
+            commands::link_commands::resolve_link,
+            commands::link_commands::rename_note,
+            commands::reminder_commands::complete_reminder,
+            commands::reminder_commands::list_reminders,
+            commands::reminder_commands::snooze_reminder,
+            commands::search_commands::search_notes,
             commands::settings_commands::load_settings,
             commands::settings_commands::save_settings,
             commands::settings_commands::select_directory,
             commands::settings_commands::update_shortcut,
             commands::settings_commands::update_tray_menu,
+            commands::snippet_commands::expand_snippet,
+            commands::snippet_commands::list_snippets,
+            commands::sync_commands::get_sync_status,
+            commands::sync_commands::sync_folder,
+            commands::sync_commands::sync_webdav,
+            commands::task_commands::list_tasks,
+            commands::task_commands::toggle_task,
+            commands::template_commands::list_templates,
+            commands::template_commands::new_note_from_template,
+            commands::url_commands::open_url,
+            commands::vault_commands::change_passphrase,
+            commands::vault_commands::enable_vault,
+            commands::vault_commands::get_vault_status,
+            commands::vault_commands::lock_vault,
+            commands::vault_commands::unlock_vault,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(context)
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Quitting from the app menu or at logout ends the event loop without the tray item
+            if let tauri::RunEvent::Exit = event {
+                let app = app.clone();
+                let locked = tauri::async_runtime::block_on(async move {
+                    commands::vault_commands::lock_all(&app).await
+                });
+                if let Err(e) = locked {
+                    eprintln!("Failed to lock before quitting: {}", e);
+                }
+            }
+        });
 }
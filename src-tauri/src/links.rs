@@ -0,0 +1,537 @@
+use crate::crypto::VaultKey;
+use crate::search;
+use crate::storage::write_atomic;
+use crate::vault;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+/// File extensions treated as notes when indexing the notes root
+const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+lazy_static! {
+    // [[Note Name]], [[Note Name|alias]] and [[Note Name#heading]]
+    static ref WIKI_LINK_RE: Regex = Regex::new(r"\[\[([^\[\]|#]+)(#[^\[\]|]*)?(\|[^\[\]]*)?\]\]").unwrap();
+    // [text](relative/path.md "optional title")
+    static ref MD_LINK_RE: Regex = Regex::new(r#"\[[^\[\]]*\]\(<?([^)\s<>]+)>?(?:\s+"[^"]*")?\)"#).unwrap();
+
+    // Global link graph for the current notes root
+    static ref LINK_GRAPH: Mutex<LinkGraph> = Mutex::new(LinkGraph::default());
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Wiki,
+    Markdown,
+}
+
+/// A link as written in a note, with the byte range of its target
+#[derive(Debug, Clone)]
+pub struct RawLink {
+    pub kind: LinkKind,
+    pub target: String,
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
+
+/// A link whose target was resolved against the notes root
+#[derive(Debug, Clone)]
+struct ResolvedLink {
+    raw: RawLink,
+    resolved: Option<PathBuf>,
+}
+
+/// A note that links to the requested note
+#[derive(Debug, Clone, Serialize)]
+pub struct Backlink {
+    pub source: String,
+    pub line: usize,
+    pub kind: LinkKind,
+    pub context: String,
+}
+
+#[derive(Debug, Default)]
+struct LinkGraph {
+    root: Option<PathBuf>,
+    /// Whether vault notes could be read when the root was indexed
+    with_vault: bool,
+    /// Every note under the root, kept in step with saves and renames
+    notes: Vec<PathBuf>,
+    outgoing: HashMap<PathBuf, Vec<ResolvedLink>>,
+}
+
+/// Extracts wiki-style and relative Markdown links from note content
+pub fn extract_links(content: &str) -> Vec<RawLink> {
+    let mut links = Vec::new();
+    let mut offset = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        for caps in WIKI_LINK_RE.captures_iter(line) {
+            let target = caps.get(1).unwrap();
+            links.push(RawLink {
+                kind: LinkKind::Wiki,
+                target: target.as_str().trim().to_string(),
+                start: offset + target.start(),
+                end: offset + target.end(),
+                line: index + 1,
+            });
+        }
+        for caps in MD_LINK_RE.captures_iter(line) {
+            let target = caps.get(1).unwrap();
+            if !is_relative_target(target.as_str()) {
+                continue;
+            }
+            links.push(RawLink {
+                kind: LinkKind::Markdown,
+                target: target.as_str().to_string(),
+                start: offset + target.start(),
+                end: offset + target.end(),
+                line: index + 1,
+            });
+        }
+        offset += line.len();
+    }
+
+    links.sort_by_key(|link| link.start);
+    links
+}
+
+/// Returns true for link targets that point at local files rather than URLs or anchors
+fn is_relative_target(target: &str) -> bool {
+    !(target.is_empty()
+        || target.starts_with('#')
+        || target.contains("://")
+        || target.starts_with("mailto:"))
+}
+
+/// Returns true if the path has one of the note extensions
+pub fn is_note_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| NOTE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Lists every note file under the notes root
+pub fn collect_notes(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_note_file(entry.path()))
+        .map(|entry| normalize_path(entry.path()))
+        .collect()
+}
+
+/// Resolves `.` and `..` components without touching the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Computes the path of `target` relative to the directory `base`
+pub fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = base
+        .iter()
+        .zip(target.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component.as_os_str());
+    }
+    relative
+}
+
+/// Decodes %XX escapes in Markdown link targets
+fn percent_decode(target: &str) -> String {
+    let bytes = target.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = |b: u8| (b as char).to_digit(16);
+            if let (Some(high), Some(low)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Resolves a link target written in `from` against the notes root
+pub fn resolve_target(root: &Path, from: &Path, kind: LinkKind, target: &str) -> Option<PathBuf> {
+    let notes = match kind {
+        LinkKind::Wiki => collect_notes(root),
+        LinkKind::Markdown => Vec::new(),
+    };
+    resolve_with_notes(root, &notes, from, kind, target)
+}
+
+fn resolve_with_notes(
+    root: &Path,
+    notes: &[PathBuf],
+    from: &Path,
+    kind: LinkKind,
+    target: &str,
+) -> Option<PathBuf> {
+    match kind {
+        LinkKind::Wiki => resolve_wiki_target(root, notes, from, target),
+        LinkKind::Markdown => {
+            // Drop any #anchor or ?query suffix before resolving
+            let path_part = target.split(['#', '?']).next().unwrap_or(target);
+            let decoded = percent_decode(path_part);
+            let candidate = if let Some(absolute) = decoded.strip_prefix('/') {
+                root.join(absolute)
+            } else {
+                from.parent().unwrap_or(root).join(decoded)
+            };
+            let candidate = normalize_path(&candidate);
+            candidate.is_file().then_some(candidate)
+        }
+    }
+}
+
+/// Finds the note whose name matches a `[[Note Name]]` target
+fn resolve_wiki_target(
+    root: &Path,
+    notes: &[PathBuf],
+    from: &Path,
+    target: &str,
+) -> Option<PathBuf> {
+    let target = target.trim();
+
+    // Allow explicit paths such as [[projects/Plan]] relative to the root
+    if target.contains('/') {
+        let base = normalize_path(&root.join(target));
+        if base.is_file() {
+            return Some(base);
+        }
+        return NOTE_EXTENSIONS
+            .iter()
+            .map(|ext| base.with_extension(ext))
+            .find(|candidate| candidate.is_file());
+    }
+
+    let wanted = target.to_lowercase();
+    let mut matches: Vec<&PathBuf> = notes
+        .iter()
+        .filter(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().to_lowercase() == wanted)
+                .unwrap_or(false)
+        })
+        .collect();
+
+    // Prefer a note next to the linking file, then the shallowest one
+    let from_dir = from.parent().map(normalize_path);
+    matches.sort_by_key(|path| {
+        let same_dir = path.parent().map(normalize_path) == from_dir;
+        (!same_dir, path.components().count())
+    });
+    matches.into_iter().next().cloned()
+}
+
+fn resolve_all(root: &Path, notes: &[PathBuf], from: &Path, content: &str) -> Vec<ResolvedLink> {
+    extract_links(content)
+        .into_iter()
+        .map(|raw| {
+            let resolved = resolve_with_notes(root, notes, from, raw.kind, &raw.target);
+            ResolvedLink { raw, resolved }
+        })
+        .collect()
+}
+
+impl LinkGraph {
+    /// Rebuilds the graph if the notes root changed since the last index, or if the vault
+    /// has been unlocked since and its notes were skipped
+    fn ensure_indexed(&mut self, root: &Path, vault: Option<&VaultKey>) {
+        let root = normalize_path(root);
+        if self.root.as_deref() == Some(root.as_path()) && (self.with_vault || vault.is_none()) {
+            return;
+        }
+        self.outgoing.clear();
+        let notes = collect_notes(&root);
+        for note in &notes {
+            if let Some(content) = search::read_indexable(note, vault) {
+                let links = resolve_all(&root, &notes, note, &content);
+                self.outgoing.insert(note.clone(), links);
+            }
+        }
+        self.root = Some(root);
+        self.with_vault = vault.is_some();
+        self.notes = notes;
+    }
+
+    /// Adds a new note to the note list; links that did not resolve before may point at it
+    fn add_note(&mut self, root: &Path, path: &Path) {
+        if self.notes.iter().any(|note| note == path) {
+            return;
+        }
+        self.notes.push(path.to_path_buf());
+        for (source, links) in self.outgoing.iter_mut() {
+            for link in links.iter_mut().filter(|link| link.resolved.is_none()) {
+                link.resolved =
+                    resolve_with_notes(root, &self.notes, source, link.raw.kind, &link.raw.target);
+            }
+        }
+    }
+
+    /// Re-parses the outgoing links of one note
+    fn update(&mut self, root: &Path, path: &Path, content: &str) {
+        let path = normalize_path(path);
+        self.add_note(root, &path);
+        let links = resolve_all(root, &self.notes, &path, content);
+        self.outgoing.insert(path, links);
+    }
+
+    fn backlinks(&self, target: &Path) -> Vec<(PathBuf, RawLink)> {
+        let mut result = Vec::new();
+        for (source, links) in &self.outgoing {
+            for link in links {
+                if link.resolved.as_deref() == Some(target) {
+                    result.push((source.clone(), link.raw.clone()));
+                }
+            }
+        }
+        result.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.start.cmp(&b.1.start)));
+        result
+    }
+}
+
+/// Re-parses the links of a note after it was saved
+pub fn update_note_links(root: &Path, path: &Path, content: &str) {
+    let path = normalize_path(path);
+    if !path.starts_with(normalize_path(root)) || !is_note_file(&path) {
+        return;
+    }
+    let mut graph = LINK_GRAPH.lock().unwrap();
+    graph.ensure_indexed(root, None);
+    graph.update(root, &path, content);
+}
+
+/// Returns every note linking to `target`; vault notes are only searched when `vault` is given
+pub fn get_backlinks(root: &Path, target: &Path, vault: Option<&VaultKey>) -> Vec<Backlink> {
+    let target = normalize_path(target);
+    let mut graph = LINK_GRAPH.lock().unwrap();
+    graph.ensure_indexed(root, vault);
+
+    graph
+        .backlinks(&target)
+        .into_iter()
+        .map(|(source, link)| {
+            let context = search::read_indexable(&source, vault)
+                .and_then(|content| content.lines().nth(link.line - 1).map(str::to_string))
+                .unwrap_or_default();
+            Backlink {
+                source: source.to_string_lossy().to_string(),
+                line: link.line,
+                kind: link.kind,
+                context: context.trim().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Formats the replacement text for a link that now points at `new_path`
+fn rewrite_target(root: &Path, source: &Path, link: &RawLink, new_path: &Path) -> String {
+    match link.kind {
+        LinkKind::Wiki => {
+            if link.target.contains('/') {
+                let relative = relative_path(root, new_path);
+                relative
+                    .with_extension("")
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            } else {
+                new_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_else(|| link.target.clone())
+            }
+        }
+        LinkKind::Markdown => {
+            // Keep any #anchor suffix the original link carried
+            let suffix = link
+                .target
+                .find('#')
+                .map(|index| link.target[index..].to_string())
+                .unwrap_or_default();
+            let relative = if link.target.starts_with('/') {
+                format!("/{}", relative_path(root, new_path).to_string_lossy())
+            } else {
+                let base = source.parent().unwrap_or(root);
+                relative_path(base, new_path).to_string_lossy().to_string()
+            };
+            format!(
+                "{}{}",
+                relative.replace('\\', "/").replace(' ', "%20"),
+                suffix
+            )
+        }
+    }
+}
+
+/// Replaces the given link targets in `content`
+fn apply_rewrites(content: &str, rewrites: &[(usize, usize, String)]) -> String {
+    let mut updated = content.to_string();
+    // Apply back to front so earlier byte offsets stay valid
+    for (start, end, replacement) in rewrites.iter().rev() {
+        updated.replace_range(*start..*end, replacement);
+    }
+    updated
+}
+
+/// Writes a note whose links were rewritten, sealing it when it lives in the vault
+fn write_rewritten(path: &Path, content: &str, vault: Option<&VaultKey>) -> anyhow::Result<()> {
+    match vault {
+        Some(key) => vault::write_file(path, key, content),
+        None => write_atomic(path, content.as_bytes()),
+    }
+}
+
+/// Moves a note and rewrites every link that pointed at it, returning the updated files.
+/// Vault notes are read and written sealed when `vault` is given.
+///
+/// Links are re-read from each file rather than taken from the index, which may be older
+/// than the file. Nothing is changed unless every affected note can be read, and the move is
+/// undone if a rewritten note cannot be written.
+pub fn rename_note(
+    root: &Path,
+    old_path: &Path,
+    new_path: &Path,
+    vault: Option<&VaultKey>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let root = normalize_path(root);
+    let old_path = normalize_path(old_path);
+    let new_path = normalize_path(new_path);
+
+    if !old_path.is_file() {
+        anyhow::bail!("File not found: {}", old_path.display());
+    }
+    if new_path.exists() {
+        anyhow::bail!("A file already exists at {}", new_path.display());
+    }
+
+    let mut graph = LINK_GRAPH.lock().unwrap();
+    graph.ensure_indexed(&root, vault);
+
+    // Notes that linked to the old path when indexed, plus the moved note itself when its
+    // relative links have to follow it to another folder
+    let mut sources: Vec<PathBuf> = graph
+        .backlinks(&old_path)
+        .into_iter()
+        .map(|(source, _)| source)
+        .collect();
+    if old_path.parent() != new_path.parent() {
+        sources.push(old_path.clone());
+    }
+    sources.sort();
+    sources.dedup();
+
+    // Work out every rewrite before touching the disk
+    let notes = graph.notes.clone();
+    let mut rewritten: Vec<(PathBuf, String, String)> = Vec::new();
+    for source in sources {
+        let content = search::read_indexable(&source, vault)
+            .ok_or_else(|| anyhow::anyhow!("Could not read {}", source.display()))?;
+        let moved = source == old_path;
+        let destination = if moved {
+            new_path.clone()
+        } else {
+            source.clone()
+        };
+
+        let rewrites: Vec<(usize, usize, String)> = extract_links(&content)
+            .into_iter()
+            .filter_map(|link| {
+                let resolved = resolve_with_notes(&root, &notes, &source, link.kind, &link.target)?;
+                let target = if resolved == old_path {
+                    new_path.clone()
+                } else if moved && link.kind == LinkKind::Markdown && !link.target.starts_with('/')
+                {
+                    resolved
+                } else {
+                    return None;
+                };
+                let replacement = rewrite_target(&root, &destination, &link, &target);
+                Some((link.start, link.end, replacement))
+            })
+            .collect();
+
+        let updated = apply_rewrites(&content, &rewrites);
+        rewritten.push((destination, content, updated));
+    }
+
+    if let Some(parent) = new_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::rename(&old_path, &new_path)?;
+
+    let mut updated_files: Vec<PathBuf> = Vec::new();
+    for (path, original, updated) in &rewritten {
+        if updated == original {
+            continue;
+        }
+        if let Err(e) = write_rewritten(path, updated, vault) {
+            // Put back what was already rewritten, then the note itself
+            for written in &updated_files {
+                if let Some((_, original, _)) = rewritten.iter().find(|(p, _, _)| p == written) {
+                    let _ = write_rewritten(written, original, vault);
+                }
+            }
+            let _ = std::fs::rename(&new_path, &old_path);
+            return Err(e);
+        }
+        updated_files.push(path.clone());
+    }
+
+    graph.notes.retain(|note| note != &old_path);
+    graph.add_note(&root, &new_path);
+    for (path, _, updated) in &rewritten {
+        graph.update(&root, path, updated);
+    }
+
+    // Drop the stale entry and make sure the moved note is indexed
+    if let Some(links) = graph.outgoing.remove(&old_path) {
+        if !graph.outgoing.contains_key(&new_path) {
+            graph.outgoing.insert(new_path.clone(), links);
+        }
+    }
+
+    // Links that previously resolved to the old path now point at the new one
+    for links in graph.outgoing.values_mut() {
+        for link in links.iter_mut() {
+            if link.resolved.as_deref() == Some(old_path.as_path()) {
+                link.resolved = Some(new_path.clone());
+            }
+        }
+    }
+
+    Ok(updated_files)
+}
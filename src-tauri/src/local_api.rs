@@ -0,0 +1,208 @@
+use crate::capture::AppendRequest;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Largest request body accepted, enough for pasted build logs
+const MAX_BODY_BYTES: u64 = 4 * 1024 * 1024;
+
+/// An operation requested through the local API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiRequest {
+    /// `POST /notes` with `{"title": ..., "content": ...}`
+    CreateNote {
+        title: Option<String>,
+        content: String,
+    },
+    /// `POST /notes/append` with `{"text": ...}` and optionally `path` or `tab_id`,
+    /// `timestamp` and `separator`
+    AppendToNote(AppendRequest),
+    /// `GET /tabs`
+    ListTabs,
+    /// `GET /tabs/<id>`
+    GetTab { id: String },
+    /// `GET /search?q=...&limit=...`
+    Search { query: String, limit: Option<usize> },
+}
+
+/// A failed request, reported with an HTTP status
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+type Handler = dyn Fn(ApiRequest) -> Result<Value, ApiError> + Send + Sync;
+
+#[derive(Deserialize)]
+struct CreateBody {
+    title: Option<String>,
+    #[serde(default)]
+    content: String,
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, ApiError> {
+    serde_json::from_str(body).map_err(|e| ApiError::new(400, format!("Invalid JSON body: {}", e)))
+}
+
+/// Maps a method, URL and body onto an API request
+pub fn route(method: &Method, url: &str, body: &str) -> Result<ApiRequest, ApiError> {
+    let parsed = url::Url::parse(&format!("http://localhost{}", url))
+        .map_err(|_| ApiError::new(400, "Invalid URL"))?;
+    let path = parsed.path().trim_end_matches('/');
+
+    match (method, path) {
+        (Method::Post, "/notes") => {
+            let body: CreateBody = parse_body(body)?;
+            Ok(ApiRequest::CreateNote {
+                title: body.title,
+                content: body.content,
+            })
+        }
+        (Method::Post, "/notes/append") => Ok(ApiRequest::AppendToNote(parse_body(body)?)),
+        (Method::Get, "/tabs") => Ok(ApiRequest::ListTabs),
+        (Method::Get, "/search") => {
+            let mut query = None;
+            let mut limit = None;
+            for (key, value) in parsed.query_pairs() {
+                match key.as_ref() {
+                    "q" => query = Some(value.into_owned()),
+                    "limit" => {
+                        limit = Some(
+                            value
+                                .parse::<usize>()
+                                .map_err(|_| ApiError::new(400, "limit must be a number"))?,
+                        )
+                    }
+                    _ => {}
+                }
+            }
+            match query.filter(|query| !query.trim().is_empty()) {
+                Some(query) => Ok(ApiRequest::Search { query, limit }),
+                None => Err(ApiError::new(400, "Missing q")),
+            }
+        }
+        (Method::Get, _) if path.starts_with("/tabs/") => Ok(ApiRequest::GetTab {
+            id: path["/tabs/".len()..].to_string(),
+        }),
+        (_, "/notes" | "/notes/append" | "/tabs" | "/search") => {
+            Err(ApiError::new(405, "Method not allowed"))
+        }
+        _ => Err(ApiError::new(404, "Not found")),
+    }
+}
+
+/// Compares in constant time so the token cannot be guessed byte by byte
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn is_authorized(request: &Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(|given| token_matches(given.trim(), token))
+        .unwrap_or(false)
+}
+
+fn respond(request: Request, status: u16, body: Value) {
+    let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        eprintln!("Failed to answer local API request: {}", e);
+    }
+}
+
+fn handle(mut request: Request, token: &str, handler: &Handler) {
+    if !is_authorized(&request, token) {
+        return respond(request, 401, json!({ "error": "Missing or wrong token" }));
+    }
+
+    let mut body = String::new();
+    if let Err(e) = request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_string(&mut body)
+    {
+        return respond(request, 400, json!({ "error": e.to_string() }));
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return respond(
+            request,
+            413,
+            json!({ "error": "Request body is too large" }),
+        );
+    }
+
+    match route(request.method(), request.url(), &body).and_then(handler) {
+        Ok(value) => respond(request, 200, value),
+        Err(e) => respond(request, e.status, json!({ "error": e.message })),
+    }
+}
+
+/// HTTP server on the loopback interface that serves the local API
+pub struct ApiServer {
+    server: Arc<Server>,
+    port: u16,
+}
+
+impl ApiServer {
+    /// Listens on 127.0.0.1:`port` (0 picks a free port) and answers requests on a background thread
+    pub fn start(
+        port: u16,
+        token: String,
+        handler: impl Fn(ApiRequest) -> Result<Value, ApiError> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let server = Server::http((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| anyhow!("Failed to start the local API on port {}: {}", port, e))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .ok_or_else(|| anyhow!("Local API is not listening on a TCP port"))?;
+        let server = Arc::new(server);
+
+        let handler: Arc<Handler> = Arc::new(handler);
+        let listener = server.clone();
+        std::thread::spawn(move || {
+            // Ends once `stop` unblocks the server
+            for request in listener.incoming_requests() {
+                let token = token.clone();
+                let handler = handler.clone();
+                // Requests that wait on the window must not hold up the others
+                std::thread::spawn(move || handle(request, &token, handler.as_ref()));
+            }
+        });
+        Ok(Self { server, port })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn stop(&self) {
+        self.server.unblock();
+    }
+}
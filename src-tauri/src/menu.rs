@@ -1,3 +1,4 @@
+use crate::commands::{capture_commands, snippet_commands, template_commands, vault_commands};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use tauri::{
@@ -8,6 +9,12 @@ use tauri::{
 // Global state to store recent files for each app instance
 lazy_static::lazy_static! {
     static ref RECENT_FILES: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+    // Template names shown in the tray "New from Template" submenu
+    static ref TEMPLATE_NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    // Snippet names shown in the tray "Insert Snippet" submenu
+    static ref SNIPPET_NAMES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    // Scheduled backup status line, hidden when automatic backups are off
+    static ref BACKUP_STATUS: Mutex<Option<String>> = Mutex::new(None);
 }
 
 /// Creates the main application menu
@@ -22,6 +29,12 @@ pub fn create_app_menu() -> Menu {
             .add_item(
                 CustomMenuItem::new("save_file_as", "Save As...").accelerator("CmdOrCtrl+Shift+S"),
             )
+            .add_item(CustomMenuItem::new("export_note", "Export...").accelerator("CmdOrCtrl+E"))
+            .add_item(
+                CustomMenuItem::new("export_pdf", "Export as PDF...")
+                    .accelerator("CmdOrCtrl+Shift+E"),
+            )
+            .add_item(CustomMenuItem::new("encrypt_note", "Encrypt Note..."))
             .add_item(CustomMenuItem::new("close_tab", "Close Tab").accelerator("CmdOrCtrl+W"))
             .add_native_item(MenuItem::Separator)
             .add_native_item(MenuItem::Quit),
@@ -56,7 +69,8 @@ pub fn create_app_menu() -> Menu {
 /// Handles main menu events by emitting them to the frontend
 pub fn handle_menu_event(window: &Window, menu_id: &str) {
     match menu_id {
-        "new_note" | "open_file" | "save_file" | "save_file_as" | "toggle_theme" | "close_tab" => {
+        "new_note" | "open_file" | "save_file" | "save_file_as" | "export_note" | "export_pdf"
+        | "encrypt_note" | "toggle_theme" | "close_tab" => {
             // Emit the menu event to the frontend
             if let Err(e) = window.emit("menu-event", menu_id) {
                 eprintln!("Failed to emit menu event: {}", e);
@@ -70,53 +84,54 @@ pub fn handle_menu_event(window: &Window, menu_id: &str) {
 
 /// Creates the system tray menu
 pub fn create_tray_menu() -> SystemTray {
+    SystemTray::new().with_menu(build_tray_menu(&[], &[], &[], None))
+}
+
+/// Builds the tray menu from the current recent files, templates, snippets and backup status
+fn build_tray_menu(
+    recent_files: &[String],
+    templates: &[String],
+    snippets: &[String],
+    backup_status: Option<&str>,
+) -> SystemTrayMenu {
     let new_note = CustomMenuItem::new("tray_new_note".to_string(), "New Note");
+    let append_clipboard = CustomMenuItem::new(
+        "tray_append_clipboard".to_string(),
+        "Append Clipboard to Capture Note",
+    );
     let close_tab = CustomMenuItem::new("tray_close_tab".to_string(), "Close Current Tab");
     let open_file = CustomMenuItem::new("tray_open_file".to_string(), "Open...");
     let save_file = CustomMenuItem::new("tray_save".to_string(), "Save");
     let save_file_as = CustomMenuItem::new("tray_save_as".to_string(), "Save As...");
+    let lock = CustomMenuItem::new("tray_lock".to_string(), "Lock");
     let show_hide = CustomMenuItem::new("tray_show_hide".to_string(), "Show/Hide Window");
     let quit = CustomMenuItem::new("tray_quit".to_string(), "Quit");
 
-    // Create recent files submenu (placeholder for now)
-    let recent_files_submenu = SystemTrayMenu::new()
-        .add_item(CustomMenuItem::new("tray_no_recent".to_string(), "No Recent Files").disabled());
-
-    let tray_menu = SystemTrayMenu::new()
-        .add_item(new_note)
-        .add_item(close_tab)
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(open_file)
-        .add_item(save_file)
-        .add_item(save_file_as)
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_submenu(SystemTraySubmenu::new(
-            "Recently Closed",
-            recent_files_submenu,
-        ))
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(show_hide)
-        .add_item(quit);
-
-    SystemTray::new().with_menu(tray_menu)
-}
-
-/// Updates the system tray menu with recent files
-pub fn update_tray_menu_with_recent_files(app: &AppHandle, recent_files: Vec<String>) {
-    // Store recent files in global state
-    let app_id = app.config().tauri.bundle.identifier.clone();
-    {
-        let mut files = RECENT_FILES.lock().unwrap();
-        files.insert(app_id, recent_files.clone());
+    // Create templates submenu
+    let mut templates_submenu = SystemTrayMenu::new();
+    if templates.is_empty() {
+        templates_submenu = templates_submenu.add_item(
+            CustomMenuItem::new("tray_no_templates".to_string(), "No Templates").disabled(),
+        );
+    } else {
+        for (index, name) in templates.iter().enumerate() {
+            let menu_id = format!("tray_template_{}", index);
+            templates_submenu = templates_submenu.add_item(CustomMenuItem::new(menu_id, name));
+        }
     }
 
-    let new_note = CustomMenuItem::new("tray_new_note".to_string(), "New Note");
-    let close_tab = CustomMenuItem::new("tray_close_tab".to_string(), "Close Current Tab");
-    let open_file = CustomMenuItem::new("tray_open_file".to_string(), "Open...");
-    let save_file = CustomMenuItem::new("tray_save".to_string(), "Save");
-    let save_file_as = CustomMenuItem::new("tray_save_as".to_string(), "Save As...");
-    let show_hide = CustomMenuItem::new("tray_show_hide".to_string(), "Show/Hide Window");
-    let quit = CustomMenuItem::new("tray_quit".to_string(), "Quit");
+    // Create snippets submenu
+    let mut snippets_submenu = SystemTrayMenu::new();
+    if snippets.is_empty() {
+        snippets_submenu = snippets_submenu.add_item(
+            CustomMenuItem::new("tray_no_snippets".to_string(), "No Snippets").disabled(),
+        );
+    } else {
+        for (index, name) in snippets.iter().enumerate() {
+            let menu_id = format!("tray_snippet_{}", index);
+            snippets_submenu = snippets_submenu.add_item(CustomMenuItem::new(menu_id, name));
+        }
+    }
 
     // Create recent files submenu
     let mut recent_files_submenu = SystemTrayMenu::new();
@@ -137,8 +152,14 @@ pub fn update_tray_menu_with_recent_files(app: &AppHandle, recent_files: Vec<Str
         }
     }
 
-    let tray_menu = SystemTrayMenu::new()
+    let mut menu = SystemTrayMenu::new()
         .add_item(new_note)
+        .add_submenu(SystemTraySubmenu::new(
+            "New from Template",
+            templates_submenu,
+        ))
+        .add_submenu(SystemTraySubmenu::new("Insert Snippet", snippets_submenu))
+        .add_item(append_clipboard)
         .add_item(close_tab)
         .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(open_file)
@@ -149,13 +170,71 @@ pub fn update_tray_menu_with_recent_files(app: &AppHandle, recent_files: Vec<Str
             "Recently Closed",
             recent_files_submenu,
         ))
-        .add_native_item(SystemTrayMenuItem::Separator)
-        .add_item(show_hide)
-        .add_item(quit);
+        .add_native_item(SystemTrayMenuItem::Separator);
+    if let Some(status) = backup_status {
+        menu = menu
+            .add_item(CustomMenuItem::new("tray_backup_status".to_string(), status).disabled())
+            .add_native_item(SystemTrayMenuItem::Separator);
+    }
+    menu.add_item(lock).add_item(show_hide).add_item(quit)
+}
+
+/// Rebuilds the tray menu from the stored recent files, templates, snippets and backup status
+fn refresh_tray_menu(app: &AppHandle) {
+    let app_id = app.config().tauri.bundle.identifier.clone();
+    let recent_files = RECENT_FILES
+        .lock()
+        .unwrap()
+        .get(&app_id)
+        .cloned()
+        .unwrap_or_default();
+    let templates = TEMPLATE_NAMES.lock().unwrap().clone();
+    let snippets = SNIPPET_NAMES.lock().unwrap().clone();
+    let backup_status = BACKUP_STATUS.lock().unwrap().clone();
 
     // Update the system tray menu
     let system_tray = app.tray_handle();
-    let _ = system_tray.set_menu(tray_menu);
+    let _ = system_tray.set_menu(build_tray_menu(
+        &recent_files,
+        &templates,
+        &snippets,
+        backup_status.as_deref(),
+    ));
+}
+
+/// Updates the system tray menu with recent files
+pub fn update_tray_menu_with_recent_files(app: &AppHandle, recent_files: Vec<String>) {
+    // Store recent files in global state
+    let app_id = app.config().tauri.bundle.identifier.clone();
+    {
+        let mut files = RECENT_FILES.lock().unwrap();
+        files.insert(app_id, recent_files);
+    }
+    refresh_tray_menu(app);
+}
+
+/// Updates the system tray menu with the available templates
+pub fn update_tray_menu_with_templates(app: &AppHandle, templates: Vec<String>) {
+    *TEMPLATE_NAMES.lock().unwrap() = templates;
+    refresh_tray_menu(app);
+}
+
+/// Updates the system tray menu with the available snippets
+pub fn update_tray_menu_with_snippets(app: &AppHandle, snippets: Vec<String>) {
+    *SNIPPET_NAMES.lock().unwrap() = snippets;
+    refresh_tray_menu(app);
+}
+
+/// Updates the scheduled backup status line, rebuilding the menu only when it changed
+pub fn update_tray_menu_with_backup_status(app: &AppHandle, status: Option<String>) {
+    {
+        let mut current = BACKUP_STATUS.lock().unwrap();
+        if *current == status {
+            return;
+        }
+        *current = status;
+    }
+    refresh_tray_menu(app);
 }
 
 /// Handles system tray events
@@ -165,16 +244,58 @@ pub fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
             SystemTrayEvent::MenuItemClick { id, .. } => {
                 match id.as_str() {
                     "tray_show_hide" => toggle_window_visibility(&window),
+                    "tray_append_clipboard" => {
+                        let app_handle = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            capture_commands::append_clipboard(&app_handle).await;
+                        });
+                    }
+                    "tray_lock" => {
+                        let app_handle = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) = vault_commands::lock_all(&app_handle).await {
+                                eprintln!("Failed to lock: {}", e);
+                            }
+                        });
+                    }
                     "tray_new_note" => window.emit("menu-event", "new_note").unwrap(),
                     "tray_open_file" => window.emit("menu-event", "open_file").unwrap(),
                     "tray_save" => window.emit("menu-event", "save_file").unwrap(),
                     "tray_save_as" => window.emit("menu-event", "save_file_as").unwrap(),
                     "tray_close_tab" => window.emit("menu-event", "close_active_tab").unwrap(),
-                    "tray_no_recent" => {
+                    "tray_no_recent" | "tray_no_templates" | "tray_no_snippets"
+                    | "tray_backup_status" => {
                         // No action for disabled placeholder item
                     }
-                    "tray_quit" => app.exit(0),
+                    "tray_quit" => {
+                        let app_handle = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            vault_commands::lock_and_exit(&app_handle).await;
+                        });
+                    }
                     _ => {
+                        // Handle template clicks
+                        if let Some(index_str) = id.strip_prefix("tray_template_") {
+                            let name = index_str.parse::<usize>().ok().and_then(|index| {
+                                TEMPLATE_NAMES.lock().unwrap().get(index).cloned()
+                            });
+                            if let Some(name) = name {
+                                let app_handle = app.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    template_commands::emit_note_from_template(&app_handle, &name)
+                                        .await;
+                                });
+                            }
+                        }
+                        // Handle snippet clicks
+                        if let Some(index_str) = id.strip_prefix("tray_snippet_") {
+                            let name = index_str.parse::<usize>().ok().and_then(|index| {
+                                SNIPPET_NAMES.lock().unwrap().get(index).cloned()
+                            });
+                            if let Some(name) = name {
+                                snippet_commands::emit_insert_snippet(app, &name);
+                            }
+                        }
                         // Handle recent file clicks
                         if id.starts_with("tray_recent_") {
                             if let Some(index_str) = id.strip_prefix("tray_recent_") {
@@ -210,5 +331,6 @@ fn toggle_window_visibility(window: &Window) {
     } else {
         window.show().unwrap();
         window.set_focus().unwrap();
+        vault_commands::request_unlock_if_locked(&window.app_handle());
     }
 }
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+use std::ops::Range;
+
+const OURS_MARKER: &str = "<<<<<<< editor";
+const BASE_MARKER: &str = "||||||| base";
+const SEPARATOR_MARKER: &str = "=======";
+const THEIRS_MARKER: &str = ">>>>>>> disk";
+
+/// A region both sides changed differently; line numbers are 1-based in the merged text
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictRegion {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// Outcome of a three-way merge
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    /// Merged text, with conflict markers around any unresolved regions
+    pub merged: String,
+    pub conflicts: Vec<ConflictRegion>,
+}
+
+impl MergeResult {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// A changed range of the base and the lines replacing it on one side
+#[derive(Debug, Clone)]
+struct Hunk {
+    base: Range<usize>,
+    side: Range<usize>,
+}
+
+impl Hunk {
+    fn delta(&self) -> isize {
+        self.side.len() as isize - self.base.len() as isize
+    }
+}
+
+/// Splits text into lines, keeping line terminators so joins are lossless
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn changed_hunks(base: &[&str], side: &[&str]) -> Vec<Hunk> {
+    capture_diff_slices(Algorithm::Myers, base, side)
+        .into_iter()
+        .filter(|op| !matches!(op, DiffOp::Equal { .. }))
+        .map(|op| Hunk {
+            base: op.old_range(),
+            side: op.new_range(),
+        })
+        .collect()
+}
+
+/// Maps a range of the base onto one side, given that side's hunks before and inside it
+fn map_range(range: &Range<usize>, before: &[Hunk], inside: &[Hunk]) -> Range<usize> {
+    let shift_before: isize = before.iter().map(Hunk::delta).sum();
+    let shift_inside: isize = inside.iter().map(Hunk::delta).sum();
+    let start = (range.start as isize + shift_before) as usize;
+    let end = (range.end as isize + shift_before + shift_inside) as usize;
+    start..end
+}
+
+/// Ensures a block ends with a newline so conflict markers start on their own line
+fn push_block(output: &mut String, lines: &[&str]) {
+    for line in lines {
+        output.push_str(line);
+    }
+    if !output.is_empty() && !output.ends_with('\n') {
+        output.push('\n');
+    }
+}
+
+/// Three-way merges `ours` (the editor buffer) and `theirs` (the file on disk) against `base`
+pub fn merge3(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let base_lines = split_lines(base);
+    let our_lines = split_lines(ours);
+    let their_lines = split_lines(theirs);
+
+    let our_hunks = changed_hunks(&base_lines, &our_lines);
+    let their_hunks = changed_hunks(&base_lines, &their_lines);
+
+    let mut merged = String::new();
+    let mut conflicts = Vec::new();
+    let mut base_pos = 0;
+    let (mut oi, mut ti) = (0, 0);
+
+    while oi < our_hunks.len() || ti < their_hunks.len() {
+        // Start a group at the earliest pending hunk from either side
+        let start = match (our_hunks.get(oi), their_hunks.get(ti)) {
+            (Some(o), Some(t)) => o.base.start.min(t.base.start),
+            (Some(o), None) => o.base.start,
+            (None, Some(t)) => t.base.start,
+            (None, None) => break,
+        };
+        let mut end = start;
+        let (group_oi, group_ti) = (oi, ti);
+
+        // Absorb every hunk that overlaps or touches the group on either side
+        loop {
+            if let Some(hunk) = our_hunks.get(oi).filter(|h| h.base.start <= end) {
+                end = end.max(hunk.base.end);
+                oi += 1;
+            } else if let Some(hunk) = their_hunks.get(ti).filter(|h| h.base.start <= end) {
+                end = end.max(hunk.base.end);
+                ti += 1;
+            } else {
+                break;
+            }
+        }
+
+        // Copy the unchanged base lines before this group
+        for line in &base_lines[base_pos..start] {
+            merged.push_str(line);
+        }
+        base_pos = end;
+
+        let group = start..end;
+        let ours_range = map_range(&group, &our_hunks[..group_oi], &our_hunks[group_oi..oi]);
+        let theirs_range = map_range(&group, &their_hunks[..group_ti], &their_hunks[group_ti..ti]);
+        let our_block = &our_lines[ours_range];
+        let their_block = &their_lines[theirs_range];
+
+        if group_ti == ti || our_block == their_block {
+            // Only we changed this region, or both sides made the same change
+            for line in our_block {
+                merged.push_str(line);
+            }
+        } else if group_oi == oi {
+            for line in their_block {
+                merged.push_str(line);
+            }
+        } else {
+            if !merged.is_empty() && !merged.ends_with('\n') {
+                merged.push('\n');
+            }
+            let start_line = merged.lines().count() + 1;
+            merged.push_str(OURS_MARKER);
+            merged.push('\n');
+            push_block(&mut merged, our_block);
+            merged.push_str(BASE_MARKER);
+            merged.push('\n');
+            push_block(&mut merged, &base_lines[group.clone()]);
+            merged.push_str(SEPARATOR_MARKER);
+            merged.push('\n');
+            push_block(&mut merged, their_block);
+            merged.push_str(THEIRS_MARKER);
+            merged.push('\n');
+            let end_line = merged.lines().count();
+
+            conflicts.push(ConflictRegion {
+                start_line,
+                end_line,
+                base: base_lines[group].concat(),
+                ours: our_block.concat(),
+                theirs: their_block.concat(),
+            });
+        }
+    }
+
+    for line in &base_lines[base_pos..] {
+        merged.push_str(line);
+    }
+
+    MergeResult { merged, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_edits_to_different_lines() {
+        let base = "one\ntwo\nthree\nfour\n";
+        let result = merge3(base, "ONE\ntwo\nthree\nfour\n", "one\ntwo\nthree\nFOUR\n");
+        assert!(result.is_clean());
+        assert_eq!(result.merged, "ONE\ntwo\nthree\nFOUR\n");
+    }
+
+    #[test]
+    fn keeps_the_changed_side_when_the_other_is_unchanged() {
+        let base = "one\ntwo\n";
+        assert_eq!(
+            merge3(base, base, "one\n2\nthree\n").merged,
+            "one\n2\nthree\n"
+        );
+        assert_eq!(merge3(base, "", base).merged, "");
+        assert_eq!(merge3(base, base, base).merged, base);
+    }
+
+    #[test]
+    fn takes_the_same_change_once() {
+        let result = merge3("a\nb\n", "a\nB\n", "a\nB\n");
+        assert!(result.is_clean());
+        assert_eq!(result.merged, "a\nB\n");
+    }
+
+    #[test]
+    fn marks_overlapping_edits_as_a_conflict() {
+        let result = merge3("a\nb\nc\n", "a\nours\nc\n", "a\ntheirs\nc\n");
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(
+            result.merged,
+            "a\n<<<<<<< editor\nours\n||||||| base\nb\n=======\ntheirs\n>>>>>>> disk\nc\n"
+        );
+        let conflict = &result.conflicts[0];
+        assert_eq!((conflict.start_line, conflict.end_line), (2, 8));
+        assert_eq!(
+            (
+                conflict.base.as_str(),
+                conflict.ours.as_str(),
+                conflict.theirs.as_str()
+            ),
+            ("b\n", "ours\n", "theirs\n")
+        );
+    }
+
+    #[test]
+    fn handles_trailing_newlines() {
+        // Adding the final newline merges with an edit further up
+        let result = merge3("a\nb\nc", "a\nb\nc\n", "x\nb\nc");
+        assert!(result.is_clean());
+        assert_eq!(result.merged, "x\nb\nc\n");
+
+        // Markers start on their own line even when a side has no final newline
+        let result = merge3("a\nb", "a\nours", "a\ntheirs");
+        assert_eq!(
+            result.merged,
+            "a\n<<<<<<< editor\nours\n||||||| base\nb\n=======\ntheirs\n>>>>>>> disk\n"
+        );
+        assert_eq!(result.conflicts[0].ours, "ours");
+    }
+}
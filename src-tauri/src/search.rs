@@ -0,0 +1,84 @@
+use crate::crypto::{self, VaultKey};
+use crate::links;
+use serde::Serialize;
+use std::path::Path;
+
+/// Longest snippet of a matching line returned to callers
+const MAX_SNIPPET_CHARS: usize = 200;
+
+/// A line of a note that contains the search term
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+    pub text: String,
+}
+
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(MAX_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Appends the lines of `content` that contain `query` (already lowercased)
+fn search_content(
+    path: &Path,
+    content: &str,
+    query: &str,
+    hits: &mut Vec<SearchHit>,
+    limit: usize,
+) {
+    for (index, line) in content.lines().enumerate() {
+        if hits.len() >= limit {
+            return;
+        }
+        if line.to_lowercase().contains(query) {
+            hits.push(SearchHit {
+                path: path.to_string_lossy().to_string(),
+                line: index + 1,
+                text: snippet(line),
+            });
+        }
+    }
+}
+
+/// Reads a note as text for indexing. Notes with their own passphrase are skipped (None),
+/// as are vault notes unless `vault` is given.
+pub fn read_indexable(path: &Path, vault: Option<&VaultKey>) -> Option<String> {
+    let data = std::fs::read(path).ok()?;
+    let data = if crypto::is_encrypted(&data) {
+        return None;
+    } else if crypto::is_sealed(&data) {
+        crypto::unseal(vault?, &data).ok()?.to_vec()
+    } else {
+        data
+    };
+    String::from_utf8(data).ok()
+}
+
+/// Case-insensitive search of every note under `root`, returning at most `limit` hits.
+/// Notes with their own passphrase are skipped, as are vault notes unless `vault` is given.
+pub fn search_notes(
+    root: &Path,
+    query: &str,
+    limit: usize,
+    vault: Option<&VaultKey>,
+) -> Vec<SearchHit> {
+    let query = query.trim().to_lowercase();
+    let mut hits = Vec::new();
+    if query.is_empty() {
+        return hits;
+    }
+    for path in links::collect_notes(root) {
+        if hits.len() >= limit {
+            break;
+        }
+        if let Some(content) = read_indexable(&path, vault) {
+            search_content(&path, &content, &query, &mut hits, limit);
+        }
+    }
+    hits
+}
@@ -1,3 +1,7 @@
+use crate::commands::capture_commands;
+use crate::commands::daily_note_commands::open_or_create_daily_note;
+use crate::commands::vault_commands;
+use crate::daily_notes;
 use std::thread;
 use std::time::Duration;
 use tauri::GlobalShortcutManager;
@@ -6,6 +10,8 @@ use tauri::{AppHandle, Manager};
 const NEW_NOTE_SHORTCUT_ID: &str = "new_note_shortcut";
 const CLOSE_TAB_SHORTCUT_ID: &str = "close_tab_shortcut";
 const TOGGLE_WINDOW_SHORTCUT_ID: &str = "toggle_window_shortcut";
+const DAILY_NOTE_SHORTCUT_ID: &str = "daily_note_shortcut";
+const CAPTURE_SHORTCUT_ID: &str = "capture_shortcut";
 
 pub fn register_new_note_shortcut(app: &AppHandle, shortcut_str: &str) {
     let mut shortcut_manager = app.global_shortcut_manager();
@@ -91,6 +97,8 @@ pub fn register_toggle_window_shortcut(app: &AppHandle, shortcut_str: &str) {
         } else {
             let _ = main_window.show();
             let _ = main_window.set_focus();
+            // Coming back after an idle lock asks for the vault passphrase again
+            vault_commands::request_unlock_if_locked(&main_window.app_handle());
         }
         let _ = main_window.set_always_on_top(true);
         thread::spawn({
@@ -111,3 +119,62 @@ pub fn register_toggle_window_shortcut(app: &AppHandle, shortcut_str: &str) {
         ),
     }
 }
+
+pub fn register_daily_note_shortcut(app: &AppHandle, shortcut_str: &str) {
+    let mut shortcut_manager = app.global_shortcut_manager();
+    let _ = shortcut_manager.unregister(DAILY_NOTE_SHORTCUT_ID);
+    let main_window = match app.get_window("main") {
+        Some(w) => w,
+        None => {
+            eprintln!("Main window not found, skipping shortcut registration");
+            return;
+        }
+    };
+    let app_handle = app.clone();
+    let shortcut = shortcut_str.to_string();
+    match shortcut_manager.register(&shortcut, move || {
+        let _ = main_window.show();
+        let _ = main_window.set_focus();
+
+        // Resolve (and create if needed) today's note, then let the frontend open it
+        let app_handle = app_handle.clone();
+        let main_window = main_window.clone();
+        tauri::async_runtime::spawn(async move {
+            match open_or_create_daily_note(&app_handle, daily_notes::today()).await {
+                Ok(payload) => {
+                    let _ = main_window.emit("open-file-path", payload.path);
+                }
+                Err(e) => {
+                    eprintln!("Failed to open daily note: {}", e);
+                    let _ = main_window.emit("error", format!("Failed to open daily note: {}", e));
+                }
+            }
+        });
+    }) {
+        Ok(_) => println!("Successfully registered daily note shortcut: {}", shortcut),
+        Err(e) => eprintln!(
+            "Failed to register daily note shortcut '{}': {:?}",
+            shortcut, e
+        ),
+    }
+}
+
+/// Appends the clipboard to the capture note without showing the window
+pub fn register_capture_shortcut(app: &AppHandle, shortcut_str: &str) {
+    let mut shortcut_manager = app.global_shortcut_manager();
+    let _ = shortcut_manager.unregister(CAPTURE_SHORTCUT_ID);
+    let app_handle = app.clone();
+    let shortcut = shortcut_str.to_string();
+    match shortcut_manager.register(&shortcut, move || {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            capture_commands::append_clipboard(&app_handle).await;
+        });
+    }) {
+        Ok(_) => println!("Successfully registered capture shortcut: {}", shortcut),
+        Err(e) => eprintln!(
+            "Failed to register capture shortcut '{}': {:?}",
+            shortcut, e
+        ),
+    }
+}
@@ -0,0 +1,176 @@
+use crate::cli::LaunchRequest;
+use anyhow::{anyhow, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long either side waits on the other before giving up
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Outcome of trying to become the running instance
+pub enum Instance {
+    /// No other instance is running; the listener receives requests from later launches
+    Primary(Listener),
+    /// The request was handed to the instance that is already running
+    Forwarded,
+}
+
+/// Directory for the lock socket: the per-user runtime dir, falling back to the temp dir
+pub fn runtime_dir() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Name of the lock in the runtime dir, per user since the temp dir may be shared
+pub fn lock_name(identifier: &str) -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    if user.is_empty() {
+        identifier.to_string()
+    } else {
+        format!("{}-{}", identifier, user)
+    }
+}
+
+fn send(mut reader: impl BufRead, mut writer: impl Write, request: &LaunchRequest) -> Result<()> {
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+    let mut reply = String::new();
+    reader.read_line(&mut reply)?;
+    if reply.trim() != "ok" {
+        return Err(anyhow!("Running instance did not accept the request"));
+    }
+    Ok(())
+}
+
+fn receive(mut reader: impl BufRead, mut writer: impl Write) -> Result<LaunchRequest> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let request = serde_json::from_str(&line)?;
+    writer.write_all(b"ok\n")?;
+    Ok(request)
+}
+
+#[cfg(unix)]
+mod platform {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    pub type Listener = UnixListener;
+
+    fn forward(path: &Path, request: &LaunchRequest) -> Result<bool> {
+        let stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            // Nobody is listening, so the socket was left behind by a crashed instance
+            Err(_) => return Ok(false),
+        };
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        send(BufReader::new(&stream), &stream, request)?;
+        Ok(true)
+    }
+
+    pub fn acquire(dir: &Path, name: &str, request: &LaunchRequest) -> Result<Instance> {
+        let path = dir.join(format!("{}.sock", name));
+        if forward(&path, request)? {
+            return Ok(Instance::Forwarded);
+        }
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            // Another launch won the race between our connect and bind
+            Err(_) if forward(&path, request)? => return Ok(Instance::Forwarded),
+            Err(e) => return Err(e.into()),
+        };
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(Instance::Primary(listener))
+    }
+
+    pub fn accept(listener: &Listener) -> Result<LaunchRequest> {
+        let (stream, _) = listener.accept()?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        receive(BufReader::new(&stream), &stream)
+    }
+}
+
+#[cfg(not(unix))]
+mod platform {
+    use super::*;
+    use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+
+    /// Loopback listener whose port and token are kept in a lock file only this user can read
+    pub struct Listener {
+        inner: TcpListener,
+        token: String,
+    }
+
+    fn forward(path: &Path, request: &LaunchRequest) -> Result<bool> {
+        let lock = match std::fs::read_to_string(path) {
+            Ok(lock) => lock,
+            Err(_) => return Ok(false),
+        };
+        let (port, token) = match lock.trim().split_once(' ') {
+            Some((port, token)) => (port.parse::<u16>().unwrap_or(0), token.to_string()),
+            None => return Ok(false),
+        };
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let stream = match TcpStream::connect_timeout(&addr, IO_TIMEOUT) {
+            Ok(stream) => stream,
+            Err(_) => return Ok(false),
+        };
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        (&stream).write_all(format!("{}\n", token).as_bytes())?;
+        send(BufReader::new(&stream), &stream, request)?;
+        Ok(true)
+    }
+
+    pub fn acquire(dir: &Path, name: &str, request: &LaunchRequest) -> Result<Instance> {
+        let path = dir.join(format!("{}.lock", name));
+        if forward(&path, request)? {
+            return Ok(Instance::Forwarded);
+        }
+        let inner = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        let token = crate::crypto::random_token();
+        let port = inner.local_addr()?.port();
+        crate::storage::write_atomic(&path, format!("{} {}", port, token).as_bytes())?;
+        Ok(Instance::Primary(Listener { inner, token }))
+    }
+
+    pub fn accept(listener: &Listener) -> Result<LaunchRequest> {
+        let (stream, _) = listener.inner.accept()?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+        let mut reader = BufReader::new(&stream);
+        let mut token = String::new();
+        reader.read_line(&mut token)?;
+        if token.trim() != listener.token {
+            return Err(anyhow!("Rejected a connection with a wrong token"));
+        }
+        receive(reader, &stream)
+    }
+}
+
+pub use platform::Listener;
+
+/// Becomes the running instance, or forwards `request` to the one that already is
+pub fn acquire(dir: &Path, name: &str, request: &LaunchRequest) -> Result<Instance> {
+    platform::acquire(dir, name, request)
+}
+
+/// Hands every request from later launches to `handler`, on a background thread
+pub fn serve(listener: Listener, handler: impl Fn(LaunchRequest) + Send + 'static) {
+    std::thread::spawn(move || loop {
+        match platform::accept(&listener) {
+            Ok(request) => handler(request),
+            Err(e) => eprintln!("Failed to receive arguments from another launch: {}", e),
+        }
+    });
+}
@@ -0,0 +1,386 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Duration, Local, Months};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// File extensions recognised as snippets
+const SNIPPET_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Marker used internally to locate {{cursor}} after expansion
+const CURSOR_MARKER: char = '\u{E000}';
+
+lazy_static! {
+    // {{name}}, {{name:argument}} or, for dates, {{date+3d:format}}
+    static ref PLACEHOLDER_RE: Regex =
+        Regex::new(r"\{\{\s*(\w+)\s*([+-]\s*\d+\s*[a-zA-Z]+)?\s*(?::([^}]*))?\}\}").unwrap();
+    static ref OFFSET_RE: Regex = Regex::new(r"^([+-])\s*(\d+)\s*([a-zA-Z]+)$").unwrap();
+}
+
+/// A snippet file from the snippets folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Snippet {
+    pub name: String,
+    /// Typed in the editor and expanded with Tab, e.g. ";sig"
+    pub trigger: Option<String>,
+    pub description: Option<String>,
+    /// Labels of the `{{field:Label}}` placeholders the user fills in, in order of appearance
+    pub fields: Vec<String>,
+    #[serde(skip)]
+    pub body: String,
+    pub path: String,
+}
+
+/// Values available to placeholders during expansion
+#[derive(Debug, Clone)]
+pub struct SnippetContext {
+    pub now: DateTime<Local>,
+    pub clipboard: Option<String>,
+    /// Values for `{{field:Label}}` placeholders, by label
+    pub fields: HashMap<String, String>,
+}
+
+impl SnippetContext {
+    pub fn new() -> Self {
+        Self {
+            now: Local::now(),
+            clipboard: None,
+            fields: HashMap::new(),
+        }
+    }
+}
+
+impl Default for SnippetContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of expanding a snippet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExpandedSnippet {
+    pub content: String,
+    /// Cursor position in UTF-16 code units, matching JavaScript string indices
+    pub cursor: Option<usize>,
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
+
+/// Splits a snippet file into its `key: value` header and body
+fn split_front_matter(content: &str) -> (BTreeMap<String, String>, &str) {
+    let mut header = BTreeMap::new();
+    let rest = match content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    {
+        Some(rest) => rest,
+        None => return (header, content),
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line == "---" {
+            return (header, &rest[offset..]);
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            header.insert(key.trim().to_lowercase(), unquote(value));
+        }
+    }
+    // No closing line, so the dashes are part of the body
+    (BTreeMap::new(), content)
+}
+
+/// Label and default value of a `{{field:Label=default}}` argument
+fn field_parts(argument: &str) -> (String, Option<String>) {
+    match argument.split_once('=') {
+        Some((label, default)) => (label.trim().to_string(), Some(default.to_string())),
+        None => (argument.trim().to_string(), None),
+    }
+}
+
+/// Parses a snippet file's content
+pub fn parse_snippet(name: &str, path: &Path, content: &str) -> Snippet {
+    let (header, body) = split_front_matter(content);
+    let mut fields: Vec<String> = Vec::new();
+    for caps in PLACEHOLDER_RE.captures_iter(body) {
+        if caps[1].eq_ignore_ascii_case("field") {
+            let (label, _) = field_parts(caps.get(3).map_or("", |m| m.as_str()));
+            if !label.is_empty() && !fields.contains(&label) {
+                fields.push(label);
+            }
+        }
+    }
+    let non_empty = |key: &str| header.get(key).filter(|value| !value.is_empty()).cloned();
+    Snippet {
+        name: name.to_string(),
+        trigger: non_empty("trigger"),
+        description: non_empty("description"),
+        fields,
+        body: body.to_string(),
+        path: path.to_string_lossy().to_string(),
+    }
+}
+
+/// Applies an offset such as "+3d" or "-1w"; units are m(inutes), h, d, w, M(onths) and y
+fn shift(now: DateTime<Local>, offset: &str) -> Option<DateTime<Local>> {
+    let caps = OFFSET_RE.captures(offset)?;
+    let amount: u32 = caps[2].parse().ok()?;
+    let forward = &caps[1] == "+";
+    let months = match &caps[3] {
+        "M" | "mo" => Some(amount),
+        "y" => Some(amount.checked_mul(12)?),
+        _ => None,
+    };
+    if let Some(months) = months {
+        return if forward {
+            now.checked_add_months(Months::new(months))
+        } else {
+            now.checked_sub_months(Months::new(months))
+        };
+    }
+    let amount = i64::from(amount);
+    let duration = match &caps[3] {
+        "m" | "min" => Duration::try_minutes(amount)?,
+        "h" => Duration::try_hours(amount)?,
+        "d" => Duration::try_days(amount)?,
+        "w" => Duration::try_weeks(amount)?,
+        _ => return None,
+    };
+    if forward {
+        now.checked_add_signed(duration)
+    } else {
+        now.checked_sub_signed(duration)
+    }
+}
+
+/// Formats a timestamp, or returns None if the format is invalid
+fn format_time(time: &DateTime<Local>, format: &str) -> Option<String> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return None;
+    }
+    Some(time.format(format).to_string())
+}
+
+/// Expands `{{date}}`, `{{time}}` and `{{datetime}}` with optional offsets and formats,
+/// `{{clipboard}}`, `{{counter:name}}`, `{{field:Label}}` and `{{cursor}}` placeholders.
+///
+/// Each counter used by the snippet goes up by one in `counters`, however often it appears.
+pub fn expand_snippet(
+    body: &str,
+    context: &SnippetContext,
+    counters: &mut BTreeMap<String, u64>,
+) -> ExpandedSnippet {
+    let mut cursor_seen = false;
+    let mut bumped: HashMap<String, u64> = HashMap::new();
+    let expanded = PLACEHOLDER_RE.replace_all(body, |caps: &Captures| {
+        let offset = caps.get(2).map(|m| m.as_str().trim());
+        let argument = caps.get(3).map(|m| m.as_str());
+        let name = caps[1].to_lowercase();
+        let default_format = match name.as_str() {
+            "date" => Some("%Y-%m-%d"),
+            "time" => Some("%H:%M"),
+            "datetime" => Some("%Y-%m-%d %H:%M"),
+            _ => None,
+        };
+        if let Some(default_format) = default_format {
+            let time = match offset {
+                Some(offset) => shift(context.now, offset),
+                None => Some(context.now),
+            };
+            let format = argument.map(str::trim).unwrap_or(default_format);
+            return time
+                .and_then(|time| format_time(&time, format))
+                .unwrap_or_else(|| caps[0].to_string());
+        }
+        if offset.is_some() {
+            return caps[0].to_string();
+        }
+        match name.as_str() {
+            "clipboard" => context.clipboard.clone().unwrap_or_default(),
+            "counter" => {
+                // {{counter:name}} or {{counter:name:width}} for zero padding
+                let mut parts = argument.unwrap_or("").splitn(2, ':');
+                let counter = parts.next().unwrap_or("").trim();
+                let counter = if counter.is_empty() {
+                    "default"
+                } else {
+                    counter
+                };
+                let width = parts
+                    .next()
+                    .and_then(|width| width.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                let value = *bumped.entry(counter.to_string()).or_insert_with(|| {
+                    let value = counters.entry(counter.to_string()).or_insert(0);
+                    *value += 1;
+                    *value
+                });
+                format!("{:0width$}", value, width = width)
+            }
+            "field" => {
+                let (label, default) = field_parts(argument.unwrap_or(""));
+                context
+                    .fields
+                    .get(&label)
+                    .cloned()
+                    .or(default)
+                    .unwrap_or_default()
+            }
+            // Only the first {{cursor}} is kept, later ones are dropped
+            "cursor" if !cursor_seen => {
+                cursor_seen = true;
+                CURSOR_MARKER.to_string()
+            }
+            "cursor" => String::new(),
+            // Leave unknown placeholders untouched
+            _ => caps[0].to_string(),
+        }
+    });
+
+    let (content, cursor) = match expanded.find(CURSOR_MARKER) {
+        Some(index) => {
+            let cursor = expanded[..index].encode_utf16().count();
+            (expanded.replacen(CURSOR_MARKER, "", 1), Some(cursor))
+        }
+        None => (expanded.into_owned(), None),
+    };
+    ExpandedSnippet { content, cursor }
+}
+
+/// Returns true if the snippet uses the given placeholder
+pub fn uses_placeholder(body: &str, name: &str) -> bool {
+    PLACEHOLDER_RE
+        .captures_iter(body)
+        .any(|caps| caps[1].eq_ignore_ascii_case(name))
+}
+
+fn is_snippet_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SNIPPET_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Loads the snippets stored in `dir`, sorted by name
+pub fn list_snippets(dir: &Path) -> Vec<Snippet> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut snippets: Vec<Snippet> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_snippet_file(path))
+        .filter_map(|path: PathBuf| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some(parse_snippet(&name, &path, &content))
+        })
+        .collect();
+    snippets.sort_by_key(|snippet| snippet.name.to_lowercase());
+    snippets
+}
+
+/// Finds a snippet by name, or failing that by trigger
+pub fn find_snippet<'a>(snippets: &'a [Snippet], key: &str) -> Option<&'a Snippet> {
+    snippets
+        .iter()
+        .find(|snippet| snippet.name == key)
+        .or_else(|| {
+            snippets
+                .iter()
+                .find(|snippet| snippet.trigger.as_deref() == Some(key))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn context_at(year: i32, month: u32, day: u32) -> SnippetContext {
+        SnippetContext {
+            now: Local.with_ymd_and_hms(year, month, day, 9, 5, 0).unwrap(),
+            ..SnippetContext::new()
+        }
+    }
+
+    fn expand(body: &str, context: &SnippetContext) -> String {
+        expand_snippet(body, context, &mut BTreeMap::new()).content
+    }
+
+    #[test]
+    fn month_offsets_stop_at_the_end_of_the_month() {
+        let context = context_at(2026, 1, 31);
+        assert_eq!(expand("{{date+1M}}", &context), "2026-02-28");
+        assert_eq!(expand("{{date+1y}}", &context), "2027-01-31");
+        assert_eq!(expand("{{date+13M}}", &context), "2027-02-28");
+    }
+
+    #[test]
+    fn applies_negative_offsets() {
+        let context = context_at(2026, 3, 31);
+        assert_eq!(expand("{{date-1M}}", &context), "2026-02-28");
+        assert_eq!(expand("{{date-1w:%A}}", &context), "Tuesday");
+        assert_eq!(expand("{{time-90m}}", &context), "07:35");
+        assert_eq!(expand("{{datetime - 2d}}", &context), "2026-03-29 09:05");
+    }
+
+    #[test]
+    fn leaves_invalid_placeholders_untouched() {
+        let context = context_at(2026, 1, 31);
+        for body in [
+            "{{date:%Q}}",
+            "{{date+3q}}",
+            "{{clipboard+1d}}",
+            "{{bogus}}",
+            "{{date+99999999999y}}",
+        ] {
+            assert_eq!(expand(body, &context), body);
+        }
+    }
+
+    #[test]
+    fn pads_counters_and_bumps_them_once_per_expansion() {
+        let context = context_at(2026, 1, 31);
+        let mut counters = BTreeMap::new();
+        let expanded = expand_snippet(
+            "{{counter:invoice:4}} {{counter:invoice}} {{counter}}",
+            &context,
+            &mut counters,
+        );
+        assert_eq!(expanded.content, "0001 1 1");
+        let expanded = expand_snippet("{{counter:invoice:3}}", &context, &mut counters);
+        assert_eq!(expanded.content, "002");
+        assert_eq!(counters["invoice"], 2);
+        assert_eq!(counters["default"], 1);
+    }
+
+    #[test]
+    fn counts_the_cursor_in_utf16_units() {
+        let context = context_at(2026, 1, 31);
+        let expanded = expand_snippet(
+            "😀é {{cursor}}after{{cursor}}",
+            &context,
+            &mut BTreeMap::new(),
+        );
+        assert_eq!(expanded.content, "😀é after");
+        assert_eq!(expanded.cursor, Some(4));
+        assert_eq!(
+            expand_snippet("none", &context, &mut BTreeMap::new()).cursor,
+            None
+        );
+    }
+}
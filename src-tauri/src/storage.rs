@@ -0,0 +1,56 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+/// Suffix of partially written files; sync never copies them
+pub const PARTIAL_SUFFIX: &str = ".quicknote-part";
+
+/// Writes a file atomically by renaming a temporary file into place
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    replace_file(path, data, false)
+}
+
+/// Like `write_atomic`, but the file is readable only by the current user from the start
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    replace_file(path, data, true)
+}
+
+fn replace_file(path: &Path, data: &[u8], private: bool) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let partial = path.with_file_name(format!(".{}{}", file_name, PARTIAL_SUFFIX));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if private {
+        // A part file left by a crash keeps its old mode, so always start a new one
+        let _ = std::fs::remove_file(&partial);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+    }
+    options.open(&partial)?.write_all(data)?;
+    std::fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Loads a JSON state file, returning the default value if it does not exist yet
+pub fn load_state<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let content = std::fs::read_to_string(path)?;
+    serde_json::from_str(&content).with_context(|| format!("{} is corrupted", path.display()))
+}
+
+pub fn save_state<T: Serialize>(path: &Path, state: &T) -> Result<()> {
+    write_atomic(path, serde_json::to_string_pretty(state)?.as_bytes())
+}
@@ -0,0 +1,590 @@
+use super::{conflict_copy_key, local_path, scan_local, seal_for, SyncEvent, SyncReport};
+use crate::crypto::{self, VaultKey};
+use crate::history::content_hash;
+use crate::merge;
+use crate::storage::{load_state, save_state, write_atomic};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Shared manifest kept in the mirror folder and updated by every device syncing with it
+const SHARED_MANIFEST: &str = ".quicknote-sync.json";
+
+/// Replica name used for edits made in the mirror by something other than QuickNote
+const MIRROR_REPLICA: &str = "mirror";
+
+/// Files up to this size keep their last synced content so concurrent edits can be merged
+const MAX_BASE_SIZE: usize = 1024 * 1024;
+
+/// Version vector: how many changes each replica has made to a file
+type Clock = BTreeMap<String, u64>;
+
+/// A file's content hash (None once deleted) and the changes that led to it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Version {
+    hash: Option<String>,
+    clock: Clock,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SharedManifest {
+    files: BTreeMap<String, Version>,
+}
+
+/// What this device last agreed on with the mirror
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalState {
+    replica: String,
+    files: BTreeMap<String, Version>,
+}
+
+fn bump(clock: &Clock, replica: &str) -> Clock {
+    let mut clock = clock.clone();
+    *clock.entry(replica.to_string()).or_insert(0) += 1;
+    clock
+}
+
+/// Elementwise maximum of two clocks
+fn join(a: &Clock, b: &Clock) -> Clock {
+    let mut joined = a.clone();
+    for (replica, &count) in b {
+        let entry = joined.entry(replica.clone()).or_insert(0);
+        *entry = (*entry).max(count);
+    }
+    joined
+}
+
+/// Orders two clocks, or None if each has changes the other has not seen
+fn compare(a: &Clock, b: &Clock) -> Option<Ordering> {
+    let replicas: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    let mut ordering = Ordering::Equal;
+    for replica in replicas {
+        let left = a.get(replica).copied().unwrap_or(0);
+        let right = b.get(replica).copied().unwrap_or(0);
+        match (ordering, left.cmp(&right)) {
+            (_, Ordering::Equal) => {}
+            (Ordering::Equal, side) => ordering = side,
+            (current, side) if current != side => return None,
+            _ => {}
+        }
+    }
+    Some(ordering)
+}
+
+/// Creates an identifier for a device that has not synced with this mirror before
+fn new_replica_id(mirror: &Path) -> String {
+    let seed = format!(
+        "{}:{}:{}",
+        mirror.display(),
+        std::process::id(),
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    content_hash(seed.as_bytes())[..12].to_string()
+}
+
+/// Two-way sync between the notes root and a mirror folder such as a Dropbox or Syncthing share
+pub struct FolderSync<'a> {
+    root: &'a Path,
+    mirror: &'a Path,
+    state_dir: &'a Path,
+    vault: Option<&'a VaultKey>,
+    state: LocalState,
+    shared: SharedManifest,
+    report: SyncReport,
+}
+
+impl<'a> FolderSync<'a> {
+    /// `state_dir` holds this device's sync state and must be specific to the mirror.
+    /// With `vault` set, notes copied into the notes root are sealed.
+    pub fn new(
+        root: &'a Path,
+        mirror: &'a Path,
+        state_dir: &'a Path,
+        vault: Option<&'a VaultKey>,
+    ) -> Result<Self> {
+        if !mirror.is_dir() {
+            return Err(anyhow!("Sync folder {} does not exist", mirror.display()));
+        }
+        let (root_full, mirror_full) = (root.canonicalize()?, mirror.canonicalize()?);
+        if root_full.starts_with(&mirror_full) || mirror_full.starts_with(&root_full) {
+            return Err(anyhow!(
+                "The sync folder must not contain or be inside the notes folder"
+            ));
+        }
+
+        let mut state: LocalState = load_state(&state_dir.join("state.json"))?;
+        if state.replica.is_empty() {
+            state.replica = new_replica_id(mirror);
+        }
+        Ok(Self {
+            root,
+            mirror,
+            state_dir,
+            vault,
+            state,
+            shared: load_state(&mirror.join(SHARED_MANIFEST))?,
+            report: SyncReport::default(),
+        })
+    }
+
+    /// Saves both manifests after every change so an interrupted sync never forgets an edit
+    fn save(&self) -> Result<()> {
+        save_state(&self.state_dir.join("state.json"), &self.state)?;
+        save_state(&self.mirror.join(SHARED_MANIFEST), &self.shared)
+    }
+
+    fn base_path(&self, hash: &str) -> PathBuf {
+        self.state_dir.join("base").join(hash)
+    }
+
+    /// Remembers synced content as the common ancestor for future merges
+    fn store_base(&self, hash: &str, data: &[u8]) -> Result<()> {
+        let path = self.base_path(hash);
+        if data.len() <= MAX_BASE_SIZE && !path.exists() {
+            write_atomic(&path, data)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self, dir: &Path, key: &str) -> Result<Vec<u8>> {
+        let path = local_path(dir, key).ok_or_else(|| anyhow!("Invalid path: {}", key))?;
+        Ok(std::fs::read(path)?)
+    }
+
+    /// Plaintext of a sealed note for merging; other data is returned as is
+    fn open(&self, data: &[u8]) -> Vec<u8> {
+        match self.vault {
+            Some(vault) if crypto::is_sealed(data) => crypto::unseal(vault, data)
+                .map(|plaintext| plaintext.to_vec())
+                .unwrap_or_else(|_| data.to_vec()),
+            _ => data.to_vec(),
+        }
+    }
+
+    /// Writes `data` (or deletes the file for None) on one side
+    fn write(&self, dir: &Path, key: &str, data: Option<&[u8]>) -> Result<()> {
+        let path = local_path(dir, key).ok_or_else(|| anyhow!("Invalid path: {}", key))?;
+        match data {
+            Some(data) => write_atomic(&path, data),
+            None if path.exists() => Ok(std::fs::remove_file(path)?),
+            None => Ok(()),
+        }
+    }
+
+    /// Records the version both sides now hold
+    fn settle(&mut self, key: &str, version: Version) -> Result<()> {
+        self.shared.files.insert(key.to_string(), version.clone());
+        self.state.files.insert(key.to_string(), version);
+        self.save()
+    }
+
+    /// Copies one side over the other, or propagates a deletion when `hash` is None.
+    /// Returns the hash both sides hold afterwards.
+    fn copy(
+        &mut self,
+        key: &str,
+        from_root: bool,
+        hash: Option<&String>,
+    ) -> Result<Option<String>> {
+        let (from, to) = if from_root {
+            (self.root, self.mirror)
+        } else {
+            (self.mirror, self.root)
+        };
+        let data = match hash {
+            Some(_) if from_root => Some(self.read(from, key)?),
+            Some(_) => Some(seal_for(self.vault, self.read(from, key)?)?),
+            None => None,
+        };
+        let copied = data.as_deref().map(content_hash);
+        self.write(to, key, data.as_deref())?;
+        if let Some(data) = &data {
+            if copied.as_ref() != hash {
+                // Sealed on the way into the vault, so the mirror gets the sealed copy as well
+                self.write(from, key, Some(data))?;
+            }
+            self.store_base(&content_hash(data), data)?;
+        }
+
+        let key = key.to_string();
+        match (from_root, data.is_some()) {
+            (true, true) => self.report.uploaded.push(key),
+            (true, false) => self.report.deleted_remote.push(key),
+            (false, true) => self.report.downloaded.push(key),
+            (false, false) => self.report.deleted_local.push(key),
+        }
+        Ok(copied)
+    }
+
+    /// Both sides changed the file independently; merge them or keep both
+    fn resolve_conflict(
+        &mut self,
+        key: &str,
+        base: Option<&String>,
+        local: &Version,
+        mirror: &Version,
+    ) -> Result<&'static str> {
+        let clock = bump(&join(&local.clock, &mirror.clock), &self.state.replica);
+
+        // An edit wins over a deletion so nothing is lost
+        let local_hash = match (&local.hash, &mirror.hash) {
+            (Some(local_hash), Some(_)) => local_hash,
+            (Some(hash), None) => {
+                let hash = self.copy(key, true, Some(hash))?;
+                self.settle(key, Version { hash, clock })?;
+                return Ok("uploading");
+            }
+            (None, Some(hash)) => {
+                let hash = self.copy(key, false, Some(hash))?;
+                self.settle(key, Version { hash, clock })?;
+                return Ok("downloading");
+            }
+            (None, None) => {
+                self.settle(key, Version { hash: None, clock })?;
+                return Ok("deleting");
+            }
+        };
+
+        let ours = self.read(self.root, key)?;
+        let theirs = self.read(self.mirror, key)?;
+        let base_text = base
+            .and_then(|hash| std::fs::read(self.base_path(hash)).ok())
+            .map(|base| self.open(&base));
+        let (ours_open, theirs_open) = (self.open(&ours), self.open(&theirs));
+        if let (Some(base), Ok(ours_text), Ok(theirs_text)) = (
+            base_text.and_then(|base| String::from_utf8(base).ok()),
+            std::str::from_utf8(&ours_open),
+            std::str::from_utf8(&theirs_open),
+        ) {
+            let result = merge::merge3(&base, ours_text, theirs_text);
+            if result.is_clean() {
+                let merged = seal_for(self.vault, result.merged.into_bytes())?;
+                let hash = content_hash(&merged);
+                self.write(self.root, key, Some(&merged))?;
+                self.write(self.mirror, key, Some(&merged))?;
+                self.store_base(&hash, &merged)?;
+                self.settle(
+                    key,
+                    Version {
+                        hash: Some(hash),
+                        clock,
+                    },
+                )?;
+                self.report.merged.push(key.to_string());
+                return Ok("merged");
+            }
+        }
+
+        // Keep ours under the real name and theirs side by side as a conflict copy
+        let copy_key = conflict_copy_key(key, MIRROR_REPLICA);
+        let theirs = seal_for(self.vault, theirs)?;
+        let copy_hash = content_hash(&theirs);
+        self.write(self.root, &copy_key, Some(&theirs))?;
+        self.write(self.mirror, &copy_key, Some(&theirs))?;
+        self.write(self.mirror, key, Some(&ours))?;
+        self.store_base(local_hash, &ours)?;
+        self.store_base(&copy_hash, &theirs)?;
+        self.settle(
+            &copy_key,
+            Version {
+                hash: Some(copy_hash),
+                clock: bump(&Clock::new(), &self.state.replica),
+            },
+        )?;
+        self.settle(
+            key,
+            Version {
+                hash: Some(local_hash.clone()),
+                clock,
+            },
+        )?;
+        self.report.conflicts.push(copy_key);
+        Ok("conflict")
+    }
+
+    fn sync_file(
+        &mut self,
+        key: &str,
+        local_hash: Option<String>,
+        mirror_hash: Option<String>,
+    ) -> Result<Option<&'static str>> {
+        let previous = self.state.files.get(key).cloned().unwrap_or_default();
+        let shared = self.shared.files.get(key).cloned().unwrap_or_default();
+
+        // A side whose content differs from what was recorded has a new change
+        let local = Version {
+            clock: if local_hash != previous.hash {
+                bump(&previous.clock, &self.state.replica)
+            } else {
+                previous.clock.clone()
+            },
+            hash: local_hash,
+        };
+        let mirror = Version {
+            clock: if mirror_hash != shared.hash {
+                bump(&shared.clock, MIRROR_REPLICA)
+            } else {
+                shared.clock.clone()
+            },
+            hash: mirror_hash,
+        };
+
+        if local.hash == mirror.hash {
+            let version = Version {
+                clock: join(&local.clock, &mirror.clock),
+                hash: local.hash,
+            };
+            if self.state.files.get(key) != Some(&version)
+                || self.shared.files.get(key) != Some(&version)
+            {
+                // Both sides already agree, e.g. on the first sync of identical folders
+                if version.hash.is_some() {
+                    let data = self.read(self.root, key)?;
+                    self.store_base(&content_hash(&data), &data)?;
+                }
+                self.settle(key, version)?;
+            }
+            return Ok(None);
+        }
+
+        match compare(&local.clock, &mirror.clock) {
+            Some(Ordering::Greater) => {
+                let hash = self.copy(key, true, local.hash.as_ref())?;
+                let phase = if hash.is_some() {
+                    "uploading"
+                } else {
+                    "deleting"
+                };
+                self.settle(key, Version { hash, ..local })?;
+                Ok(Some(phase))
+            }
+            Some(Ordering::Less) => {
+                let hash = self.copy(key, false, mirror.hash.as_ref())?;
+                let phase = if hash.is_some() {
+                    "downloading"
+                } else {
+                    "deleting"
+                };
+                self.settle(key, Version { hash, ..mirror })?;
+                Ok(Some(phase))
+            }
+            _ => self
+                .resolve_conflict(key, previous.hash.as_ref(), &local, &mirror)
+                .map(Some),
+        }
+    }
+
+    /// Drops stored merge bases that no synced file refers to any more
+    fn prune_bases(&self) -> Result<()> {
+        let dir = self.state_dir.join("base");
+        if !dir.exists() {
+            return Ok(());
+        }
+        let live: BTreeSet<&String> = self
+            .state
+            .files
+            .values()
+            .filter_map(|version| version.hash.as_ref())
+            .collect();
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            if !live.contains(&entry.file_name().to_string_lossy().to_string()) {
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs a full two-way sync, reporting progress through `on_event`
+    pub fn run(mut self, on_event: &mut dyn FnMut(SyncEvent)) -> Result<SyncReport> {
+        on_event(SyncEvent::new("scanning", None, 0, 0));
+        let local = scan_local(self.root)?;
+        let mirror = scan_local(self.mirror)?;
+
+        let keys: BTreeSet<String> = local
+            .keys()
+            .chain(mirror.keys())
+            .chain(self.state.files.keys())
+            .chain(self.shared.files.keys())
+            .cloned()
+            .collect();
+        let total = keys.len();
+
+        for (index, key) in keys.iter().enumerate() {
+            let local_hash = local.get(key).map(|file| file.hash.clone());
+            let mirror_hash = mirror.get(key).map(|file| file.hash.clone());
+            match self.sync_file(key, local_hash, mirror_hash) {
+                Ok(Some(phase)) => on_event(SyncEvent::new(phase, Some(key), index + 1, total)),
+                Ok(None) => {}
+                Err(e) => {
+                    on_event(
+                        SyncEvent::new("error", Some(key), index + 1, total)
+                            .with_message(e.to_string()),
+                    );
+                    self.report.errors.push((key.clone(), e.to_string()));
+                }
+            }
+        }
+
+        self.save()?;
+        self.prune_bases()?;
+        on_event(SyncEvent::new("done", None, total, total));
+        Ok(self.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn tempdir(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "quicknote-folder-{}-{}-{}",
+            std::process::id(),
+            name,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Two devices syncing through one mirror folder, each with its own state
+    struct Setup {
+        a: PathBuf,
+        b: PathBuf,
+        mirror: PathBuf,
+        state_a: PathBuf,
+        state_b: PathBuf,
+    }
+
+    impl Setup {
+        /// Both devices start out with `files` synced
+        fn new(files: &[(&str, &str)]) -> Self {
+            let setup = Self {
+                a: tempdir("a"),
+                b: tempdir("b"),
+                mirror: tempdir("mirror"),
+                state_a: tempdir("state-a"),
+                state_b: tempdir("state-b"),
+            };
+            for (name, content) in files {
+                std::fs::write(setup.a.join(name), content).unwrap();
+            }
+            setup.sync_a();
+            setup.sync_b();
+            setup
+        }
+
+        fn sync_a(&self) -> SyncReport {
+            sync(&self.a, &self.mirror, &self.state_a)
+        }
+
+        fn sync_b(&self) -> SyncReport {
+            sync(&self.b, &self.mirror, &self.state_b)
+        }
+    }
+
+    fn sync(root: &Path, mirror: &Path, state_dir: &Path) -> SyncReport {
+        let report = FolderSync::new(root, mirror, state_dir, None)
+            .unwrap()
+            .run(&mut |_| {})
+            .unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        report
+    }
+
+    fn read(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    fn is_idle(report: &SyncReport) -> bool {
+        report.uploaded.is_empty()
+            && report.downloaded.is_empty()
+            && report.deleted_local.is_empty()
+            && report.deleted_remote.is_empty()
+            && report.merged.is_empty()
+            && report.conflicts.is_empty()
+    }
+
+    #[test]
+    fn copies_new_notes_to_the_other_device() {
+        let setup = Setup::new(&[]);
+        std::fs::write(setup.a.join("one.md"), "one").unwrap();
+        assert_eq!(setup.sync_a().uploaded, vec!["one.md"]);
+        assert_eq!(setup.sync_b().downloaded, vec!["one.md"]);
+        assert_eq!(read(setup.b.join("one.md")), "one");
+    }
+
+    #[test]
+    fn a_second_run_has_nothing_to_do() {
+        let setup = Setup::new(&[("one.md", "one")]);
+        assert!(is_idle(&setup.sync_a()));
+        assert!(is_idle(&setup.sync_b()));
+    }
+
+    #[test]
+    fn merges_concurrent_edits_to_different_lines() {
+        let setup = Setup::new(&[("one.md", "first\nsecond\nthird\n")]);
+        std::fs::write(setup.a.join("one.md"), "FIRST\nsecond\nthird\n").unwrap();
+        std::fs::write(setup.b.join("one.md"), "first\nsecond\nTHIRD\n").unwrap();
+        setup.sync_a();
+        assert_eq!(setup.sync_b().merged, vec!["one.md"]);
+        setup.sync_a();
+        for root in [&setup.a, &setup.b, &setup.mirror] {
+            assert_eq!(read(root.join("one.md")), "FIRST\nsecond\nTHIRD\n");
+        }
+        assert!(is_idle(&setup.sync_a()));
+    }
+
+    #[test]
+    fn keeps_both_versions_of_conflicting_edits() {
+        let setup = Setup::new(&[("one.md", "base\n")]);
+        std::fs::write(setup.a.join("one.md"), "from a\n").unwrap();
+        std::fs::write(setup.b.join("one.md"), "from b\n").unwrap();
+        setup.sync_a();
+        let report = setup.sync_b();
+        assert_eq!(report.conflicts.len(), 1);
+        let copy = &report.conflicts[0];
+        assert_eq!(read(setup.b.join("one.md")), "from b\n");
+        assert_eq!(read(setup.b.join(copy)), "from a\n");
+
+        // The other device picks up both without another conflict
+        assert!(setup.sync_a().conflicts.is_empty());
+        assert_eq!(read(setup.a.join("one.md")), "from b\n");
+        assert_eq!(read(setup.a.join(copy)), "from a\n");
+    }
+
+    #[test]
+    fn an_edit_wins_over_a_deletion() {
+        let setup = Setup::new(&[("one.md", "one")]);
+        std::fs::remove_file(setup.a.join("one.md")).unwrap();
+        std::fs::write(setup.b.join("one.md"), "edited").unwrap();
+        setup.sync_a();
+        setup.sync_b();
+        setup.sync_a();
+        for root in [&setup.a, &setup.b, &setup.mirror] {
+            assert_eq!(read(root.join("one.md")), "edited");
+        }
+    }
+
+    #[test]
+    fn propagates_an_unopposed_deletion() {
+        let setup = Setup::new(&[("one.md", "one")]);
+        std::fs::remove_file(setup.a.join("one.md")).unwrap();
+        assert_eq!(setup.sync_a().deleted_remote, vec!["one.md"]);
+        assert_eq!(setup.sync_b().deleted_local, vec!["one.md"]);
+        assert!(!setup.b.join("one.md").exists());
+    }
+
+    #[test]
+    fn rejects_a_mirror_inside_the_notes_root() {
+        let root = tempdir("nested");
+        std::fs::create_dir_all(root.join("mirror")).unwrap();
+        assert!(FolderSync::new(&root, &root.join("mirror"), &tempdir("state"), None).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+pub mod folder;
+pub mod webdav;
+
+use crate::crypto::{self, VaultKey};
+use crate::history::content_hash;
+use crate::storage::PARTIAL_SUFFIX;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A file in the local notes root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalFile {
+    pub hash: String,
+    pub size: u64,
+}
+
+/// Progress update emitted while a sync runs
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncEvent {
+    /// "scanning", "uploading", "downloading", "deleting", "merged", "conflict", "done" or "error"
+    pub phase: String,
+    pub path: Option<String>,
+    pub done: usize,
+    pub total: usize,
+    pub message: Option<String>,
+}
+
+impl SyncEvent {
+    pub fn new(phase: &str, path: Option<&str>, done: usize, total: usize) -> Self {
+        Self {
+            phase: phase.to_string(),
+            path: path.map(str::to_string),
+            done,
+            total,
+            message: None,
+        }
+    }
+
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+}
+
+/// Summary of one sync run; paths are relative to the notes root
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncReport {
+    pub uploaded: Vec<String>,
+    pub downloaded: Vec<String>,
+    pub deleted_local: Vec<String>,
+    pub deleted_remote: Vec<String>,
+    /// Files both sides changed whose edits were merged cleanly
+    pub merged: Vec<String>,
+    /// Conflict copies created because both sides changed
+    pub conflicts: Vec<String>,
+    /// Files that failed, with the reason; the rest of the sync still runs
+    pub errors: Vec<(String, String)>,
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// Returns true for files a sync should ignore
+pub fn is_ignored(relative: &str) -> bool {
+    relative.split('/').any(is_hidden) || relative.ends_with(PARTIAL_SUFFIX)
+}
+
+/// Converts a path under `root` to a relative path with forward slashes
+pub fn relative_key(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Maps a relative key back to a path under `root`, rejecting keys that would escape it
+pub fn local_path(root: &Path, key: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for part in key.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return None;
+        }
+        path.push(part);
+    }
+    Some(path)
+}
+
+/// Hashes every syncable file under `root`
+pub fn scan_local(root: &Path) -> Result<BTreeMap<String, LocalFile>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(root).into_iter().filter_entry(|entry| {
+        entry.depth() == 0 || !is_hidden(&entry.file_name().to_string_lossy())
+    }) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let key = match relative_key(root, entry.path()) {
+            Some(key) if !is_ignored(&key) => key,
+            _ => continue,
+        };
+        let data = std::fs::read(entry.path())
+            .with_context(|| format!("Failed to read {}", entry.path().display()))?;
+        files.insert(
+            key,
+            LocalFile {
+                hash: content_hash(&data),
+                size: data.len() as u64,
+            },
+        );
+    }
+    Ok(files)
+}
+
+/// Name for the copy of a file kept when both sides changed, e.g. "a (conflict laptop 2026-10-19 1430).md"
+pub fn conflict_copy_key(key: &str, label: &str) -> String {
+    let (dir, name) = match key.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), key),
+    };
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    let stamp = chrono::Local::now().format("%Y-%m-%d %H%M%S");
+    format!(
+        "{}{} (conflict {} {}){}",
+        dir, stem, label, stamp, extension
+    )
+}
+
+/// Seals plaintext arriving in a vault notes root; anything else passes through unchanged
+pub fn seal_for(vault: Option<&VaultKey>, data: Vec<u8>) -> Result<Vec<u8>> {
+    match vault {
+        Some(vault) if !crypto::is_sealed(&data) && !crypto::is_encrypted(&data) => {
+            crypto::seal(vault, &data)
+        }
+        _ => Ok(data),
+    }
+}
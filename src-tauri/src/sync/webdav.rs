@@ -0,0 +1,1074 @@
+use super::{
+    conflict_copy_key, is_ignored, local_path, scan_local, seal_for, LocalFile, SyncEvent,
+    SyncReport,
+};
+use crate::crypto::VaultKey;
+use crate::history::content_hash;
+use crate::storage::{load_state, save_state, write_atomic, PARTIAL_SUFFIX};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+/// Uploads larger than this are sent in ranges so an interrupted upload can resume
+const CHUNK_SIZE: usize = 1024 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getetag/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+/// Server and credentials to sync with
+#[derive(Debug, Clone)]
+pub struct WebDavConfig {
+    /// Collection URL the notes root is mirrored to
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// State of a file as of the last successful sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncedFile {
+    hash: String,
+    etag: String,
+}
+
+/// Local manifest remembering what both sides looked like after the last sync
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct WebDavManifest {
+    url: String,
+    files: BTreeMap<String, SyncedFile>,
+    /// Uploads that were started but not finished, keyed by path with the content hash
+    pending: BTreeMap<String, String>,
+}
+
+/// One resource from a PROPFIND response
+struct DavEntry {
+    key: String,
+    is_collection: bool,
+    /// Empty when the server does not report ETags
+    etag: String,
+    size: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+struct RemoteFile {
+    etag: String,
+}
+
+/// Raised when the server copy changed between listing and writing
+#[derive(Debug)]
+struct RemoteChanged;
+
+impl std::fmt::Display for RemoteChanged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "File changed on the server during sync")
+    }
+}
+
+impl std::error::Error for RemoteChanged {}
+
+/// Response of a request, with error statuses kept apart from transport errors
+enum Reply {
+    Ok(Box<ureq::Response>),
+    Status(u16),
+}
+
+fn reply(result: std::result::Result<ureq::Response, ureq::Error>) -> Result<Reply> {
+    match result {
+        Ok(response) => Ok(Reply::Ok(Box::new(response))),
+        Err(ureq::Error::Status(code, _)) => Ok(Reply::Status(code)),
+        Err(e) => Err(anyhow!("WebDAV request failed: {}", e)),
+    }
+}
+
+/// Percent-encodes one path segment
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Path part of a URL or href, e.g. "/dav/notes/" from "https://host/dav/notes/"
+fn url_path(url: &str) -> &str {
+    match url.find("://") {
+        Some(scheme_end) => {
+            let rest = &url[scheme_end + 3..];
+            rest.find('/').map(|slash| &rest[slash..]).unwrap_or("/")
+        }
+        None => url,
+    }
+}
+
+/// Minimal WebDAV client covering what the sync engine needs
+pub struct WebDavClient {
+    agent: ureq::Agent,
+    /// Collection URL, always ending with a slash
+    base: String,
+    /// Decoded path of the collection, used to turn hrefs back into keys
+    base_path: String,
+    authorization: Option<String>,
+}
+
+impl WebDavClient {
+    pub fn new(config: &WebDavConfig) -> Result<Self> {
+        let url = config.url.trim();
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(anyhow!("WebDAV URL must start with http:// or https://"));
+        }
+        let base = format!("{}/", url.trim_end_matches('/'));
+        let base_path = percent_decode(url_path(&base));
+        let authorization = config.username.as_deref().map(|username| {
+            let credentials = format!("{}:{}", username, config.password.as_deref().unwrap_or(""));
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            )
+        });
+        Ok(Self {
+            agent: ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build(),
+            base,
+            base_path,
+            authorization,
+        })
+    }
+
+    fn url(&self, key: &str) -> String {
+        let encoded: Vec<String> = key.split('/').map(encode_segment).collect();
+        format!("{}{}", self.base, encoded.join("/"))
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    /// Turns an href from a PROPFIND response into a key relative to the collection
+    fn key_of(&self, href: &str) -> Option<String> {
+        let path = percent_decode(url_path(href));
+        let key = path.strip_prefix(&self.base_path)?;
+        Some(key.trim_end_matches('/').to_string())
+    }
+
+    /// Runs PROPFIND with the given depth; None if the resource does not exist
+    fn propfind(&self, url: &str, depth: &str) -> Result<Option<Vec<DavEntry>>> {
+        let response = match reply(
+            self.request("PROPFIND", url)
+                .set("Depth", depth)
+                .set("Content-Type", "application/xml; charset=utf-8")
+                .send_string(PROPFIND_BODY),
+        )? {
+            Reply::Ok(response) => response,
+            Reply::Status(404) => return Ok(None),
+            Reply::Status(401) => return Err(anyhow!("WebDAV server rejected the credentials")),
+            Reply::Status(code) => return Err(anyhow!("PROPFIND {} returned {}", url, code)),
+        };
+        let body = response.into_string()?;
+        let document = roxmltree::Document::parse(&body).context("Invalid PROPFIND response")?;
+
+        let mut entries = Vec::new();
+        for node in document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "response")
+        {
+            let find = |name: &str| {
+                node.descendants()
+                    .find(|child| child.tag_name().name() == name)
+                    .and_then(|child| child.text())
+                    .map(str::trim)
+            };
+            let key = match find("href").and_then(|href| self.key_of(href)) {
+                Some(key) => key,
+                None => continue,
+            };
+            entries.push(DavEntry {
+                key,
+                is_collection: node
+                    .descendants()
+                    .any(|child| child.tag_name().name() == "collection"),
+                etag: find("getetag").unwrap_or("").to_string(),
+                size: find("getcontentlength").and_then(|size| size.parse().ok()),
+            });
+        }
+        Ok(Some(entries))
+    }
+
+    /// Recursively lists every file and collection under the base collection
+    fn list(&self) -> Result<(BTreeMap<String, RemoteFile>, HashSet<String>)> {
+        let mut files = BTreeMap::new();
+        let mut dirs = HashSet::new();
+        let mut queue = vec![String::new()];
+
+        while let Some(dir) = queue.pop() {
+            let url = if dir.is_empty() {
+                self.base.clone()
+            } else {
+                format!("{}/", self.url(&dir))
+            };
+            // A missing collection must not look like an empty one, or every note would be deleted
+            let entries = self
+                .propfind(&url, "1")?
+                .ok_or_else(|| anyhow!("{} was not found on the WebDAV server", url))?;
+            for entry in entries {
+                // The collection itself is listed first
+                if entry.key == dir || entry.key.is_empty() || is_ignored(&entry.key) {
+                    continue;
+                }
+                if entry.is_collection {
+                    if dirs.insert(entry.key.clone()) {
+                        queue.push(entry.key);
+                    }
+                } else {
+                    files.insert(entry.key, RemoteFile { etag: entry.etag });
+                }
+            }
+        }
+        Ok((files, dirs))
+    }
+
+    /// Creates the collections leading up to `key`
+    fn ensure_parents(&self, key: &str, dirs: &mut HashSet<String>) -> Result<()> {
+        let parts: Vec<&str> = key.split('/').collect();
+        for depth in 1..parts.len() {
+            let dir = parts[..depth].join("/");
+            if dirs.contains(&dir) {
+                continue;
+            }
+            match reply(
+                self.request("MKCOL", &format!("{}/", self.url(&dir)))
+                    .call(),
+            )? {
+                // 405 means the collection already exists
+                Reply::Ok(_) | Reply::Status(405) => {
+                    dirs.insert(dir);
+                }
+                Reply::Status(code) => return Err(anyhow!("MKCOL {} returned {}", dir, code)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Current ETag of a file, or None if it does not exist
+    fn head(&self, key: &str) -> Result<Option<String>> {
+        match reply(self.request("HEAD", &self.url(key)).call())? {
+            Reply::Ok(response) => Ok(Some(response.header("ETag").unwrap_or("").to_string())),
+            Reply::Status(404) => Ok(None),
+            Reply::Status(code) => Err(anyhow!("HEAD {} returned {}", key, code)),
+        }
+    }
+
+    /// Size of a file as reported by the server, or None if it does not exist
+    fn size(&self, key: &str) -> Result<Option<u64>> {
+        Ok(self.reported_size(key)?.map(|size| size.unwrap_or(0)))
+    }
+
+    /// Like `size`, but keeps apart files whose size the server does not report
+    fn reported_size(&self, key: &str) -> Result<Option<Option<u64>>> {
+        let entries = self.propfind(&self.url(key), "0")?;
+        Ok(entries
+            .and_then(|entries| entries.into_iter().next())
+            .map(|entry| entry.size))
+    }
+
+    /// True unless the server reports a different size for the uploaded part
+    fn part_complete(&self, part_key: &str, expected: usize) -> Result<bool> {
+        match self.reported_size(part_key)? {
+            Some(Some(size)) => Ok(size == expected as u64),
+            Some(None) => Ok(true),
+            None => Ok(false),
+        }
+    }
+
+    fn download(&self, key: &str) -> Result<(Vec<u8>, String)> {
+        match reply(self.request("GET", &self.url(key)).call())? {
+            Reply::Ok(response) => {
+                let etag = response.header("ETag").unwrap_or("").to_string();
+                let mut data = Vec::new();
+                response.into_reader().read_to_end(&mut data)?;
+                Ok((data, etag))
+            }
+            Reply::Status(code) => Err(anyhow!("GET {} returned {}", key, code)),
+        }
+    }
+
+    fn put(&self, key: &str, data: &[u8], range: Option<(usize, usize)>) -> Result<Reply> {
+        let mut request = self.request("PUT", &self.url(key));
+        if let Some((start, end)) = range {
+            request = request.set(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", start, end - 1, data.len()),
+            );
+        }
+        let body = match range {
+            Some((start, end)) => &data[start..end],
+            None => data,
+        };
+        reply(request.send_bytes(body))
+    }
+
+    /// Uploads `data` in one request
+    fn put_whole(&self, part_key: &str, data: &[u8]) -> Result<()> {
+        match self.put(part_key, data, None)? {
+            Reply::Ok(_) => Ok(()),
+            Reply::Status(code) => Err(anyhow!("PUT {} returned {}", part_key, code)),
+        }
+    }
+
+    /// Uploads `data` to a hidden part file, continuing a previous attempt when `resume` is set.
+    /// The part is only accepted once the server reports it at its full size.
+    fn upload_part(&self, part_key: &str, data: &[u8], resume: bool) -> Result<()> {
+        if data.len() > CHUNK_SIZE {
+            self.upload_chunks(part_key, data, resume)?;
+        } else {
+            self.put_whole(part_key, data)?;
+        }
+        if self.part_complete(part_key, data.len())? {
+            return Ok(());
+        }
+
+        // Servers that ignore Content-Range keep only the last chunk, so send it all again
+        let _ = self.request("DELETE", &self.url(part_key)).call();
+        self.put_whole(part_key, data)?;
+        if self.part_complete(part_key, data.len())? {
+            Ok(())
+        } else {
+            let _ = self.request("DELETE", &self.url(part_key)).call();
+            Err(anyhow!(
+                "The server stored an incomplete copy of {}",
+                part_key
+            ))
+        }
+    }
+
+    /// Sends `data` in ranges, starting after what a previous attempt already uploaded
+    fn upload_chunks(&self, part_key: &str, data: &[u8], resume: bool) -> Result<()> {
+        let mut offset = match self.size(part_key)? {
+            Some(size) if resume && (size as usize) <= data.len() => size as usize,
+            Some(_) => {
+                // A stale part from different content; start over
+                let _ = self.request("DELETE", &self.url(part_key)).call();
+                0
+            }
+            None => 0,
+        };
+        while offset < data.len() {
+            let end = (offset + CHUNK_SIZE).min(data.len());
+            match self.put(part_key, data, Some((offset, end)))? {
+                Reply::Ok(_) => offset = end,
+                // The server does not support ranged PUT, so send everything at once
+                Reply::Status(400 | 405 | 501) if offset == 0 => {
+                    return self.put_whole(part_key, data);
+                }
+                Reply::Status(code) => return Err(anyhow!("PUT {} returned {}", part_key, code)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves a finished part file over the real file and returns the new ETag
+    fn commit_part(&self, part_key: &str, key: &str) -> Result<String> {
+        let request = self
+            .request("MOVE", &self.url(part_key))
+            .set("Destination", &self.url(key))
+            .set("Overwrite", "T");
+        match reply(request.call())? {
+            Reply::Ok(_) => {}
+            Reply::Status(code) => return Err(anyhow!("MOVE {} returned {}", key, code)),
+        }
+        match self.head(key)? {
+            Some(etag) => Ok(etag),
+            None => Err(anyhow!("{} is missing after upload", key)),
+        }
+    }
+
+    fn delete(&self, key: &str, etag: &str) -> Result<()> {
+        let mut request = self.request("DELETE", &self.url(key));
+        if !etag.is_empty() {
+            request = request.set("If-Match", etag);
+        }
+        match reply(request.call())? {
+            Reply::Ok(_) | Reply::Status(404) => Ok(()),
+            Reply::Status(412) => Err(RemoteChanged.into()),
+            Reply::Status(code) => Err(anyhow!("DELETE {} returned {}", key, code)),
+        }
+    }
+}
+
+/// Hidden part file used while uploading `key`
+fn part_key(key: &str) -> String {
+    match key.rsplit_once('/') {
+        Some((dir, name)) => format!("{}/.{}{}", dir, name, PARTIAL_SUFFIX),
+        None => format!(".{}{}", key, PARTIAL_SUFFIX),
+    }
+}
+
+/// Mirrors the notes root and a WebDAV collection in both directions
+pub struct WebDavSync<'a> {
+    client: WebDavClient,
+    root: &'a Path,
+    manifest_path: &'a Path,
+    vault: Option<&'a VaultKey>,
+    manifest: WebDavManifest,
+    remote_dirs: HashSet<String>,
+    report: SyncReport,
+}
+
+impl<'a> WebDavSync<'a> {
+    /// With `vault` set, notes downloaded into the notes root are sealed
+    pub fn new(
+        config: &WebDavConfig,
+        root: &'a Path,
+        manifest_path: &'a Path,
+        vault: Option<&'a VaultKey>,
+    ) -> Result<Self> {
+        let mut manifest: WebDavManifest = load_state(manifest_path)?;
+        // A different server means the old manifest says nothing about what is there
+        if manifest.url != config.url {
+            manifest = WebDavManifest {
+                url: config.url.clone(),
+                ..WebDavManifest::default()
+            };
+        }
+        Ok(Self {
+            client: WebDavClient::new(config)?,
+            root,
+            manifest_path,
+            vault,
+            manifest,
+            remote_dirs: HashSet::new(),
+            report: SyncReport::default(),
+        })
+    }
+
+    /// Saves the manifest after every change so an interrupted sync resumes where it stopped
+    fn save_manifest(&self) -> Result<()> {
+        save_state(self.manifest_path, &self.manifest)
+    }
+
+    fn record(&mut self, key: &str, hash: String, etag: String) -> Result<()> {
+        self.manifest
+            .files
+            .insert(key.to_string(), SyncedFile { hash, etag });
+        self.save_manifest()
+    }
+
+    fn forget(&mut self, key: &str) -> Result<()> {
+        self.manifest.files.remove(key);
+        self.save_manifest()
+    }
+
+    /// Uploads a local file, checking that the server copy still has `expected_etag`
+    fn upload(&mut self, key: &str, expected_etag: Option<&str>) -> Result<()> {
+        let path = local_path(self.root, key).ok_or_else(|| anyhow!("Invalid path: {}", key))?;
+        let data = std::fs::read(&path)?;
+        let hash = content_hash(&data);
+
+        let current = self.client.head(key)?;
+        if current.as_deref() != expected_etag {
+            return Err(RemoteChanged.into());
+        }
+
+        self.client.ensure_parents(key, &mut self.remote_dirs)?;
+        let part = part_key(key);
+        let resume = self.manifest.pending.get(key) == Some(&hash);
+        self.manifest.pending.insert(key.to_string(), hash.clone());
+        self.save_manifest()?;
+
+        self.client.upload_part(&part, &data, resume)?;
+        let etag = self.client.commit_part(&part, key)?;
+        self.manifest.pending.remove(key);
+        self.record(key, hash, etag)?;
+        self.report.uploaded.push(key.to_string());
+        Ok(())
+    }
+
+    /// Stores the server copy locally, using `fetched` if it was already downloaded
+    fn download(&mut self, key: &str, fetched: Option<(Vec<u8>, String)>) -> Result<()> {
+        let (data, etag) = match fetched {
+            Some(fetched) => fetched,
+            None => self.client.download(key)?,
+        };
+        let path = local_path(self.root, key).ok_or_else(|| anyhow!("Invalid path: {}", key))?;
+        let hash = content_hash(&data);
+        let stored = seal_for(self.vault, data)?;
+        write_atomic(&path, &stored)?;
+        self.report.downloaded.push(key.to_string());
+        if content_hash(&stored) == hash {
+            self.record(key, hash, etag)
+        } else {
+            // The vault only keeps sealed notes, so the server gets the sealed copy as well
+            self.upload(key, Some(&etag))
+        }
+    }
+
+    /// Both sides changed: keep the server version as a conflict copy and upload ours
+    fn resolve_conflict(
+        &mut self,
+        key: &str,
+        local: &LocalFile,
+        remote: &RemoteFile,
+        base: Option<&SyncedFile>,
+        fetched: Option<(Vec<u8>, String)>,
+    ) -> Result<()> {
+        let (data, etag) = match fetched {
+            Some(fetched) => fetched,
+            None => self.client.download(key)?,
+        };
+        let hash = content_hash(&data);
+        if hash == local.hash {
+            // Both sides made the same change
+            return self.record(key, local.hash.clone(), etag);
+        }
+        if base.is_some_and(|base| base.hash == hash) {
+            // Only the ETag changed on the server, so ours is simply newer
+            return self.upload(key, Some(&etag));
+        }
+
+        let copy_key = conflict_copy_key(key, "server");
+        let copy_path =
+            local_path(self.root, &copy_key).ok_or_else(|| anyhow!("Invalid path: {}", key))?;
+        write_atomic(&copy_path, &seal_for(self.vault, data)?)?;
+        self.report.conflicts.push(copy_key.clone());
+
+        let expected = if remote.etag.is_empty() {
+            etag
+        } else {
+            remote.etag.clone()
+        };
+        self.upload(key, Some(&expected))?;
+        self.upload(&copy_key, None)
+    }
+
+    /// Decides what to do with one path given both sides and the last synced state
+    fn sync_file(
+        &mut self,
+        key: &str,
+        local: Option<&LocalFile>,
+        remote: Option<&RemoteFile>,
+    ) -> Result<Option<&'static str>> {
+        let base = self.manifest.files.get(key).cloned();
+        let local_changed = local.map(|file| &file.hash) != base.as_ref().map(|base| &base.hash);
+        // Without ETags the server copy is downloaded and compared by content
+        let mut fetched = None;
+        let remote_changed = match (remote, &base) {
+            (Some(remote), Some(base)) if remote.etag.is_empty() => {
+                let (data, etag) = self.client.download(key)?;
+                let changed = content_hash(&data) != base.hash;
+                fetched = Some((data, etag));
+                changed
+            }
+            (Some(remote), Some(base)) => remote.etag != base.etag,
+            (None, None) => false,
+            _ => true,
+        };
+
+        match (local, remote) {
+            (None, None) => {
+                self.forget(key)?;
+                Ok(None)
+            }
+            (Some(_), None) if base.is_none() || !remote_changed => {
+                self.upload(key, None)?;
+                Ok(Some("uploading"))
+            }
+            (Some(_), None) if !local_changed => {
+                // Deleted on the server and untouched here
+                let path = local_path(self.root, key).ok_or_else(|| anyhow!("Invalid path"))?;
+                std::fs::remove_file(path)?;
+                self.forget(key)?;
+                self.report.deleted_local.push(key.to_string());
+                Ok(Some("deleting"))
+            }
+            (Some(_), None) => {
+                // Deleted on the server but edited here; keep the edit
+                self.upload(key, None)?;
+                Ok(Some("uploading"))
+            }
+            (None, Some(remote)) if base.is_some() && !remote_changed => {
+                self.client.delete(key, &remote.etag)?;
+                self.forget(key)?;
+                self.report.deleted_remote.push(key.to_string());
+                Ok(Some("deleting"))
+            }
+            (None, Some(_)) => {
+                self.download(key, fetched)?;
+                Ok(Some("downloading"))
+            }
+            (Some(_), Some(_)) if !local_changed && !remote_changed => Ok(None),
+            (Some(_), Some(remote)) if !remote_changed => {
+                self.upload(key, Some(&remote.etag))?;
+                Ok(Some("uploading"))
+            }
+            (Some(_), Some(_)) if !local_changed => {
+                self.download(key, fetched)?;
+                Ok(Some("downloading"))
+            }
+            (Some(local), Some(remote)) => {
+                self.resolve_conflict(key, local, remote, base.as_ref(), fetched)?;
+                Ok(Some("conflict"))
+            }
+        }
+    }
+
+    /// Runs a full two-way sync, reporting progress through `on_event`
+    pub fn run(mut self, on_event: &mut dyn FnMut(SyncEvent)) -> Result<SyncReport> {
+        on_event(SyncEvent::new("scanning", None, 0, 0));
+        let local = scan_local(self.root)?;
+        let (remote, dirs) = self.client.list()?;
+        self.remote_dirs = dirs;
+
+        let keys: BTreeSet<String> = local
+            .keys()
+            .chain(remote.keys())
+            .chain(self.manifest.files.keys())
+            .cloned()
+            .collect();
+        let total = keys.len();
+
+        for (index, key) in keys.iter().enumerate() {
+            match self.sync_file(key, local.get(key), remote.get(key)) {
+                Ok(Some(phase)) => on_event(SyncEvent::new(phase, Some(key), index + 1, total)),
+                Ok(None) => {}
+                Err(e) => {
+                    let message = if e.is::<RemoteChanged>() {
+                        "Changed on the server during sync; will retry next time".to_string()
+                    } else {
+                        e.to_string()
+                    };
+                    on_event(
+                        SyncEvent::new("error", Some(key), index + 1, total)
+                            .with_message(message.clone()),
+                    );
+                    self.report.errors.push((key.clone(), message));
+                }
+            }
+        }
+
+        self.save_manifest()?;
+        on_event(SyncEvent::new("done", None, total, total));
+        Ok(self.report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tiny_http::{Header, Response, Server};
+
+    /// How the stand-in server treats a PUT with Content-Range
+    #[derive(Clone, Copy, PartialEq)]
+    enum Ranges {
+        Supported,
+        Rejected,
+        /// Stores the chunk as the whole file, like servers that do not look at the header
+        Ignored,
+    }
+
+    /// A minimal WebDAV server backed by a temporary folder
+    struct Dav {
+        url: String,
+        root: PathBuf,
+        received: Arc<AtomicUsize>,
+        fail_chunk: Arc<AtomicBool>,
+    }
+
+    fn decode(s: &str) -> String {
+        let bytes = s.as_bytes();
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                out.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    fn etag_of(path: &Path) -> String {
+        format!("\"{}\"", content_hash(&std::fs::read(path).unwrap()))
+    }
+
+    fn header(name: &str, value: &str) -> Header {
+        Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+    }
+
+    fn start(ranges: Ranges, etags: bool) -> Dav {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let root = tempdir("server");
+        let received = Arc::new(AtomicUsize::new(0));
+        let fail_chunk = Arc::new(AtomicBool::new(false));
+        let (dir, counter, fail) = (root.clone(), received.clone(), fail_chunk.clone());
+        let base = format!("http://127.0.0.1:{}/dav/", port);
+        let prefix = base.clone();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let url = request.url().to_string();
+                let relative = decode(
+                    url.strip_prefix("/dav/")
+                        .unwrap_or("")
+                        .trim_end_matches('/'),
+                );
+                let path = if relative.is_empty() {
+                    dir.clone()
+                } else {
+                    dir.join(&relative)
+                };
+                let get = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|header| header.field.equiv(name))
+                        .map(|header| header.value.to_string())
+                };
+                let (if_match, range, destination) =
+                    (get("If-Match"), get("Content-Range"), get("Destination"));
+                if get("Authorization").as_deref() != Some("Basic dXNlcjpzZWNyZXQ=") {
+                    let _ = request.respond(Response::empty(401));
+                    continue;
+                }
+                let status = |code: u16| Response::from_string("").with_status_code(code);
+                let response = match request.method().to_string().as_str() {
+                    "PROPFIND" if !path.exists() => status(404),
+                    "PROPFIND" => {
+                        let depth0 = get("Depth").as_deref() == Some("0");
+                        let mut body =
+                            String::from(r#"<?xml version="1.0"?><D:multistatus xmlns:D="DAV:">"#);
+                        let mut push = |href: String, path: &Path| {
+                            let props = if path.is_dir() {
+                                "<D:resourcetype><D:collection/></D:resourcetype>".to_string()
+                            } else {
+                                let etag = if etags {
+                                    format!("<D:getetag>{}</D:getetag>", etag_of(path))
+                                } else {
+                                    String::new()
+                                };
+                                format!(
+                                    "<D:resourcetype/>{}<D:getcontentlength>{}</D:getcontentlength>",
+                                    etag,
+                                    std::fs::metadata(path).unwrap().len()
+                                )
+                            };
+                            body.push_str(&format!(
+                                "<D:response><D:href>{}</D:href><D:propstat><D:prop>{}</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>",
+                                href, props
+                            ));
+                        };
+                        push(url.clone(), &path);
+                        let children = std::fs::read_dir(&path).into_iter().flatten();
+                        for entry in children.filter(|_| !depth0) {
+                            let entry = entry.unwrap();
+                            let name = entry.file_name().to_string_lossy().to_string();
+                            let child = if relative.is_empty() {
+                                name
+                            } else {
+                                format!("{}/{}", relative, name)
+                            };
+                            let slash = if entry.path().is_dir() { "/" } else { "" };
+                            let href = url::Url::parse(&prefix).unwrap().join(&child).unwrap();
+                            push(format!("{}{}", href, slash), &entry.path());
+                        }
+                        body.push_str("</D:multistatus>");
+                        Response::from_string(body).with_status_code(207)
+                    }
+                    "GET" | "HEAD" if path.is_file() => {
+                        let response = Response::from_data(std::fs::read(&path).unwrap());
+                        if etags {
+                            response.with_header(header("ETag", &etag_of(&path)))
+                        } else {
+                            response
+                        }
+                    }
+                    "GET" | "HEAD" => status(404),
+                    "PUT" => {
+                        let mut data = Vec::new();
+                        request.as_reader().read_to_end(&mut data).unwrap();
+                        match range {
+                            Some(_) if ranges == Ranges::Rejected => status(501),
+                            Some(range) if ranges == Ranges::Supported => {
+                                let start: usize =
+                                    range[6..].split('-').next().unwrap().parse().unwrap();
+                                if start > 0 && fail.swap(false, Ordering::SeqCst) {
+                                    status(500)
+                                } else {
+                                    counter.fetch_add(data.len(), Ordering::SeqCst);
+                                    let mut existing = std::fs::read(&path).unwrap_or_default();
+                                    existing.truncate(start);
+                                    existing.extend_from_slice(&data);
+                                    std::fs::write(&path, existing).unwrap();
+                                    status(204)
+                                }
+                            }
+                            _ => {
+                                counter.fetch_add(data.len(), Ordering::SeqCst);
+                                std::fs::write(&path, data).unwrap();
+                                status(201)
+                            }
+                        }
+                    }
+                    "DELETE" if path.is_file() && if_match.is_some_and(|m| m != etag_of(&path)) => {
+                        status(412)
+                    }
+                    "DELETE" => {
+                        if path.is_dir() {
+                            std::fs::remove_dir_all(&path).unwrap();
+                        } else {
+                            let _ = std::fs::remove_file(&path);
+                        }
+                        status(204)
+                    }
+                    "MKCOL" if path.exists() => status(405),
+                    "MKCOL" => {
+                        std::fs::create_dir(&path).unwrap();
+                        status(201)
+                    }
+                    "MOVE" => {
+                        let target = decode(destination.unwrap().split("/dav/").nth(1).unwrap());
+                        std::fs::rename(&path, dir.join(target)).unwrap();
+                        status(201)
+                    }
+                    _ => status(405),
+                };
+                let _ = request.respond(response);
+            }
+        });
+        Dav {
+            url: base,
+            root,
+            received,
+            fail_chunk,
+        }
+    }
+
+    fn tempdir(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "quicknote-webdav-{}-{}-{}",
+            std::process::id(),
+            name,
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config(dav: &Dav, password: &str) -> WebDavConfig {
+        WebDavConfig {
+            url: dav.url.clone(),
+            username: Some("user".into()),
+            password: Some(password.into()),
+        }
+    }
+
+    fn sync(dav: &Dav, local: &Path, manifest: &Path) -> SyncReport {
+        let mut last = None;
+        let report = WebDavSync::new(&config(dav, "secret"), local, manifest, None)
+            .unwrap()
+            .run(&mut |event| last = Some(event.phase))
+            .unwrap();
+        assert_eq!(last.as_deref(), Some("done"));
+        report
+    }
+
+    fn read(path: PathBuf) -> String {
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn syncs_both_ways() {
+        let dav = start(Ranges::Supported, true);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        std::fs::write(local.join("a.md"), "a").unwrap();
+        std::fs::create_dir_all(local.join("sub dir")).unwrap();
+        std::fs::write(local.join("sub dir/b é.md"), "b").unwrap();
+        std::fs::write(dav.root.join("c.md"), "c").unwrap();
+
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.uploaded.len(), 2, "{:?}", report);
+        assert_eq!(report.downloaded, vec!["c.md"]);
+        assert_eq!(read(dav.root.join("sub dir/b é.md")), "b");
+        assert_eq!(read(local.join("c.md")), "c");
+
+        let report = sync(&dav, &local, &manifest);
+        assert!(report.uploaded.is_empty() && report.downloaded.is_empty());
+
+        std::fs::write(local.join("a.md"), "a2").unwrap();
+        std::fs::write(dav.root.join("c.md"), "c2").unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.uploaded, vec!["a.md"]);
+        assert_eq!(report.downloaded, vec!["c.md"]);
+
+        std::fs::remove_file(local.join("a.md")).unwrap();
+        std::fs::remove_file(dav.root.join("sub dir/b é.md")).unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.deleted_remote, vec!["a.md"]);
+        assert_eq!(report.deleted_local, vec!["sub dir/b é.md"]);
+        assert!(!dav.root.join("a.md").exists());
+        assert!(!local.join("sub dir/b é.md").exists());
+    }
+
+    #[test]
+    fn keeps_a_conflict_copy() {
+        let dav = start(Ranges::Supported, true);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        std::fs::write(local.join("c.md"), "c").unwrap();
+        sync(&dav, &local, &manifest);
+
+        std::fs::write(local.join("c.md"), "local edit").unwrap();
+        std::fs::write(dav.root.join("c.md"), "server edit").unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.conflicts.len(), 1, "{:?}", report);
+        let copy = &report.conflicts[0];
+        assert!(copy.starts_with("c (conflict server "));
+        assert_eq!(read(local.join(copy)), "server edit");
+        assert_eq!(read(dav.root.join(copy)), "server edit");
+        assert_eq!(read(dav.root.join("c.md")), "local edit");
+
+        let report = sync(&dav, &local, &manifest);
+        assert!(report.uploaded.is_empty() && report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn compares_content_without_etags() {
+        let dav = start(Ranges::Supported, false);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        std::fs::write(local.join("a.md"), "a").unwrap();
+        std::fs::write(local.join("b.md"), "b").unwrap();
+        sync(&dav, &local, &manifest);
+
+        // A local edit is uploaded without a spurious conflict copy
+        std::fs::write(local.join("a.md"), "a2").unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.uploaded, vec!["a.md"], "{:?}", report);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(read(dav.root.join("a.md")), "a2");
+
+        // A local deletion reaches the server
+        std::fs::remove_file(local.join("b.md")).unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.deleted_remote, vec!["b.md"], "{:?}", report);
+        assert!(!dav.root.join("b.md").exists());
+
+        // A server edit is still picked up
+        std::fs::write(dav.root.join("a.md"), "a3").unwrap();
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.downloaded, vec!["a.md"], "{:?}", report);
+        assert_eq!(read(local.join("a.md")), "a3");
+    }
+
+    #[test]
+    fn resumes_an_interrupted_upload() {
+        let dav = start(Ranges::Supported, true);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        let big: Vec<u8> = (0..3_500_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(local.join("big.bin"), &big).unwrap();
+        dav.fail_chunk.store(true, Ordering::SeqCst);
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.errors.len(), 1, "{:?}", report);
+        assert_eq!(dav.received.load(Ordering::SeqCst), CHUNK_SIZE);
+
+        let report = sync(&dav, &local, &manifest);
+        assert_eq!(report.uploaded, vec!["big.bin"], "{:?}", report);
+        assert_eq!(dav.received.load(Ordering::SeqCst), big.len());
+        assert_eq!(std::fs::read(dav.root.join("big.bin")).unwrap(), big);
+        assert!(!dav.root.join(".big.bin.quicknote-part").exists());
+    }
+
+    #[test]
+    fn uploads_whole_files_when_ranges_fail() {
+        let big = vec![7u8; 2_500_000];
+        for ranges in [Ranges::Rejected, Ranges::Ignored] {
+            let dav = start(ranges, true);
+            let local = tempdir("local");
+            let manifest = tempdir("state").join("manifest.json");
+            std::fs::write(local.join("big.bin"), &big).unwrap();
+            let report = sync(&dav, &local, &manifest);
+            assert_eq!(report.uploaded, vec!["big.bin"], "{:?}", report);
+            assert_eq!(std::fs::read(dav.root.join("big.bin")).unwrap(), big);
+        }
+    }
+
+    #[test]
+    fn seals_downloads_into_a_vault() {
+        let dav = start(Ranges::Supported, true);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        let vault = VaultKey::generate();
+        std::fs::write(dav.root.join("a.md"), "secret").unwrap();
+        let run = || {
+            WebDavSync::new(&config(&dav, "secret"), &local, &manifest, Some(&vault))
+                .unwrap()
+                .run(&mut |_| {})
+                .unwrap()
+        };
+
+        let report = run();
+        assert_eq!(report.downloaded, vec!["a.md"], "{:?}", report);
+        let stored = std::fs::read(local.join("a.md")).unwrap();
+        assert!(crate::crypto::is_sealed(&stored));
+        assert_eq!(std::fs::read(dav.root.join("a.md")).unwrap(), stored);
+        assert_eq!(
+            crate::vault::read_file(&local.join("a.md"), &vault).unwrap(),
+            "secret"
+        );
+
+        let report = run();
+        assert!(report.uploaded.is_empty() && report.downloaded.is_empty());
+    }
+
+    #[test]
+    fn reports_bad_credentials() {
+        let dav = start(Ranges::Supported, true);
+        let local = tempdir("local");
+        let manifest = tempdir("state").join("manifest.json");
+        let error = WebDavSync::new(&config(&dav, "wrong"), &local, &manifest, None)
+            .unwrap()
+            .run(&mut |_| {})
+            .unwrap_err();
+        assert!(error.to_string().contains("credentials"), "{}", error);
+    }
+}
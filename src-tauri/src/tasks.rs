@@ -0,0 +1,345 @@
+use crate::crypto::VaultKey;
+use crate::links;
+use crate::search;
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Hour used for due dates written without a time
+const DEFAULT_DUE_HOUR: u32 = 9;
+
+/// Format due dates are written back in
+const DUE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+lazy_static! {
+    // "- [ ] text", "* [x] text" or "1. [ ] text", keeping the prefix so it can be rewritten
+    static ref TASK_RE: Regex = Regex::new(r"^(\s*(?:[-*+]|\d+[.)])\s+\[)([ xX])\](.*)$").unwrap();
+    // "@2026-10-20", "@2026-10-20 14:00" or "@2026-10-20T14:00"
+    static ref DUE_RE: Regex =
+        Regex::new(r"(^|\s)@(\d{4}-\d{2}-\d{2})(?:[ T](\d{1,2}:\d{2}))?(\s|$)").unwrap();
+    // "#tag" or "#area/sub-tag", but not "#1"
+    static ref TAG_RE: Regex = Regex::new(r"(?:^|\s)#([\w/-]*[^\W\d][\w/-]*)").unwrap();
+}
+
+/// A Markdown checkbox item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaskLine {
+    /// 1-based line number
+    pub line: usize,
+    pub done: bool,
+    /// The item's text without the checkbox and due date
+    pub text: String,
+    /// Local time, sent to the webview as "YYYY-MM-DD HH:MM"
+    #[serde(serialize_with = "serialize_due")]
+    pub due: Option<NaiveDateTime>,
+    /// Tags written as "#tag" in the text, without the "#"
+    pub tags: Vec<String>,
+    /// The whole line as written, used to check it is unchanged before editing it
+    pub raw: String,
+}
+
+fn serialize_due<S: serde::Serializer>(
+    due: &Option<NaiveDateTime>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    match due {
+        Some(due) => serializer.serialize_some(&due.format(DUE_FORMAT).to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn parse_due(caps: &regex::Captures) -> Option<NaiveDateTime> {
+    let date = NaiveDate::parse_from_str(&caps[2], "%Y-%m-%d").ok()?;
+    let time = match caps.get(3) {
+        Some(time) => NaiveTime::parse_from_str(time.as_str(), "%H:%M").ok()?,
+        None => NaiveTime::from_hms_opt(DEFAULT_DUE_HOUR, 0, 0)?,
+    };
+    Some(date.and_time(time))
+}
+
+/// Parses one line, returning None if it is not a checkbox item
+pub fn parse_task_line(line: &str, number: usize) -> Option<TaskLine> {
+    let caps = TASK_RE.captures(line)?;
+    let body = &caps[3];
+    let (due, text) = match DUE_RE.captures(body) {
+        Some(due_caps) => {
+            let whole = due_caps.get(0).unwrap();
+            let text = format!("{} {}", &body[..whole.start()], &body[whole.end()..]);
+            (parse_due(&due_caps), text)
+        }
+        None => (None, body.to_string()),
+    };
+    let mut tags: Vec<String> = Vec::new();
+    for tag in TAG_RE.captures_iter(body) {
+        if !tags.contains(&tag[1].to_string()) {
+            tags.push(tag[1].to_string());
+        }
+    }
+    Some(TaskLine {
+        line: number,
+        done: &caps[2] != " ",
+        text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+        due,
+        tags,
+        raw: line.to_string(),
+    })
+}
+
+/// Collects the checkbox items of a note
+pub fn parse_tasks(content: &str) -> Vec<TaskLine> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| parse_task_line(line, index + 1))
+        .collect()
+}
+
+/// Collects the checkbox items of every note under `root` that has any.
+/// Notes with their own passphrase are skipped, as are vault notes unless `vault` is given.
+pub fn scan_notes(root: &Path, vault: Option<&VaultKey>) -> Vec<(PathBuf, Vec<TaskLine>)> {
+    links::collect_notes(root)
+        .into_iter()
+        .filter_map(|path| {
+            let content = search::read_indexable(&path, vault)?;
+            let tasks = parse_tasks(&content);
+            (!tasks.is_empty()).then_some((path, tasks))
+        })
+        .collect()
+}
+
+/// Ticks or unticks the checkbox of a task line
+pub fn set_done(line: &str, done: bool) -> Option<String> {
+    let caps = TASK_RE.captures(line)?;
+    let mark = if done { "x" } else { " " };
+    Some(format!("{}{}]{}", &caps[1], mark, &caps[3]))
+}
+
+/// Replaces the due date of a task line, or adds one at the end
+pub fn set_due(line: &str, due: NaiveDateTime) -> Option<String> {
+    let caps = TASK_RE.captures(line)?;
+    let body = &caps[3];
+    let stamp = format!("@{}", due.format(DUE_FORMAT));
+    let body = match DUE_RE.captures(body) {
+        Some(due_caps) => {
+            let whole = due_caps.get(0).unwrap();
+            format!(
+                "{}{}{}{}{}",
+                &body[..whole.start()],
+                &due_caps[1],
+                stamp,
+                &due_caps[4],
+                &body[whole.end()..]
+            )
+        }
+        None => format!("{} {}", body.trim_end(), stamp),
+    };
+    Some(format!("{}{}]{}", &caps[1], &caps[2], body))
+}
+
+/// Rewrites the task at `line` (1-based), which must still read `expected`.
+/// If lines were added or removed above it, the single line that reads `expected` is used instead.
+pub fn edit_task(
+    content: &str,
+    line: usize,
+    expected: &str,
+    edit: impl FnOnce(&str) -> Option<String>,
+) -> Result<String> {
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let text_of = |line: &str| line.trim_end_matches(['\r', '\n']).to_string();
+    let index = match lines.get(line.wrapping_sub(1)) {
+        Some(current) if text_of(current) == expected => line - 1,
+        _ => {
+            let matches: Vec<usize> = (0..lines.len())
+                .filter(|index| text_of(lines[*index]) == expected)
+                .collect();
+            match matches.as_slice() {
+                [index] => *index,
+                [] => return Err(anyhow!("The task was changed or removed")),
+                _ => return Err(anyhow!("The task appears more than once and has moved")),
+            }
+        }
+    };
+
+    let current = lines[index];
+    let text = text_of(current);
+    let ending = &current[text.len()..];
+    let edited = edit(&text).ok_or_else(|| anyhow!("Line {} is not a task", index + 1))?;
+    let replaced = format!("{}{}", edited, ending);
+    lines[index] = &replaced;
+    Ok(lines.concat())
+}
+
+/// Which tasks `list_tasks` returns
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    #[default]
+    All,
+    Open,
+    Done,
+}
+
+/// Filter for the task list; every field given must match
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TaskFilter {
+    pub status: TaskStatus,
+    /// Tag to look for, with or without the "#", ignoring case
+    pub tag: Option<String>,
+    /// Earliest due date, "YYYY-MM-DD" or "YYYY-MM-DD HH:MM"; tasks without one are left out
+    pub due_from: Option<String>,
+    /// Latest due date; a date alone includes the whole day
+    pub due_to: Option<String>,
+}
+
+/// Parses a due date bound; a date alone means its start, or its end when `end_of_day` is set
+pub fn parse_due_bound(value: &str, end_of_day: bool) -> Result<NaiveDateTime> {
+    let value = value.trim();
+    for format in [DUE_FORMAT, "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(time);
+        }
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date '{}', expected YYYY-MM-DD [HH:MM]", value))?;
+    let time = if end_of_day {
+        NaiveTime::from_hms_opt(23, 59, 59)
+    } else {
+        NaiveTime::from_hms_opt(0, 0, 0)
+    };
+    Ok(date.and_time(time.unwrap_or_default()))
+}
+
+/// A task filter with its dates parsed
+#[derive(Debug, Clone, Default)]
+pub struct TaskQuery {
+    status: TaskStatus,
+    tag: Option<String>,
+    due_from: Option<NaiveDateTime>,
+    due_to: Option<NaiveDateTime>,
+}
+
+impl TaskQuery {
+    pub fn new(filter: &TaskFilter) -> Result<Self> {
+        let bound = |value: &Option<String>, end_of_day| {
+            value
+                .as_deref()
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| parse_due_bound(value, end_of_day))
+                .transpose()
+        };
+        Ok(Self {
+            status: filter.status,
+            tag: filter
+                .tag
+                .as_deref()
+                .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+                .filter(|tag| !tag.is_empty()),
+            due_from: bound(&filter.due_from, false)?,
+            due_to: bound(&filter.due_to, true)?,
+        })
+    }
+
+    pub fn matches(&self, task: &TaskLine) -> bool {
+        let status = match self.status {
+            TaskStatus::All => true,
+            TaskStatus::Open => !task.done,
+            TaskStatus::Done => task.done,
+        };
+        let tag = self
+            .tag
+            .as_ref()
+            .is_none_or(|wanted| task.tags.iter().any(|tag| tag.to_lowercase() == *wanted));
+        let due = match (task.due, self.due_from.is_some() || self.due_to.is_some()) {
+            (_, false) => true,
+            (None, true) => false,
+            (Some(due), true) => {
+                self.due_from.is_none_or(|from| due >= from)
+                    && self.due_to.is_none_or(|to| due <= to)
+            }
+        };
+        status && tag && due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn due(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn tick(text: &str) -> Option<String> {
+        set_done(text, true)
+    }
+
+    #[test]
+    fn sets_the_checkbox() {
+        assert_eq!(
+            set_done("- [ ] a @2026-10-20 14:00", true).unwrap(),
+            "- [x] a @2026-10-20 14:00"
+        );
+        assert_eq!(set_done("  1. [X] a", false).unwrap(), "  1. [ ] a");
+        assert_eq!(set_done("- not a task", true), None);
+    }
+
+    #[test]
+    fn replaces_or_adds_the_due_date() {
+        let new_due = due(21, 8, 30);
+        assert_eq!(
+            set_due("- [ ] a @2026-10-20 14:00 b", new_due).unwrap(),
+            "- [ ] a @2026-10-21 08:30 b"
+        );
+        assert_eq!(
+            set_due("- [x] a @2026-10-20", new_due).unwrap(),
+            "- [x] a @2026-10-21 08:30"
+        );
+        assert_eq!(
+            set_due("- [ ] a ", new_due).unwrap(),
+            "- [ ] a @2026-10-21 08:30"
+        );
+        let line = set_due("- [ ] a", due(22, 9, 0)).unwrap();
+        assert_eq!(parse_task_line(&line, 1).unwrap().due, Some(due(22, 9, 0)));
+    }
+
+    #[test]
+    fn finds_a_task_that_moved() {
+        let content = "new line\nx\n- [ ] a\n";
+        assert_eq!(
+            edit_task(content, 2, "- [ ] a", tick).unwrap(),
+            "new line\nx\n- [x] a\n"
+        );
+        assert!(edit_task("- [ ] b\n", 1, "- [ ] a", tick).is_err());
+    }
+
+    #[test]
+    fn refuses_a_moved_task_that_appears_twice() {
+        let content = "- [ ] a\n- [ ] a\nz";
+        assert!(edit_task(content, 3, "- [ ] a", tick).is_err());
+        // Still at its line, so there is no doubt which one is meant
+        assert_eq!(
+            edit_task(content, 2, "- [ ] a", tick).unwrap(),
+            "- [ ] a\n- [x] a\nz"
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let content = "x\r\n- [ ] a\r\n- [ ] b";
+        assert_eq!(
+            edit_task(content, 2, "- [ ] a", tick).unwrap(),
+            "x\r\n- [x] a\r\n- [ ] b"
+        );
+        assert_eq!(
+            edit_task(content, 3, "- [ ] b", |text| set_due(text, due(20, 9, 0))).unwrap(),
+            "x\r\n- [ ] a\r\n- [ ] b @2026-10-20 09:00"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// File extensions recognised as note templates
+const TEMPLATE_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// Marker used internally to locate {{cursor}} after expansion
+const CURSOR_MARKER: char = '\u{E000}';
+
+lazy_static! {
+    // {{name}} or {{name:format}}
+    static ref PLACEHOLDER_RE: Regex = Regex::new(r"\{\{\s*(\w+)(?::([^}]*))?\s*\}\}").unwrap();
+}
+
+/// Values available to placeholders during expansion
+#[derive(Debug, Clone)]
+pub struct TemplateContext {
+    pub now: DateTime<Local>,
+    pub title: String,
+    pub clipboard: Option<String>,
+}
+
+impl TemplateContext {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            now: Local::now(),
+            title: title.into(),
+            clipboard: None,
+        }
+    }
+}
+
+/// Result of expanding a template
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpandedTemplate {
+    pub title: String,
+    pub content: String,
+    /// Cursor position in UTF-16 code units, matching JavaScript string indices
+    pub cursor: Option<usize>,
+}
+
+/// A template file available in the templates folder
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: String,
+}
+
+/// Expands `{{date}}`, `{{time}}`, `{{clipboard}}`, `{{cursor}}` and `{{title}}` placeholders
+pub fn expand_template(template: &str, context: &TemplateContext) -> ExpandedTemplate {
+    let mut cursor_seen = false;
+    let expanded = PLACEHOLDER_RE.replace_all(template, |caps: &Captures| {
+        let format = caps.get(2).map(|m| m.as_str().trim());
+        match caps[1].to_lowercase().as_str() {
+            "date" => format_time(&context.now, format.unwrap_or("%Y-%m-%d"), &caps[0]),
+            "time" => format_time(&context.now, format.unwrap_or("%H:%M"), &caps[0]),
+            "datetime" => format_time(&context.now, format.unwrap_or("%Y-%m-%d %H:%M"), &caps[0]),
+            "title" => context.title.clone(),
+            "clipboard" => context.clipboard.clone().unwrap_or_default(),
+            // Only the first {{cursor}} is kept, later ones are dropped
+            "cursor" if !cursor_seen => {
+                cursor_seen = true;
+                CURSOR_MARKER.to_string()
+            }
+            "cursor" => String::new(),
+            // Leave unknown placeholders untouched
+            _ => caps[0].to_string(),
+        }
+    });
+
+    let (content, cursor) = match expanded.find(CURSOR_MARKER) {
+        Some(index) => {
+            let cursor = expanded[..index].encode_utf16().count();
+            (expanded.replacen(CURSOR_MARKER, "", 1), Some(cursor))
+        }
+        None => (expanded.into_owned(), None),
+    };
+
+    ExpandedTemplate {
+        title: context.title.clone(),
+        content,
+        cursor,
+    }
+}
+
+/// Formats a timestamp, keeping the placeholder as-is if the format is invalid
+fn format_time(now: &DateTime<Local>, format: &str, placeholder: &str) -> String {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return placeholder.to_string();
+    }
+    now.format(format).to_string()
+}
+
+/// Returns true if the template uses the given placeholder
+pub fn uses_placeholder(template: &str, name: &str) -> bool {
+    PLACEHOLDER_RE
+        .captures_iter(template)
+        .any(|caps| caps[1].eq_ignore_ascii_case(name))
+}
+
+/// Lists the templates stored in `dir`, sorted by name
+pub fn list_templates(dir: &Path) -> Vec<TemplateInfo> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut templates: Vec<TemplateInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_template_file(path))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            Some(TemplateInfo {
+                name,
+                path: path.to_string_lossy().to_string(),
+            })
+        })
+        .collect();
+    templates.sort_by_key(|template| template.name.to_lowercase());
+    templates
+}
+
+/// Finds a template by name in `dir`
+pub fn find_template(dir: &Path, name: &str) -> Option<PathBuf> {
+    list_templates(dir)
+        .into_iter()
+        .find(|template| template.name == name)
+        .map(|template| PathBuf::from(template.path))
+}
+
+fn is_template_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| TEMPLATE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
@@ -0,0 +1,131 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use url::Url;
+
+pub const SCHEME: &str = "quicknote";
+
+/// Longest text accepted in a `new` URL
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+
+/// What a quicknote:// URL asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlAction {
+    /// `quicknote://new?text=...&title=...`, saved to `path` when one is given
+    New {
+        text: String,
+        title: Option<String>,
+        path: Option<PathBuf>,
+    },
+    /// `quicknote://open?path=...&line=...`
+    Open { path: PathBuf, line: Option<usize> },
+    /// `quicknote://search?q=...`
+    Search { query: String },
+}
+
+impl UrlAction {
+    /// URLs that write to disk need the user's confirmation before they run
+    pub fn writes_files(&self) -> bool {
+        matches!(self, UrlAction::New { path: Some(_), .. })
+    }
+}
+
+/// Returns true if the argument looks like a quicknote:// URL rather than a file
+pub fn is_url(arg: &str) -> bool {
+    arg.len() > SCHEME.len() + 3
+        && arg
+            .get(..SCHEME.len())
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+        && arg[SCHEME.len()..].starts_with("://")
+}
+
+fn absolute_path(value: &str) -> Result<PathBuf> {
+    let path = Path::new(value);
+    if !path.is_absolute() {
+        return Err(anyhow!("Path must be absolute: {}", value));
+    }
+    Ok(path.to_path_buf())
+}
+
+/// Parses and validates a quicknote:// URL
+pub fn parse(input: &str) -> Result<UrlAction> {
+    let url = Url::parse(input).map_err(|e| anyhow!("Invalid URL: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(anyhow!("Not a {}:// URL", SCHEME));
+    }
+
+    let mut text = None;
+    let mut title = None;
+    let mut path = None;
+    let mut line = None;
+    let mut query = None;
+    for (key, value) in url.query_pairs() {
+        let value = value.into_owned();
+        match key.as_ref() {
+            "text" => text = Some(value),
+            "title" => title = Some(value),
+            "path" => path = Some(absolute_path(&value)?),
+            "line" => {
+                let number = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|line| *line > 0)
+                    .ok_or_else(|| anyhow!("line must be a positive number, got '{}'", value))?;
+                line = Some(number);
+            }
+            "q" => query = Some(value),
+            // Unknown parameters are ignored so newer links still work in older versions
+            _ => {}
+        }
+    }
+
+    // "quicknote://new?..." puts the action in the host, "quicknote:new?..." in the path
+    let action = url
+        .host_str()
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| url.path().trim_matches('/'))
+        .to_lowercase();
+    match action.as_str() {
+        "new" => {
+            let text = text.unwrap_or_default();
+            if text.len() > MAX_TEXT_BYTES {
+                return Err(anyhow!("text is longer than {} bytes", MAX_TEXT_BYTES));
+            }
+            Ok(UrlAction::New {
+                text,
+                title: title.filter(|title| !title.trim().is_empty()),
+                path,
+            })
+        }
+        "open" => Ok(UrlAction::Open {
+            path: path.ok_or_else(|| anyhow!("open needs a path"))?,
+            line,
+        }),
+        "search" => {
+            let query = query.filter(|query| !query.trim().is_empty());
+            Ok(UrlAction::Search {
+                query: query.ok_or_else(|| anyhow!("search needs q"))?,
+            })
+        }
+        other => Err(anyhow!("Unknown action: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognises_quicknote_urls() {
+        assert!(is_url("quicknote://open?path=/notes/a.md"));
+        assert!(is_url("QuickNote://search?q=x"));
+        assert!(!is_url("quicknote:"));
+        assert!(!is_url("/notes/quicknote://a.md"));
+    }
+
+    #[test]
+    fn does_not_panic_on_multibyte_arguments() {
+        // The scheme length falls inside a character
+        assert!(!is_url("/tmp/日本語のノート.md"));
+        assert!(!is_url("/notes/ünïcødé.md"));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::crypto::{self, NoteKey, VaultKey};
+use crate::storage::write_atomic;
+use anyhow::{anyhow, Result};
+use std::path::Path;
+use walkdir::WalkDir;
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+/// Creates a vault key protected by `passphrase` and writes it to `key_path`
+pub fn create(key_path: &Path, passphrase: &str) -> Result<VaultKey> {
+    if key_path.exists() {
+        return Err(anyhow!("A vault already exists"));
+    }
+    let vault = VaultKey::generate();
+    write_atomic(key_path, &vault.wrap(&NoteKey::generate(passphrase)?)?)?;
+    Ok(vault)
+}
+
+/// Reads the vault key, failing on a wrong passphrase
+pub fn unlock(key_path: &Path, passphrase: &str) -> Result<VaultKey> {
+    let wrapped = std::fs::read(key_path).map_err(|_| anyhow!("No vault has been set up"))?;
+    VaultKey::unwrap(passphrase, &wrapped).map_err(|_| anyhow!("Wrong vault passphrase"))
+}
+
+/// Re-wraps the vault key under a new passphrase; the files themselves are untouched
+pub fn change_passphrase(key_path: &Path, old: &str, new: &str) -> Result<()> {
+    let vault = unlock(key_path, old)?;
+    write_atomic(key_path, &vault.wrap(&NoteKey::generate(new)?)?)
+}
+
+/// Seals a plaintext file in place; returns false if it was already encrypted
+pub fn seal_file(path: &Path, vault: &VaultKey) -> Result<bool> {
+    let data = std::fs::read(path)?;
+    // Notes with their own passphrase are already encrypted at rest
+    if crypto::is_sealed(&data) || crypto::is_encrypted(&data) {
+        return Ok(false);
+    }
+    write_atomic(path, &crypto::seal(vault, &data)?)?;
+    Ok(true)
+}
+
+/// Seals every plaintext file under `root`, e.g. notes other programs put in the folder
+pub fn seal_tree(root: &Path, vault: &VaultKey) -> Result<usize> {
+    let mut sealed = 0;
+    for entry in WalkDir::new(root).into_iter().filter_entry(|entry| {
+        entry.depth() == 0 || !is_hidden(&entry.file_name().to_string_lossy())
+    }) {
+        let entry = entry?;
+        if entry.file_type().is_file() && seal_file(entry.path(), vault)? {
+            sealed += 1;
+        }
+    }
+    Ok(sealed)
+}
+
+/// Reads a file in the vault, accepting plaintext that has not been sealed yet
+pub fn read_file(path: &Path, vault: &VaultKey) -> Result<String> {
+    open(std::fs::read(path)?, vault)
+}
+
+/// Turns file content from the vault into text, unsealing it if needed
+pub fn open(data: Vec<u8>, vault: &VaultKey) -> Result<String> {
+    let plaintext = if crypto::is_sealed(&data) {
+        crypto::unseal(vault, &data)?.to_vec()
+    } else {
+        data
+    };
+    String::from_utf8(plaintext).map_err(|_| anyhow!("Note is not valid text"))
+}
+
+/// Writes a file in the vault sealed, so its plaintext never reaches the disk
+pub fn write_file(path: &Path, vault: &VaultKey, content: &str) -> Result<()> {
+    write_atomic(path, &crypto::seal(vault, content.as_bytes())?)
+}
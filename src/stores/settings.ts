@@ -11,6 +11,8 @@ export interface SettingsState {
   newNoteShortcut: string;
   closeTabShortcut: string;
   toggleWindowShortcut: string;
+  // Backend-only settings, kept so saving from the UI does not reset them
+  backendSettings: Record<string, unknown>;
   loadError: boolean;
 }
 
@@ -26,6 +28,7 @@ export const useSettingsStore = defineStore("settings", {
     newNoteShortcut: "CmdOrCtrl+Option+T",
     closeTabShortcut: "CmdOrCtrl+Option+Y",
     toggleWindowShortcut: "CmdOrCtrl+Option+U",
+    backendSettings: {},
     loadError: false,
   }),
 
@@ -49,9 +52,11 @@ export const useSettingsStore = defineStore("settings", {
           new_note_shortcut: string;
           close_tab_shortcut: string;
           toggle_window_shortcut?: string;
+          [key: string]: unknown;
         }>("load_settings");
 
         if (settings) {
+          this.backendSettings = settings;
           this.theme = settings.theme as Theme;
           this.language = settings.language;
           this.defaultFormat = settings.default_format;
@@ -86,6 +91,7 @@ export const useSettingsStore = defineStore("settings", {
       try {
         await invoke("save_settings", {
           settings: {
+            ...this.backendSettings,
             theme: this.theme,
             language: this.language,
             default_format: this.defaultFormat,
@@ -112,6 +118,7 @@ export const useSettingsStore = defineStore("settings", {
       try {
         await invoke("save_settings", {
           settings: {
+            ...this.backendSettings,
             theme: this.theme,
             language: this.language,
             default_format: this.defaultFormat,
@@ -19,6 +19,35 @@ export interface Tab {
   isUnsaved: boolean;
 }
 
+/**
+ * Files and text to open, given on the command line.
+ */
+export interface LaunchRequest {
+  files: string[];
+  urls: string[];
+  new_note: boolean;
+  text: string | null;
+  line: number | null;
+}
+
+/**
+ * A snippet from the snippets folder.
+ */
+export interface Snippet {
+  name: string;
+  trigger: string | null;
+  description: string | null;
+  fields: string[];
+  path: string;
+}
+
+// What the backend resolved a quicknote:// link to
+type UrlOutcome =
+  | { kind: "new_note"; title: string | null; content: string }
+  | { kind: "open"; path: string; line: number | null }
+  | { kind: "search"; query: string; hits: { path: string; line: number; text: string }[] }
+  | { kind: "cancelled" };
+
 // Helper to generate a unique ID for new tabs
 const generateId = () => `tab_${Date.now()}_${Math.random()}`;
 
@@ -55,6 +84,74 @@ function extractFileName(path: string): string {
   return path.split(/[\\/]/).pop() || path;
 }
 
+// Character offset of the start of a 1-based line, clamped to the end of the content
+function lineOffset(content: string, line: number): number {
+  let offset = 0;
+  for (let current = 1; current < line; current++) {
+    const next = content.indexOf("\n", offset);
+    if (next === -1) return content.length;
+    offset = next + 1;
+  }
+  return offset;
+}
+
+// Appends a captured entry on a fresh line below the separator; mirrors capture::append_entry
+function appendEntry(
+  existing: string,
+  entry: string,
+  separator: string | null,
+  timestamp: boolean,
+): string {
+  let content = existing;
+  if (content !== "") {
+    if (!content.endsWith("\n")) content += "\n";
+    if (separator) {
+      content += `\n${separator}\n\n`;
+    } else if (timestamp) {
+      // Keep a blank line above a timestamp header so it renders as a heading
+      content += "\n";
+    }
+  }
+  return content + entry;
+}
+
+// Ticks or unticks the task at a 1-based line, or the single line reading `raw` if it has
+// moved; mirrors tasks::edit_task and tasks::set_done. Returns null if the task is gone.
+function toggleTaskLine(
+  content: string,
+  line: number,
+  raw: string,
+  done: boolean | null,
+): string | null {
+  const lines = content.match(/[^\n]*\n|[^\n]+$/g) ?? [];
+  const textOf = (text: string) => text.replace(/[\r\n]+$/, "");
+  let index = line - 1;
+  if (index < 0 || index >= lines.length || textOf(lines[index]) !== raw) {
+    const matches = lines.flatMap((text, i) => (textOf(text) === raw ? [i] : []));
+    if (matches.length !== 1) return null;
+    index = matches[0];
+  }
+  const text = textOf(lines[index]);
+  const task = /^(\s*(?:[-*+]|\d+[.)])\s+\[)([ xX])\](.*)$/.exec(text);
+  if (!task) return null;
+  const mark = (done ?? task[2] === " ") ? "x" : " ";
+  lines[index] = `${task[1]}${mark}]${task[3]}${lines[index].slice(text.length)}`;
+  return lines.join("");
+}
+
+// Error the backend returns when an encrypted note needs its passphrase
+const PASSPHRASE_REQUIRED = "passphrase_required";
+
+// Asks for the passphrase of an encrypted note
+function promptPassphrase(path: string): string | null {
+  return window.prompt(
+    i18next.t("encryption.passphrasePrompt", { name: extractFileName(path) }),
+  );
+}
+
+// Error the backend returns when a note in the vault is opened or saved while it is locked
+const VAULT_LOCKED = "vault_locked";
+
 // Check if we're running in a web environment
 const isWeb = typeof window !== 'undefined' && !window.__TAURI__;
 
@@ -74,6 +171,7 @@ export const useTabsStore = defineStore("tabs", {
       ] as Tab[],
       activeTabId: firstTabId as string | null,
       recentlyClosed: [] as string[],
+      snippets: [] as Snippet[],
     };
   },
 
@@ -88,16 +186,20 @@ export const useTabsStore = defineStore("tabs", {
 
   actions: {
     /**
-     * Creates a new, empty tab and sets it as the active one.
+     * Creates a new tab and sets it as the active one.
+     * Optional initial content is used by templates.
      */
-    async createTab() {
+    async createTab(initial?: { name?: string; content?: string; cursorPos?: number | null }) {
+      const content = initial?.content ?? "";
       const newTab: Tab = {
         id: generateId(),
-        name: `${i18next.t("tab.untitled_prefix")}-${getNextUntitledNumber(this.tabs)}`,
+        name:
+          initial?.name ||
+          `${i18next.t("tab.untitled_prefix")}-${getNextUntitledNumber(this.tabs)}`,
         path: null,
-        content: "",
-        cursorPos: 0,
-        isUnsaved: false,
+        content,
+        cursorPos: initial?.cursorPos ?? 0,
+        isUnsaved: content.length > 0,
       };
       this.tabs.push(newTab);
       this.activeTabId = newTab.id;
@@ -216,6 +318,74 @@ export const useTabsStore = defineStore("tabs", {
       }
     },
 
+    /**
+     * Inserts text at the cursor of the active tab, or in a new tab if none is open.
+     * The cursor ends up at `cursor` within the text, or after it.
+     */
+    async insertText(text: string, cursor?: number | null) {
+      const activeTab = this.activeTab;
+      if (!activeTab) {
+        await this.createTab({ content: text, cursorPos: cursor ?? text.length });
+        return;
+      }
+      const end = activeTab.content.length;
+      const position = Math.min(activeTab.cursorPos ?? end, end);
+      activeTab.content =
+        activeTab.content.slice(0, position) + text + activeTab.content.slice(position);
+      activeTab.cursorPos = position + (cursor ?? text.length);
+      activeTab.isUnsaved = true;
+    },
+
+    // === Snippets ===
+
+    async loadSnippets() {
+      try {
+        this.snippets = await invoke<Snippet[]>("list_snippets");
+      } catch (error) {
+        console.error("Failed to load snippets:", error);
+      }
+    },
+
+    /**
+     * Finds the snippet whose trigger was typed right before the cursor.
+     */
+    snippetForTrigger(textBeforeCursor: string): Snippet | undefined {
+      return this.snippets.find((snippet) => {
+        if (!snippet.trigger || !textBeforeCursor.endsWith(snippet.trigger)) return false;
+        const before = textBeforeCursor.slice(0, -snippet.trigger.length);
+        return before === "" || /\s$/.test(before);
+      });
+    },
+
+    /**
+     * Asks for the snippet's fields and expands it; null if the user cancelled.
+     */
+    async expandSnippet(name: string, fields: string[]) {
+      const values: Record<string, string> = {};
+      for (const field of fields) {
+        const value = window.prompt(i18next.t("snippets.fieldPrompt", { field }));
+        if (value === null) return null;
+        values[field] = value;
+      }
+      try {
+        return await invoke<{ content: string; cursor: number | null }>("expand_snippet", {
+          name,
+          fields: values,
+        });
+      } catch (error) {
+        console.error("Failed to expand snippet:", error);
+        window.alert(String(error));
+        return null;
+      }
+    },
+
+    async insertSnippet(name: string, fields: string[]) {
+      const expanded = await this.expandSnippet(name, fields);
+      if (expanded) {
+        await this.insertText(expanded.content, expanded.cursor);
+      }
+    },
+
     // === File operations using backend commands ===
     async openFileFromDialog() {
       if (isWeb) {
@@ -254,7 +424,7 @@ export const useTabsStore = defineStore("tabs", {
       }
     },
 
-    async openSpecificFile(path: string) {
+    async openSpecificFile(path: string, line?: number | null) {
       if (isWeb) {
         // In web environment, show a message that file operations are not available
         alert("File operations are not available in the web version. Please download the desktop app for full functionality.");
@@ -265,22 +435,38 @@ export const useTabsStore = defineStore("tabs", {
         // 如果已經有這個檔案的分頁，直接切換
         const existingTab = this.tabs.find((tab) => tab.path === path);
         if (existingTab) {
+          if (line) existingTab.cursorPos = lineOffset(existingTab.content, line);
           this.selectTab(existingTab.id);
           await appWindow.show();
           await appWindow.setFocus();
           return;
         }
-        const result = await invoke<{ path: string; content: string } | null>(
-          "open_specific_file",
-          { path },
-        );
+        let result: { path: string; content: string } | null;
+        try {
+          result = await invoke<{ path: string; content: string } | null>(
+            "open_specific_file",
+            { path },
+          );
+        } catch (error) {
+          if (error === VAULT_LOCKED) {
+            if (!(await this.unlockVault())) return;
+            return this.openSpecificFile(path, line);
+          }
+          if (error !== PASSPHRASE_REQUIRED) throw error;
+          const passphrase = promptPassphrase(path);
+          if (passphrase === null) return;
+          result = await invoke<{ path: string; content: string }>(
+            "open_encrypted_note",
+            { path, passphrase },
+          );
+        }
         if (result) {
           const newTab: Tab = {
             id: generateId(),
             name: extractFileName(result.path),
             path: result.path,
             content: result.content,
-            cursorPos: null,
+            cursorPos: line ? lineOffset(result.content, line) : null,
             isUnsaved: false,
           };
           this.tabs.push(newTab);
@@ -311,10 +497,40 @@ export const useTabsStore = defineStore("tabs", {
       }
 
       try {
-        await invoke("save_file", {
-          path: activeTab.path,
-          content: activeTab.content,
-        });
+        const save = () =>
+          invoke<{
+            status: "saved" | "merged" | "conflict";
+            content: string;
+            conflicts: unknown[];
+          }>("save_file", {
+            path: activeTab.path,
+            content: activeTab.content,
+          });
+        let result;
+        try {
+          result = await save();
+        } catch (error) {
+          if (error === VAULT_LOCKED) {
+            if (!(await this.unlockVault())) return false;
+            return this.saveActiveFile();
+          }
+          // The key of an encrypted note expired; unlock it again and retry
+          if (error !== PASSPHRASE_REQUIRED || !activeTab.path) throw error;
+          const passphrase = promptPassphrase(activeTab.path);
+          if (passphrase === null) return false;
+          await invoke("open_encrypted_note", { path: activeTab.path, passphrase });
+          result = await save();
+        }
+        if (result.status === "conflict") {
+          // Show the conflict markers so the user can resolve them and save again
+          activeTab.content = result.content;
+          activeTab.isUnsaved = true;
+          console.warn("Save conflicts with external changes:", result.conflicts);
+          return false;
+        }
+        if (result.status === "merged") {
+          activeTab.content = result.content;
+        }
         // Mark as saved
         activeTab.isUnsaved = false;
         return true;
@@ -366,7 +582,115 @@ export const useTabsStore = defineStore("tabs", {
       }
     },
 
+    /**
+     * Saves the active tab as an encrypted note, asking for a new passphrase twice.
+     */
+    async encryptActiveFile(): Promise<boolean> {
+      const activeTab = this.tabs.find((tab) => tab.id === this.activeTabId);
+      if (!activeTab || isWeb) return false;
+      // The note needs a location before it can be encrypted in place
+      if (!activeTab.path && !(await this.saveActiveFileAs())) return false;
+      const path = activeTab.path as string;
+
+      const passphrase = window.prompt(
+        i18next.t("encryption.newPassphrasePrompt", { name: extractFileName(path) }),
+      );
+      if (!passphrase) return false;
+      if (window.prompt(i18next.t("encryption.confirmPassphrasePrompt")) !== passphrase) {
+        alert(i18next.t("encryption.passphraseMismatch"));
+        return false;
+      }
+
+      try {
+        await invoke("encrypt_note", { path, content: activeTab.content, passphrase });
+        activeTab.isUnsaved = false;
+        return true;
+      } catch (error) {
+        console.error("Failed to encrypt note:", error);
+        return false;
+      }
+    },
+
+    /**
+     * Exports the active tab (e.g. to standalone HTML) through a save dialog.
+     */
+    async exportActiveTab(format: string): Promise<string | null> {
+      const activeTab = this.tabs.find((tab) => tab.id === this.activeTabId);
+      if (!activeTab || isWeb) return null;
+
+      try {
+        return await invoke<string | null>("export_note", {
+          path: activeTab.path,
+          name: activeTab.name,
+          content: activeTab.content,
+          format,
+        });
+      } catch (error) {
+        console.error("Failed to export note:", error);
+        return null;
+      }
+    },
+
     // === Menu event listeners ===
+    // Asks for the vault passphrase; returns true once the vault is unlocked
+    async unlockVault(): Promise<boolean> {
+      const passphrase = window.prompt(i18next.t("vault.passphrasePrompt"));
+      if (passphrase === null) return false;
+      try {
+        await invoke("unlock_vault", { passphrase });
+        return true;
+      } catch (error) {
+        console.error("Failed to unlock vault:", error);
+        window.alert(String(error));
+        return false;
+      }
+    },
+
+    // Opens what was asked for on the command line
+    async handleLaunchRequest(request: LaunchRequest) {
+      for (const path of request.files) {
+        await this.openSpecificFile(path, request.line);
+      }
+      for (const url of request.urls ?? []) {
+        await this.openUrl(url);
+      }
+      if (request.text !== null) {
+        await this.createTab({ content: request.text });
+      }
+      if (request.new_note) {
+        await this.createTab();
+      }
+    },
+
+    // Carries out a quicknote:// link
+    async openUrl(url: string) {
+      let outcome: UrlOutcome;
+      try {
+        outcome = await invoke<UrlOutcome>("open_url", { url });
+      } catch (error) {
+        console.error("Failed to open link:", error);
+        window.alert(String(error));
+        return;
+      }
+      switch (outcome.kind) {
+        case "new_note":
+          await this.createTab({ name: outcome.title ?? undefined, content: outcome.content });
+          break;
+        case "open":
+          await this.openSpecificFile(outcome.path, outcome.line);
+          break;
+        case "search": {
+          // Results open as a plain-text tab, one "path:line: text" per hit
+          const lines = outcome.hits.map((hit) => `${hit.path}:${hit.line}: ${hit.text}`);
+          await this.createTab({
+            name: i18next.t("search.resultsTitle", { query: outcome.query }),
+            content: lines.length > 0 ? lines.join("\n") : i18next.t("search.noResults"),
+          });
+          break;
+        }
+      }
+    },
+
     setup_menu_listeners() {
       if (isWeb) {
         // Skip menu listeners in web environment
@@ -391,6 +715,186 @@ export const useTabsStore = defineStore("tabs", {
         this.openSpecificFile(path);
       });
 
+      // Listen for new-note-from-template event from backend (tray templates submenu)
+      listen("new-note-from-template", (event) => {
+        const note = event.payload as { title: string; content: string; cursor: number | null };
+        this.createTab({ name: note.title, content: note.content, cursorPos: note.cursor });
+      });
+
+      // Text to insert at the cursor, e.g. a clipboard history entry
+      listen("insert-text", (event) => {
+        const { text } = event.payload as { text: string };
+        this.insertText(text);
+      });
+
+      // A snippet picked from the tray; the list is reloaded since snippet files may have changed
+      listen("insert-snippet", (event) => {
+        const { name, fields } = event.payload as { name: string; fields: string[] };
+        this.insertSnippet(name, fields);
+        this.loadSnippets();
+      });
+
+      // Snippet triggers typed in the editor are matched against this list
+      this.loadSnippets();
+
+      // Text to open in a new tab, e.g. a clipboard history entry
+      listen("new-note", (event) => {
+        const note = event.payload as { title: string | null; content: string };
+        this.createTab({ name: note.title ?? undefined, content: note.content });
+      });
+
+      // Local API requests that need the open tabs; every request gets a reply
+      listen("api-request", async (event) => {
+        const request = event.payload as { id: number; kind: string; args: any };
+        let result: unknown = null;
+        switch (request.kind) {
+          case "create_note":
+            await this.createTab({
+              name: request.args.title ?? undefined,
+              content: request.args.content,
+            });
+            result = { tab_id: this.activeTabId };
+            break;
+          case "list_tabs":
+            result = this.tabs.map((tab) => ({
+              id: tab.id,
+              name: tab.name,
+              path: tab.path,
+              unsaved: tab.isUnsaved,
+            }));
+            break;
+          case "get_tab": {
+            const tab = this.tabs.find((t) => t.id === request.args.id);
+            result = tab
+              ? { id: tab.id, name: tab.name, path: tab.path, content: tab.content }
+              : null;
+            break;
+          }
+          case "tab_contents":
+            result = this.tabs.map((tab) => ({
+              id: tab.id,
+              path: tab.path,
+              content: tab.content,
+              unsaved: tab.isUnsaved,
+            }));
+            break;
+        }
+        await invoke("api_reply", { id: request.id, result });
+      });
+
+      // A file was changed in the background, e.g. appended to or a task ticked off; refresh
+      // its tabs unless they have unsaved edits, which pick up the change by merging on save
+      const refreshSavedTabs = (event: { payload: unknown }) => {
+        const { path, content } = event.payload as { path: string; content: string };
+        for (const tab of this.tabs.filter((t) => t.path === path && !t.isUnsaved)) {
+          tab.content = content;
+        }
+      };
+      listen("note-appended", refreshSavedTabs);
+      listen("note-changed", refreshSavedTabs);
+
+      // A task fell due; the system notification is already shown, so only ask while the
+      // window is on screen
+      listen("reminder-due", async (event) => {
+        const reminder = event.payload as { path: string; line: number; raw: string; text: string };
+        if (!(await appWindow.isVisible())) return;
+        const done = await ask(reminder.text, {
+          title: i18next.t("reminders.dueTitle"),
+          okLabel: i18next.t("reminders.complete"),
+          cancelLabel: i18next.t("reminders.snooze"),
+        });
+        const command = done ? "complete_reminder" : "snooze_reminder";
+        try {
+          await invoke(command, { path: reminder.path, line: reminder.line, raw: reminder.raw });
+        } catch (error) {
+          console.error("Failed to update reminder:", error);
+          window.alert(String(error));
+        }
+      });
+
+      // A task in an unsaved tab was ticked off from the task list; edit the text as it is
+      // now, so typing since the request is kept
+      listen("toggle-tab-task", (event) => {
+        const { id, line, raw, done } = event.payload as {
+          id: string;
+          line: number;
+          raw: string;
+          done: boolean | null;
+        };
+        const tab = this.tabs.find((t) => t.id === id);
+        const content = tab ? toggleTaskLine(tab.content, line, raw, done) : null;
+        if (tab && content !== null) {
+          tab.content = content;
+          tab.isUnsaved = true;
+        }
+      });
+
+      // A fragment was captured into an open tab; append it to the text as it is now, so
+      // typing since the capture started is kept
+      listen("append-to-tab", (event) => {
+        const { id, entry, separator, timestamp } = event.payload as {
+          id: string;
+          entry: string;
+          separator: string | null;
+          timestamp: boolean;
+        };
+        const tab = this.tabs.find((t) => t.id === id);
+        if (tab) {
+          tab.content = appendEntry(tab.content, entry, separator, timestamp);
+          tab.isUnsaved = true;
+        }
+      });
+
+      // Files and text forwarded from a second launch of the app
+      listen("launch-request", (event) => {
+        this.handleLaunchRequest(event.payload as LaunchRequest);
+      });
+
+      // Listen for open-file-path event from backend (e.g. daily notes)
+      listen("open-file-path", (event) => {
+        const path = event.payload as string;
+        this.openSpecificFile(path);
+      });
+
+      // Encrypted files picked in the open dialog need their passphrase first
+      listen("encrypted-file", (event) => {
+        this.openSpecificFile(event.payload as string);
+      });
+
+      // An encrypted note was locked; close its saved tabs so no plaintext stays on screen
+      listen("note-locked", (event) => {
+        const path = event.payload as string;
+        for (const tab of this.tabs.filter((t) => t.path === path && !t.isUnsaved)) {
+          this.closeTab(tab.id);
+        }
+      });
+
+      // The window was shown while the vault is locked
+      listen("vault-unlock-required", () => {
+        this.unlockVault();
+      });
+
+      // The vault was locked; close saved tabs from the notes directory
+      listen("vault-locked", (event) => {
+        const root = event.payload as string;
+        for (const tab of this.tabs.filter((t) => t.path?.startsWith(root) && !t.isUnsaved)) {
+          this.closeTab(tab.id);
+        }
+      });
+
+      // Tell the clipboard watcher which note is active so copies from encrypted notes are skipped
+      watch(
+        () => this.activeTab?.path ?? null,
+        async (path) => {
+          try {
+            await invoke("set_clipboard_focus", { path });
+          } catch (error) {
+            console.error("Failed to update clipboard focus:", error);
+          }
+        },
+        { immediate: true },
+      );
+
       // Watch for changes in recentlyClosed array and update tray menu
       watch(
         () => this.recentlyClosed,